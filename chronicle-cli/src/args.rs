@@ -1,7 +1,7 @@
 use std::{fmt::Display, path::PathBuf};
 
 use chronicle::{
-    author::AuthorQuery,
+    author::{AuthorCredit, AuthorQuery, AuthorWorks},
     record::RecordDetails,
     search::Query,
     tag::{DiscriminatedTag, TagExpression, TagPart},
@@ -49,6 +49,11 @@ pub enum Command {
         #[command(subcommand)]
         command: ServiceCommand,
     },
+    /// Import works from exported archives
+    Import {
+        #[command(subcommand)]
+        command: ImportCommand,
+    },
     /// Bulk operations
     Bulk {
         /// The number of tasks to use when importing
@@ -57,6 +62,16 @@ pub enum Command {
         #[command(subcommand)]
         command: BulkCommand,
     },
+    /// Rules which automatically tag works
+    Rule {
+        #[command(subcommand)]
+        command: RuleCommand,
+    },
+    /// Database maintenance
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
     /// Generate shell completions
     Completions {
         /// The shell to generate completions for, if omitted attempts to infer it from the current
@@ -78,7 +93,18 @@ pub enum TagCommand {
         /// This should take the form of
         ///
         /// [<search query>/]tag1/(tag2,tag3)/tag4
+        ///
+        /// Tags may be prefixed with a category, such as `character:marina`. Within the search
+        /// query, categories named like a search term such as `artist` must be written
+        /// `tag:artist:name`
         expression: TagExpression,
+        /// Print the changes which would be made without making them
+        #[arg(long)]
+        dry_run: bool,
+        /// What to do when a discriminated tag is used while an undiscriminated tag with the same
+        /// name exists
+        #[arg(long, value_enum, default_value_t = ConflictPolicy::Prompt)]
+        on_conflict: ConflictPolicy,
     },
     /// Get information on individual tags
     Info {
@@ -92,6 +118,197 @@ pub enum TagCommand {
         /// The discriminator to apply to the tag
         discriminator: TagPart,
     },
+    /// Change the discriminator of a tag
+    Rediscriminate {
+        /// The tag to change
+        tag: DiscriminatedTag,
+        /// The new discriminator
+        discriminator: TagPart,
+        /// Merge the tag into the existing tag with the new discriminator, if there is one
+        #[arg(long)]
+        merge: bool,
+    },
+    /// Remove the discriminator of a tag once it is the only tag with its name
+    Undiscriminate {
+        /// The tag to change
+        tag: DiscriminatedTag,
+        /// Merge every other tag with the same name into this tag
+        #[arg(long)]
+        merge: bool,
+    },
+    /// List every tag sharing a name, regardless of discriminator
+    Family {
+        /// The tag name
+        name: TagPart,
+    },
+    /// Set the description of a tag
+    Describe {
+        /// The tag to describe
+        tag: DiscriminatedTag,
+        /// The description to set, if omitted the description is edited in $EDITOR
+        description: Option<String>,
+    },
+    /// Search for tags by name, alias or description
+    Search {
+        /// The text to search for
+        text: String,
+    },
+    /// List tags along with how many works use them
+    List {
+        /// Only list tags whose name starts with this prefix
+        #[arg(short, long)]
+        prefix: Option<String>,
+        /// The order to list tags in
+        #[arg(short, long, value_enum, default_value_t = TagSort::Name)]
+        sort: TagSort,
+        /// Reverse the sorting order
+        #[arg(short, long)]
+        reverse: bool,
+        /// The format to output the tags in
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Remove tags which have no works, no implications and are not used by any rule or mapping
+    Prune {
+        /// List the tags which would be removed without removing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Export the tag implication graph
+    Graph {
+        /// Only export the tags implying this tag
+        root: Option<DiscriminatedTag>,
+        /// The format to export the graph in
+        #[arg(short, long, value_enum, default_value_t = GraphFormat::Dot)]
+        format: GraphFormat,
+        /// The maximum depth to export
+        #[arg(short, long)]
+        depth: Option<u32>,
+        /// The file to write the graph to, if omitted the graph is written to stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Add an alias which resolves to an existing tag
+    Alias {
+        /// The tag to alias
+        tag: DiscriminatedTag,
+        /// The alias to assign to the tag
+        alias: DiscriminatedTag,
+    },
+    /// Set how a tag from a service is translated when importing
+    ///
+    /// Tags without a mapping are imported as is
+    Map {
+        /// The service the tag comes from, such as `tumblr`
+        service: String,
+        /// The tag as written on the service
+        source: String,
+        /// The tag to apply instead
+        #[arg(required_unless_present_any = ["ignore", "pass"])]
+        tag: Option<DiscriminatedTag>,
+        /// Do not import this tag
+        #[arg(long, conflicts_with_all = ["tag", "pass"])]
+        ignore: bool,
+        /// Import this tag as is
+        #[arg(long, conflicts_with = "tag")]
+        pass: bool,
+    },
+    /// Remove the mapping for a tag from a service
+    Unmap {
+        /// The service the tag comes from
+        service: String,
+        /// The tag as written on the service
+        source: String,
+    },
+    /// List tag mappings
+    Mappings {
+        /// Only list mappings for this service
+        service: Option<String>,
+    },
+    /// Remove an implication between two tags
+    Untag {
+        /// The more specific tag
+        tag: DiscriminatedTag,
+        /// The tag which should no longer be implied
+        parent: DiscriminatedTag,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RuleCommand {
+    /// Add a rule which applies tags to every work matching a query, including future imports
+    Add {
+        /// The search query works must match
+        query: String,
+        /// The tags to apply to matching works
+        #[arg(required = true)]
+        tags: Vec<DiscriminatedTag>,
+    },
+    /// List all rules
+    List,
+    /// Remove a rule
+    Remove {
+        /// The id of the rule
+        id: i64,
+    },
+    /// Apply every rule to all existing works
+    Run,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DbCommand {
+    /// Recompute the tag closure table from the tag implications
+    RebuildClosure,
+    /// Rewrite stored work and author urls into their canonical form, merging authors which end
+    /// up sharing a url
+    CanonicalizeUrls {
+        /// Print the changes which would be made without making them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ImportCommand {
+    /// Import the media of your tweets and likes from a Twitter archive zip
+    TwitterArchive {
+        /// The path to the archive
+        path: PathBuf,
+        /// Import liked tweets whose media is not in the archive from Twitter
+        #[arg(long)]
+        fetch_likes: bool,
+        /// What to do when an author matches several existing authors, or an id or url matches
+        /// none
+        #[arg(long, value_enum, default_value_t = AuthorResolution::Prompt)]
+        author_policy: AuthorResolution,
+    },
+    /// Import the images of your posts from a Tumblr blog export zip
+    TumblrExport {
+        /// The path to the export
+        path: PathBuf,
+        /// The name of the exported blog, as in `<blog>.tumblr.com`
+        #[arg(short, long)]
+        blog: String,
+        /// What to do when an author matches several existing authors, or an id or url matches
+        /// none
+        #[arg(long, value_enum, default_value_t = AuthorResolution::Prompt)]
+        author_policy: AuthorResolution,
+    },
+    /// Import the images of your posts from a Bluesky repository CAR file
+    BskyRepo {
+        /// The path to the repository
+        path: PathBuf,
+        /// A folder of blobs named by their cid to read images from instead of downloading them
+        #[arg(long)]
+        blobs: Option<PathBuf>,
+        /// The handle of the account, looked up from its DID when not given
+        #[arg(long)]
+        handle: Option<String>,
+        /// What to do when an author matches several existing authors, or an id or url matches
+        /// none
+        #[arg(long, value_enum, default_value_t = AuthorResolution::Prompt)]
+        author_policy: AuthorResolution,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -102,6 +319,10 @@ pub enum BulkCommand {
         path: PathBuf,
         #[command(flatten)]
         details: BulkWorkDetails,
+        /// What to do when an author matches several existing authors, or an id or url matches
+        /// none, prompting is not supported
+        #[arg(long, value_enum, default_value_t = AuthorResolution::Fail)]
+        author_policy: AuthorResolution,
     },
     /// Import a list of paths as works
     Add {
@@ -109,6 +330,10 @@ pub enum BulkCommand {
         path: PathBuf,
         #[command(flatten)]
         details: BulkWorkDetails,
+        /// What to do when an author matches several existing authors, or an id or url matches
+        /// none, prompting is not supported
+        #[arg(long, value_enum, default_value_t = AuthorResolution::Fail)]
+        author_policy: AuthorResolution,
     },
     /// Performs a series of tag operations
     Tag {
@@ -116,6 +341,15 @@ pub enum BulkCommand {
         ///
         /// The format of each line of this file should be the same as the input for `chronicle tag`
         path: PathBuf,
+        /// Print the changes each line would make without making them
+        ///
+        /// Each line is planned independently of the others
+        #[arg(long)]
+        dry_run: bool,
+        /// What to do when a discriminated tag is used while an undiscriminated tag with the same
+        /// name exists, prompting is not supported
+        #[arg(long, value_enum, default_value_t = ConflictPolicy::Fail)]
+        on_conflict: ConflictPolicy,
     },
 }
 
@@ -127,6 +361,10 @@ pub enum WorkCommand {
         path: PathBuf,
         #[command(flatten)]
         details: WorkDetails,
+        /// What to do when an author matches several existing authors, or an id or url matches
+        /// none
+        #[arg(long, value_enum, default_value_t = AuthorResolution::Prompt)]
+        author_policy: AuthorResolution,
     },
     /// Chronicle a link as work(s)
     Import {
@@ -134,12 +372,19 @@ pub enum WorkCommand {
         source: Url,
         #[command(flatten)]
         details: WorkDetails,
+        /// What to do when an author matches several existing authors, or an id or url matches
+        /// none
+        #[arg(long, value_enum, default_value_t = AuthorResolution::Prompt)]
+        author_policy: AuthorResolution,
     },
     /// Search and display works
     Search {
         #[command(flatten)]
         display_options: WorkDisplayOptions,
         /// The query to search for, if omitted the query is read from stdin
+        ///
+        /// Tags in categories named like a search term, such as `artist`, must be written
+        /// `tag:artist:name` since `artist:name` searches authors
         query: Option<Query>,
     },
     /// List all works chronicled
@@ -147,6 +392,14 @@ pub enum WorkCommand {
         #[command(flatten)]
         display_options: WorkDisplayOptions,
     },
+    /// Suggest tags for a work based on similar works and interactively apply them
+    SuggestTags {
+        /// The id of the work
+        id: i64,
+        /// The maximum number of suggestions to show
+        #[arg(short, long, default_value_t = 10)]
+        limit: u32,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -174,6 +427,63 @@ pub enum AuthorCommand {
         /// The url to assign to the author
         url: Url,
     },
+    /// Show the names, urls, tags and recent works of an author
+    Info {
+        /// The author to show
+        ///
+        /// Can be a name, id, or url
+        query: AuthorQuery,
+        /// The number of recent works to show
+        #[arg(short, long, default_value_t = 5)]
+        recent: usize,
+        #[command(flatten)]
+        display_options: WorkDisplayOptions,
+    },
+    /// Remove a name from an author
+    Unalias {
+        /// The author to remove the name from
+        ///
+        /// Can be a name, id, or url
+        query: AuthorQuery,
+        /// The name to remove
+        alias: String,
+    },
+    /// Remove a url from an author
+    RemoveUrl {
+        /// The author to remove the url from
+        ///
+        /// Can be a name, id, or url
+        query: AuthorQuery,
+        /// The url to remove
+        url: Url,
+    },
+    /// Delete an author
+    Delete {
+        /// The author to delete
+        ///
+        /// Can be a name, id, or url
+        query: AuthorQuery,
+        /// What to do with the author's works, if omitted you will be asked
+        #[arg(short, long, value_enum)]
+        works: Option<AuthorWorksAction>,
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Merge one author into another, moving all of their works, names and urls
+    Merge {
+        /// The author to merge and remove
+        ///
+        /// Can be a name, id, or url
+        source: AuthorQuery,
+        /// The author to merge into
+        ///
+        /// Can be a name, id, or url
+        target: AuthorQuery,
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -201,11 +511,74 @@ pub struct AuthorDisplayOptions {
     pub columns: Vec<AuthorColumn>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AuthorResolution {
+    /// Ask which author to credit
+    Prompt,
+    /// Abort without making any changes
+    Fail,
+    /// Credit the only matching author which already has the imported author url
+    PickByUrl,
+    /// Create a new author
+    Create,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConflictPolicy {
+    /// Ask for a discriminator for the existing tag
+    Prompt,
+    /// Abort without making any changes
+    Fail,
+    /// Discriminate the existing tag with the lowest unused number
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum TagSort {
+    Name,
+    Works,
+    Implied,
+    Parents,
+    Children,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum AuthorWorksAction {
+    /// Keep the works, only removing the author's credits
+    Orphan,
+    /// Delete the works only this author is credited on and their files
+    Delete,
+}
+
+impl From<AuthorWorksAction> for AuthorWorks {
+    fn from(action: AuthorWorksAction) -> Self {
+        match action {
+            AuthorWorksAction::Orphan => AuthorWorks::Orphan,
+            AuthorWorksAction::Delete => AuthorWorks::Delete,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum)]
 pub enum AuthorColumn {
     Id,
     Aliases,
     Urls,
+    Works,
+    Roles,
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -215,9 +588,11 @@ pub enum WorkColumn {
     Hash,
     Title,
     AuthorId,
+    Authors,
     Caption,
     Url,
     Size,
+    Tags,
 }
 
 impl Display for WorkColumn {
@@ -228,9 +603,11 @@ impl Display for WorkColumn {
             WorkColumn::Hash => write!(f, "HASH"),
             WorkColumn::Title => write!(f, "TITLE"),
             WorkColumn::AuthorId => write!(f, "AUTHOR_ID"),
+            WorkColumn::Authors => write!(f, "AUTHORS"),
             WorkColumn::Caption => write!(f, "CAPTION"),
             WorkColumn::Url => write!(f, "URL"),
             WorkColumn::Size => write!(f, "SIZE"),
+            WorkColumn::Tags => write!(f, "TAGS"),
         }
     }
 }
@@ -241,6 +618,8 @@ impl Display for AuthorColumn {
             AuthorColumn::Id => write!(f, "ID"),
             AuthorColumn::Aliases => write!(f, "ALIASES"),
             AuthorColumn::Urls => write!(f, "URLS"),
+            AuthorColumn::Works => write!(f, "WORKS"),
+            AuthorColumn::Roles => write!(f, "ROLES"),
         }
     }
 }
@@ -252,9 +631,11 @@ pub struct WorkDetails {
     /// The title of the work
     #[arg(short, long)]
     pub title: Option<String>,
-    /// The original author of the work
-    #[arg(short, long)]
-    pub author: Option<AuthorQuery>,
+    /// The authors of the work, may be repeated
+    ///
+    /// Prefix with a role to credit the author as something else, such as `commissioner=name`
+    #[arg(short, long = "author")]
+    pub authors: Vec<AuthorCredit>,
     /// The url to associate with the work
     #[arg(short, long)]
     pub url: Option<Url>,
@@ -270,9 +651,11 @@ pub struct BulkWorkDetails {
     /// The title of the works
     #[arg(short, long)]
     pub title: Option<String>,
-    /// The original author of the works
-    #[arg(short, long)]
-    pub author: Option<AuthorQuery>,
+    /// The authors of the works, may be repeated
+    ///
+    /// Prefix with a role to credit the author as something else, such as `commissioner=name`
+    #[arg(short, long = "author")]
+    pub authors: Vec<AuthorCredit>,
     /// The caption associated with the works
     #[arg(short, long)]
     pub caption: Option<String>,
@@ -283,7 +666,7 @@ impl From<WorkDetails> for RecordDetails {
         WorkDetails {
             tags,
             title,
-            author,
+            authors,
             url,
             caption,
         }: WorkDetails,
@@ -291,10 +674,10 @@ impl From<WorkDetails> for RecordDetails {
         Self {
             tags,
             title,
-            author,
+            authors,
             url,
             caption,
-            author_url: None,
+            native_tags: Vec::new(),
         }
     }
 }
@@ -304,17 +687,17 @@ impl From<BulkWorkDetails> for RecordDetails {
         BulkWorkDetails {
             tags,
             title,
-            author,
+            authors,
             caption,
         }: BulkWorkDetails,
     ) -> Self {
         Self {
             title,
             url: None,
-            author,
+            authors,
             caption,
             tags,
-            author_url: None,
+            native_tags: Vec::new(),
         }
     }
 }
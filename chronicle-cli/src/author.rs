@@ -1,17 +1,26 @@
-use std::process::ExitCode;
+use std::{fs, process::ExitCode, sync::Arc};
 
 use chronicle::{
-    author::{self, AuthorQuery},
+    author::{
+        self, AuthorCandidate, AuthorChoice, AuthorCredit, AuthorPolicy, AuthorQuery, AuthorWorks,
+    },
     models::{Author, Work},
 };
 use console::style;
-use dialoguer::Select;
+use dialoguer::{Confirm, Select};
+use indicatif::ProgressBar;
+use itertools::Itertools;
+use sqlx::{Sqlite, Transaction};
 use url::Url;
 
 use crate::{
-    args::{AuthorColumn, AuthorCommand, AuthorDisplayOptions},
+    args::{
+        AuthorColumn, AuthorCommand, AuthorDisplayOptions, AuthorResolution, WorkDisplayOptions,
+    },
     get_chronicle,
     table::Table,
+    tag::style_tag,
+    work::print_works,
     write_failure, write_success, TERMINAL,
 };
 
@@ -20,6 +29,21 @@ pub async fn author_command(command: &AuthorCommand) -> anyhow::Result<ExitCode>
         AuthorCommand::List { display_options } => list_authors(display_options).await,
         AuthorCommand::Alias { query, alias } => alias_author(query, alias).await,
         AuthorCommand::AddUrl { query, url } => add_url_author(query, url).await,
+        AuthorCommand::Info {
+            query,
+            recent,
+            display_options,
+        } => author_info(query, *recent, display_options).await,
+        AuthorCommand::Unalias { query, alias } => unalias_author(query, alias).await,
+        AuthorCommand::RemoveUrl { query, url } => remove_url_author(query, url).await,
+        AuthorCommand::Delete { query, works, yes } => {
+            delete_author(query, works.map(AuthorWorks::from), *yes).await
+        }
+        AuthorCommand::Merge {
+            source,
+            target,
+            yes,
+        } => merge_authors(source, target, *yes).await,
     }
 }
 
@@ -28,7 +52,7 @@ async fn add_url_author(query: &AuthorQuery, url: &Url) -> anyhow::Result<ExitCo
     let mut tx = chronicle.begin().await?;
     let author = &Author::get(&mut tx, query).await?[0];
 
-    author.add_url(&mut tx, url).await?;
+    let url = author.add_url(&mut tx, url).await?.url;
 
     tx.commit().await?;
 
@@ -37,28 +61,372 @@ async fn add_url_author(query: &AuthorQuery, url: &Url) -> anyhow::Result<ExitCo
     Ok(ExitCode::SUCCESS)
 }
 
-async fn alias_author(query: &AuthorQuery, alias: &str) -> anyhow::Result<ExitCode> {
+/// Resolves `query` to a single author, prompting the user to pick one if it is ambiguous.
+pub async fn select_author(
+    tx: &mut Transaction<'_, Sqlite>,
+    query: &AuthorQuery,
+    prompt: &str,
+) -> anyhow::Result<Option<Author>> {
+    let mut authors = Author::get(tx, query).await?;
+
+    if authors.len() <= 1 {
+        return Ok(authors.pop());
+    }
+
+    let mut items = Vec::with_capacity(authors.len());
+
+    for author in &authors {
+        items.push(author_label(tx, author).await?);
+    }
+
+    let index = Select::new().items(&items).with_prompt(prompt).interact()?;
+
+    Ok(Some(authors.swap_remove(index)))
+}
+
+/// Builds the library policy for resolving author credits, `bar` is hidden while prompting.
+pub fn author_policy(resolution: AuthorResolution, bar: Option<ProgressBar>) -> AuthorPolicy {
+    match resolution {
+        AuthorResolution::Fail => AuthorPolicy::Error,
+        AuthorResolution::PickByUrl => AuthorPolicy::PickByUrl,
+        AuthorResolution::Create => AuthorPolicy::Create,
+        AuthorResolution::Prompt => {
+            AuthorPolicy::Callback(Arc::new(move |credit, candidates| match &bar {
+                Some(bar) => bar.suspend(|| prompt_author(credit, candidates)),
+                None => prompt_author(credit, candidates),
+            }))
+        }
+    }
+}
+
+fn prompt_author(
+    credit: &AuthorCredit,
+    candidates: &[AuthorCandidate],
+) -> Result<AuthorChoice, chronicle::Error> {
+    let mut items: Vec<String> = candidates
+        .iter()
+        .map(|candidate| {
+            format!(
+                "{} ({}) {}",
+                candidate.author.author_id,
+                candidate.names.join(", "),
+                style(candidate.urls.join(", ")).dim()
+            )
+        })
+        .collect();
+
+    items.push(String::from("Create a new author"));
+    items.push(String::from("Leave them uncredited"));
+
+    let prompt = if candidates.is_empty() {
+        format!("No author matches {}", credit.query)
+    } else {
+        format!("Select the author to credit for {}", credit.query)
+    };
+
+    let index = Select::new()
+        .with_prompt(prompt)
+        .items(&items)
+        .default(0)
+        .interact()
+        .map_err(|err| chronicle::Error::Generic(err.to_string()))?;
+
+    Ok(match candidates.get(index) {
+        Some(candidate) => AuthorChoice::Existing(candidate.author.author_id),
+        None if index == candidates.len() => AuthorChoice::Create,
+        None => AuthorChoice::Skip,
+    })
+}
+
+/// Formats an author as its id followed by its names.
+pub async fn author_label(
+    tx: &mut Transaction<'_, Sqlite>,
+    author: &Author,
+) -> anyhow::Result<String> {
+    let names = author.get_author_names(tx).await?;
+
+    Ok(format!(
+        "{} ({})",
+        author.author_id,
+        names.iter().map(|name| name.name.as_str()).join(", ")
+    ))
+}
+
+async fn author_info(
+    query: &AuthorQuery,
+    recent: usize,
+    display_options: &WorkDisplayOptions,
+) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+    let mut tx = chronicle.begin().await?;
+
+    let Some(author) = select_author(&mut tx, query, "Select the author to show").await? else {
+        write_failure("Failed to find author")?;
+        return Ok(ExitCode::FAILURE);
+    };
+
+    let names = author.get_author_names(&mut tx).await?;
+    let urls = author.get_author_urls(&mut tx).await?;
+    let mut works = author.works(&mut tx).await?;
+    let tags = author.top_tags(&mut tx, 10).await?;
+    let roles = author.roles(&mut tx).await?;
+
+    TERMINAL.write_line(&format!("{} {}", style("Id:").bold(), author.author_id))?;
+    TERMINAL.write_line(&format!(
+        "{} {}",
+        style("Names:").bold(),
+        names.iter().map(|name| name.name.as_str()).join(", ")
+    ))?;
+    TERMINAL.write_line(&format!(
+        "{} {}",
+        style("Urls:").bold(),
+        urls.iter().map(|url| url.url.as_str()).join(", ")
+    ))?;
+    TERMINAL.write_line(&format!("{} {}", style("Works:").bold(), works.len()))?;
+    TERMINAL.write_line(&format!(
+        "{} {}",
+        style("Roles:").bold(),
+        roles
+            .iter()
+            .map(|role| format!("{} ({})", role.role, role.works))
+            .join(", ")
+    ))?;
+    TERMINAL.write_line(&format!(
+        "{} {}",
+        style("Tags:").bold(),
+        tags.iter()
+            .map(|tag| format!("{} ({})", style_tag(&tag.tag), tag.works))
+            .join(", ")
+    ))?;
+
+    if !works.is_empty() && recent > 0 {
+        works.sort_by_key(|work| std::cmp::Reverse(work.work_id.0));
+        works.truncate(recent);
+
+        TERMINAL.write_line("")?;
+
+        print_works(&mut tx, &works, display_options).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn unalias_author(query: &AuthorQuery, alias: &str) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+    let mut tx = chronicle.begin().await?;
+
+    let Some(author) =
+        select_author(&mut tx, query, "Select the author to remove the name from").await?
+    else {
+        write_failure("Failed to find author")?;
+        return Ok(ExitCode::FAILURE);
+    };
+
+    if author.get_author_names(&mut tx).await?.len() <= 1 {
+        write_failure(&format!(
+            "Failed {alias} is the only name of {}",
+            author.author_id
+        ))?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    if !author.remove_alias(&mut tx, alias).await? {
+        write_failure(&format!("Failed {} has no name {alias}", author.author_id))?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    tx.commit().await?;
+
+    write_success(&format!("Removed name {alias} from {}", author.author_id))?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn remove_url_author(query: &AuthorQuery, url: &Url) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+    let mut tx = chronicle.begin().await?;
+
+    let Some(author) =
+        select_author(&mut tx, query, "Select the author to remove the url from").await?
+    else {
+        write_failure("Failed to find author")?;
+        return Ok(ExitCode::FAILURE);
+    };
+
+    if !author.remove_url(&mut tx, url).await? {
+        write_failure(&format!("Failed {} has no url {url}", author.author_id))?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    tx.commit().await?;
+
+    write_success(&format!("Removed url {url} from {}", author.author_id))?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn delete_author(
+    query: &AuthorQuery,
+    works: Option<AuthorWorks>,
+    yes: bool,
+) -> anyhow::Result<ExitCode> {
     let chronicle = get_chronicle().await;
     let mut tx = chronicle.begin().await?;
-    let authors = &Author::get(&mut tx, query).await?;
 
-    let author = match authors.len() {
-        0 => {
-            write_failure("Failed to find author")?;
-            return Ok(ExitCode::FAILURE);
+    let Some(author) = select_author(&mut tx, query, "Select the author to delete").await? else {
+        write_failure("Failed to find author")?;
+        return Ok(ExitCode::FAILURE);
+    };
+
+    let count = author.works(&mut tx).await?.len();
+
+    let works = match works {
+        Some(works) => works,
+        None if count == 0 => AuthorWorks::Orphan,
+        None => {
+            let choice = Select::new()
+                .with_prompt(format!(
+                    "{} has {count} works, what should happen to them?",
+                    author_label(&mut tx, &author).await?
+                ))
+                .items(&[
+                    "Keep them, only removing the credits",
+                    "Delete the ones no one else is credited on",
+                ])
+                .default(0)
+                .interact()?;
+
+            if choice == 0 {
+                AuthorWorks::Orphan
+            } else {
+                AuthorWorks::Delete
+            }
         }
-        1 => &authors[0],
-        _ => {
-            &authors[Select::new()
-                .items(
-                    &authors
-                        .iter()
-                        .map(|author| author.author_id.to_string())
-                        .collect::<Vec<_>>(),
-                )
-                .with_prompt("Select the author id you wish to alias")
-                .interact()?]
+    };
+
+    if !yes
+        && !Confirm::new()
+            .with_prompt(format!(
+                "Delete {}{}?",
+                author_label(&mut tx, &author).await?,
+                if works == AuthorWorks::Delete {
+                    String::from(" and the works no one else is credited on")
+                } else {
+                    String::new()
+                }
+            ))
+            .default(false)
+            .interact()?
+    {
+        write_failure("Aborted deletion")?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let deleted = author.delete(&mut tx, works).await?;
+
+    tx.commit().await?;
+
+    for work in &deleted {
+        let path = chronicle.config.data_path.join(&work.path);
+
+        if let Err(err) = fs::remove_file(&path) {
+            write_failure(&format!("Failed removing {}: {err}", path.display()))?;
         }
+    }
+
+    match works {
+        AuthorWorks::Orphan => write_success(&format!(
+            "Deleted {} and their credits on {count} works",
+            author.author_id
+        ))?,
+        AuthorWorks::Delete => write_success(&format!(
+            "Deleted {} and {} works",
+            author.author_id,
+            deleted.len()
+        ))?,
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn merge_authors(
+    source: &AuthorQuery,
+    target: &AuthorQuery,
+    yes: bool,
+) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+    let mut tx = chronicle.begin().await?;
+
+    let Some(source) = select_author(&mut tx, source, "Select the author to merge from").await?
+    else {
+        write_failure("Failed to find the author to merge from")?;
+        return Ok(ExitCode::FAILURE);
+    };
+
+    let Some(target) = select_author(&mut tx, target, "Select the author to merge into").await?
+    else {
+        write_failure("Failed to find the author to merge into")?;
+        return Ok(ExitCode::FAILURE);
+    };
+
+    if source.author_id == target.author_id {
+        write_failure("Failed both queries refer to the same author")?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let works = source.works(&mut tx).await?;
+    let names = source.get_author_names(&mut tx).await?;
+    let urls = source.get_author_urls(&mut tx).await?;
+
+    TERMINAL.write_line(&format!(
+        "Merging {} into {}",
+        author_label(&mut tx, &source).await?,
+        author_label(&mut tx, &target).await?
+    ))?;
+    TERMINAL.write_line(&format!("{} {}", style("Works:").bold(), works.len()))?;
+    TERMINAL.write_line(&format!(
+        "{} {}",
+        style("Names:").bold(),
+        names.iter().map(|name| name.name.as_str()).join(", ")
+    ))?;
+    TERMINAL.write_line(&format!(
+        "{} {}",
+        style("Urls:").bold(),
+        urls.iter().map(|url| url.url.as_str()).join(", ")
+    ))?;
+
+    if !yes
+        && !Confirm::new()
+            .with_prompt("Merge these authors?")
+            .default(false)
+            .interact()?
+    {
+        write_failure("Aborted merge")?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    source.merge(&mut tx, &target).await?;
+
+    tx.commit().await?;
+
+    write_success(&format!(
+        "Merged {} into {}",
+        source.author_id, target.author_id
+    ))?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn alias_author(query: &AuthorQuery, alias: &str) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+    let mut tx = chronicle.begin().await?;
+    let Some(author) =
+        select_author(&mut tx, query, "Select the author id you wish to alias").await?
+    else {
+        write_failure("Failed to find author")?;
+        return Ok(ExitCode::FAILURE);
     };
 
     author.add_alias(&mut tx, alias).await?;
@@ -101,8 +469,10 @@ async fn list_authors(options: &AuthorDisplayOptions) -> anyhow::Result<ExitCode
     for author in authors {
         let aliases = author.get_author_names(&mut tx).await?;
         let urls = author.get_author_urls(&mut tx).await?;
+        let roles = author.roles(&mut tx).await?;
+        let works = author.works(&mut tx).await?.len();
 
-        for i in 0..usize::max(aliases.len(), urls.len()).max(1) {
+        for i in 0..aliases.len().max(urls.len()).max(roles.len()).max(1) {
             for column in options.columns.iter() {
                 match column {
                     AuthorColumn::Id => {
@@ -126,6 +496,20 @@ async fn list_authors(options: &AuthorDisplayOptions) -> anyhow::Result<ExitCode
                             table.push_left("")?;
                         }
                     }
+                    AuthorColumn::Works => {
+                        if i == 0 {
+                            table.push_left(works)?;
+                        } else {
+                            table.push_left("")?;
+                        }
+                    }
+                    AuthorColumn::Roles => {
+                        if let Some(role) = roles.get(i) {
+                            table.push_left(format!("{} ({})", role.role, role.works))?;
+                        } else {
+                            table.push_left("")?;
+                        }
+                    }
                 }
             }
         }
@@ -9,6 +9,7 @@ use std::{
 };
 
 use chronicle::{
+    author::AuthorPolicy,
     models::Work,
     record::{Record, RecordDetails},
     tag::TagExpression,
@@ -20,8 +21,11 @@ use url::Url;
 use uuid::Uuid;
 
 use crate::{
-    args::{BulkCommand, WorkDetails},
-    get_chronicle, write_failure, write_success, ERROR_STYLE, PREFIX_STYLE, SPINNER_STYLE,
+    args::{AuthorResolution, BulkCommand, ConflictPolicy, WorkDetails},
+    author::author_policy,
+    get_chronicle,
+    tag::{format_plan, resolve_discriminator_conflicts},
+    write_failure, write_success, ERROR_STYLE, PREFIX_STYLE, SPINNER_STYLE,
 };
 
 pub async fn bulk_operation<
@@ -115,7 +119,16 @@ struct BulkImportCommand {
 
 pub async fn bulk(tasks: usize, command: &BulkCommand) -> anyhow::Result<ExitCode> {
     match command {
-        BulkCommand::Import { path, details } => {
+        BulkCommand::Import {
+            path,
+            details,
+            author_policy: resolution,
+        } => {
+            if *resolution == AuthorResolution::Prompt {
+                write_failure("Failed prompting for authors is not supported in bulk")?;
+                return Ok(ExitCode::FAILURE);
+            }
+
             let reader = BufReader::new(File::open(&path)?);
 
             let urls = reader.lines();
@@ -124,8 +137,10 @@ pub async fn bulk(tasks: usize, command: &BulkCommand) -> anyhow::Result<ExitCod
 
             let works = bulk_operation(
                 urls.flatten().collect(),
-                record_details,
-                |bar: ProgressBar, line: String, details: RecordDetails| async move {
+                (record_details, author_policy(*resolution, None)),
+                |bar: ProgressBar,
+                 line: String,
+                 (details, policy): (RecordDetails, AuthorPolicy)| async move {
                     let chronicle = get_chronicle().await;
 
                     let Ok(mut tx) = chronicle.begin().await else {
@@ -144,6 +159,7 @@ pub async fn bulk(tasks: usize, command: &BulkCommand) -> anyhow::Result<ExitCod
                                 &mut tx,
                                 &url,
                                 Some(&details),
+                                &policy,
                             )
                             .await
                             {
@@ -189,7 +205,16 @@ pub async fn bulk(tasks: usize, command: &BulkCommand) -> anyhow::Result<ExitCod
 
             write_success(&format!("Imported {} works", works.len()))?;
         }
-        BulkCommand::Add { path, details } => {
+        BulkCommand::Add {
+            path,
+            details,
+            author_policy: resolution,
+        } => {
+            if *resolution == AuthorResolution::Prompt {
+                write_failure("Failed prompting for authors is not supported in bulk")?;
+                return Ok(ExitCode::FAILURE);
+            }
+
             let reader = BufReader::new(File::open(&path)?);
 
             let paths = reader.lines();
@@ -198,8 +223,10 @@ pub async fn bulk(tasks: usize, command: &BulkCommand) -> anyhow::Result<ExitCod
 
             let works = bulk_operation(
                 paths.flatten().collect(),
-                record_details,
-                |bar: ProgressBar, path: String, details: RecordDetails| async move {
+                (record_details, author_policy(*resolution, None)),
+                |bar: ProgressBar,
+                 path: String,
+                 (details, policy): (RecordDetails, AuthorPolicy)| async move {
                     let chronicle = get_chronicle().await;
 
                     let original_path = PathBuf::from(path);
@@ -248,7 +275,7 @@ pub async fn bulk(tasks: usize, command: &BulkCommand) -> anyhow::Result<ExitCod
                         return None;
                     };
 
-                    let work = match Work::create_from_record(&mut tx, &record).await {
+                    let work = match Work::create_from_record(&mut tx, &record, &policy).await {
                         Ok(work) => Some(work),
                         Err(err) => {
                             bar.println(
@@ -280,15 +307,26 @@ pub async fn bulk(tasks: usize, command: &BulkCommand) -> anyhow::Result<ExitCod
 
             write_success(&format!("Added {} works", works.len(),))?;
         }
-        BulkCommand::Tag { path } => {
+        BulkCommand::Tag {
+            path,
+            dry_run,
+            on_conflict,
+        } => {
+            if *on_conflict == ConflictPolicy::Prompt {
+                write_failure("Failed prompting for discriminators is not supported in bulk")?;
+                return Ok(ExitCode::FAILURE);
+            }
+
             let reader = BufReader::new(File::open(&path)?);
 
             let paths = reader.lines();
 
             let tagged = bulk_operation(
                 paths.flatten().collect(),
-                (),
-                |bar: ProgressBar, tag_expression: String, _: ()| async move {
+                (*dry_run, *on_conflict),
+                |bar: ProgressBar,
+                 tag_expression: String,
+                 (dry_run, on_conflict): (bool, ConflictPolicy)| async move {
                     let expression = match TagExpression::from_str(&tag_expression) {
                         Ok(expression) => expression,
                         Err(err) => {
@@ -311,6 +349,59 @@ pub async fn bulk(tasks: usize, command: &BulkCommand) -> anyhow::Result<ExitCod
                         return 0;
                     };
 
+                    if dry_run {
+                        return match expression.plan(&mut tx).await {
+                            Ok(plan) => {
+                                bar.println(format!("{}", PREFIX_STYLE.apply_to(&tag_expression)));
+
+                                for line in format_plan(&plan) {
+                                    bar.println(format!("  {line}"));
+                                }
+
+                                plan.new_connections()
+                            }
+                            Err(err) => {
+                                bar.println(
+                                    ERROR_STYLE
+                                        .apply_to(format!(
+                                            "Could not plan '{tag_expression}': {err}"
+                                        ))
+                                        .to_string(),
+                                );
+
+                                0
+                            }
+                        };
+                    }
+
+                    match resolve_discriminator_conflicts(&mut tx, &expression, on_conflict)
+                        .await
+                    {
+                        Ok(None) => {}
+                        Ok(Some(other)) => {
+                            bar.println(
+                                ERROR_STYLE
+                                    .apply_to(format!(
+                                        "Could not execute '{tag_expression}': tag '{other}' already exists and is not discriminated"
+                                    ))
+                                    .to_string(),
+                            );
+
+                            return 0;
+                        }
+                        Err(err) => {
+                            bar.println(
+                                ERROR_STYLE
+                                    .apply_to(format!(
+                                        "Could not execute '{tag_expression}': {err}"
+                                    ))
+                                    .to_string(),
+                            );
+
+                            return 0;
+                        }
+                    }
+
                     let total = match expression.execute(&mut tx).await {
                         Ok(total) => total,
                         Err(err) => {
@@ -337,13 +428,19 @@ pub async fn bulk(tasks: usize, command: &BulkCommand) -> anyhow::Result<ExitCod
                     total
                 },
                 "Tagging",
-                tasks,
+                // plans are printed as they complete, so keep them in order and avoid
+                // contending for the write lock
+                if *dry_run { 1 } else { tasks },
             )
             .await?
             .into_iter()
             .sum::<usize>();
 
-            write_success(&format!("Tagged {tagged} new connections"))?;
+            if *dry_run {
+                write_success(&format!("Would tag {tagged} new connections"))?;
+            } else {
+                write_success(&format!("Tagged {tagged} new connections"))?;
+            }
         }
     }
 
@@ -0,0 +1,62 @@
+use std::process::ExitCode;
+
+use chronicle::{canonical::canonicalize_stored_urls, models::Tag};
+
+use crate::{args::DbCommand, author::author_label, get_chronicle, write_success, TERMINAL};
+
+pub async fn db_command(command: &DbCommand) -> anyhow::Result<ExitCode> {
+    match command {
+        DbCommand::RebuildClosure => rebuild_closure().await,
+        DbCommand::CanonicalizeUrls { dry_run } => canonicalize_urls(*dry_run).await,
+    }
+}
+
+pub async fn rebuild_closure() -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    Tag::rebuild_closure(&mut tx).await?;
+
+    tx.commit().await?;
+
+    write_success("Rebuilt tag closure")?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub async fn canonicalize_urls(dry_run: bool) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    let report = canonicalize_stored_urls(&mut tx).await?;
+
+    for merge in &report.merges {
+        TERMINAL.write_line(&format!(
+            "Merged author {} into {} sharing {}",
+            merge.source.author_id,
+            author_label(&mut tx, &merge.target).await?,
+            merge.url
+        ))?;
+    }
+
+    let summary = format!(
+        "{} work urls, {} author urls and {} author merges",
+        report.work_urls,
+        report.author_urls,
+        report.merges.len()
+    );
+
+    if dry_run {
+        tx.rollback().await?;
+
+        write_success(&format!("Would canonicalize {summary}"))?;
+    } else {
+        tx.commit().await?;
+
+        write_success(&format!("Canonicalized {summary}"))?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,352 @@
+use std::{path::Path, process::ExitCode, time::Duration};
+
+use chronicle::{
+    archive::{
+        self,
+        bsky::{BskyEntry, BskyExport, BskyIdentity, Repository},
+        tumblr::TumblrExport,
+        twitter::TwitterArchive,
+        Archive, ArchiveEntry,
+    },
+    author::AuthorPolicy,
+    models::Work,
+    Chronicle,
+};
+use console::style;
+use indicatif::ProgressBar;
+use tokio::sync::OnceCell;
+use url::Url;
+
+use crate::{
+    args::{AuthorResolution, ImportCommand},
+    author::author_policy,
+    get_chronicle, write_failure, write_success, ERROR_STYLE, PREFIX_STYLE, PROGRESS_STYLE,
+};
+
+pub async fn import_command(command: &ImportCommand) -> anyhow::Result<ExitCode> {
+    match command {
+        ImportCommand::TwitterArchive {
+            path,
+            fetch_likes,
+            author_policy,
+        } => import_twitter_archive(path, *fetch_likes, *author_policy).await,
+        ImportCommand::TumblrExport {
+            path,
+            blog,
+            author_policy,
+        } => import_tumblr_export(path, blog, *author_policy).await,
+        ImportCommand::BskyRepo {
+            path,
+            blobs,
+            handle,
+            author_policy,
+        } => import_bsky_repo(path, blobs.as_deref(), handle.as_deref(), *author_policy).await,
+    }
+}
+
+/// What happened to the works found in an archive.
+#[derive(Default)]
+struct ImportSummary {
+    imported: usize,
+    skipped: usize,
+    failed: usize,
+}
+
+impl ImportSummary {
+    fn report(&self) -> anyhow::Result<ExitCode> {
+        write_success(&format!(
+            "Imported {} {}, skipped {} already chronicled",
+            style(self.imported).bold(),
+            if self.imported == 1 { "work" } else { "works" },
+            style(self.skipped).bold(),
+        ))?;
+
+        if self.failed > 0 {
+            write_failure(&format!("Failed importing {} works", self.failed))?;
+            return Ok(ExitCode::FAILURE);
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Removes entries of posts which were imported before, returning how many were removed.
+async fn retain_new(
+    chronicle: &Chronicle,
+    entries: &mut Vec<ArchiveEntry>,
+) -> anyhow::Result<usize> {
+    let mut tx = chronicle.begin().await?;
+
+    let removed = archive::retain_new(&mut tx, entries, |entry| &entry.details).await?;
+
+    tx.rollback().await?;
+
+    Ok(removed)
+}
+
+fn progress_bar(len: usize) -> ProgressBar {
+    let bar = ProgressBar::new(len as u64).with_style(PROGRESS_STYLE.clone());
+
+    bar.set_prefix(PREFIX_STYLE.apply_to("Importing").to_string());
+    bar.enable_steady_tick(Duration::from_millis(100));
+
+    bar
+}
+
+/// Imports every entry of an archive, each in its own transaction so one failure does not lose
+/// the rest.
+async fn import_entries(
+    chronicle: &Chronicle,
+    archive: &mut Archive,
+    service: &str,
+    entries: &[ArchiveEntry],
+    policy: &AuthorPolicy,
+    bar: &ProgressBar,
+    summary: &mut ImportSummary,
+) -> anyhow::Result<()> {
+    for entry in entries {
+        bar.set_message(entry.name.clone());
+
+        let mut tx = chronicle.begin().await?;
+
+        match archive
+            .import_entry(chronicle, &mut tx, service, entry, policy)
+            .await
+        {
+            Ok(Some(_)) => {
+                tx.commit().await?;
+                summary.imported += 1;
+            }
+            Ok(None) => summary.skipped += 1,
+            Err(err) => {
+                bar.println(
+                    ERROR_STYLE
+                        .apply_to(format!("Unable to import '{}': {err}", entry.name))
+                        .to_string(),
+                );
+                summary.failed += 1;
+            }
+        }
+
+        bar.inc(1);
+    }
+
+    Ok(())
+}
+
+/// Imports works from urls which have not been imported before.
+async fn import_urls(
+    chronicle: &Chronicle,
+    urls: &[Url],
+    policy: &AuthorPolicy,
+    bar: &ProgressBar,
+    summary: &mut ImportSummary,
+) -> anyhow::Result<()> {
+    for url in urls {
+        bar.set_message(url.to_string());
+
+        let mut tx = chronicle.begin().await?;
+
+        if !Work::get_by_url(&mut tx, url).await?.is_empty() {
+            summary.skipped += 1;
+        } else {
+            match Work::import_works_from_url(chronicle, &mut tx, url, None, policy).await {
+                Ok(works) => {
+                    tx.commit().await?;
+                    summary.imported += works.len();
+                }
+                Err(err) => {
+                    bar.println(
+                        ERROR_STYLE
+                            .apply_to(format!("Unable to import '{url}': {err}"))
+                            .to_string(),
+                    );
+                    summary.failed += 1;
+                }
+            }
+        }
+
+        bar.inc(1);
+    }
+
+    Ok(())
+}
+
+pub async fn import_twitter_archive(
+    path: &Path,
+    fetch_likes: bool,
+    resolution: AuthorResolution,
+) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut archive = Archive::open(path)?;
+
+    let TwitterArchive { mut entries, liked } = TwitterArchive::read(&mut archive)?;
+
+    let liked = if fetch_likes { liked } else { Vec::new() };
+
+    let mut summary = ImportSummary {
+        skipped: retain_new(chronicle, &mut entries).await?,
+        ..Default::default()
+    };
+
+    let bar = progress_bar(entries.len() + liked.len());
+    let policy = author_policy(resolution, Some(bar.clone()));
+
+    import_entries(
+        chronicle,
+        &mut archive,
+        "twitter",
+        &entries,
+        &policy,
+        &bar,
+        &mut summary,
+    )
+    .await?;
+
+    import_urls(chronicle, &liked, &policy, &bar, &mut summary).await?;
+
+    bar.finish_and_clear();
+
+    summary.report()
+}
+
+pub async fn import_tumblr_export(
+    path: &Path,
+    blog: &str,
+    resolution: AuthorResolution,
+) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let TumblrExport {
+        mut archive,
+        mut entries,
+    } = TumblrExport::read(Archive::open(path)?, blog)?;
+
+    let mut summary = ImportSummary {
+        skipped: retain_new(chronicle, &mut entries).await?,
+        ..Default::default()
+    };
+
+    let bar = progress_bar(entries.len());
+    let policy = author_policy(resolution, Some(bar.clone()));
+
+    import_entries(
+        chronicle,
+        &mut archive,
+        "tumblr",
+        &entries,
+        &policy,
+        &bar,
+        &mut summary,
+    )
+    .await?;
+
+    bar.finish_and_clear();
+
+    summary.report()
+}
+
+/// Imports the image of a post, reading it from the blobs folder if it is there and otherwise
+/// downloading it from the account's server, which is only looked up the first time it is needed.
+///
+/// Returns whether a work was created.
+async fn import_blob(
+    chronicle: &Chronicle,
+    did: &str,
+    identity: &OnceCell<BskyIdentity>,
+    blobs: Option<&Path>,
+    entry: &BskyEntry,
+    policy: &AuthorPolicy,
+) -> anyhow::Result<bool> {
+    let stored = blobs
+        .map(|blobs| blobs.join(entry.blob.to_string()))
+        .filter(|path| path.exists());
+
+    let data = match stored {
+        Some(path) => std::fs::read(path)?,
+        None => {
+            identity
+                .get_or_try_init(|| BskyIdentity::resolve(did))
+                .await?
+                .fetch_blob(&entry.blob)
+                .await?
+        }
+    };
+
+    let mut tx = chronicle.begin().await?;
+
+    let work = archive::import_data(
+        chronicle,
+        &mut tx,
+        "bsky",
+        &data,
+        &entry.extension,
+        &entry.details,
+        policy,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(work.is_some())
+}
+
+pub async fn import_bsky_repo(
+    path: &Path,
+    blobs: Option<&Path>,
+    handle: Option<&str>,
+    resolution: AuthorResolution,
+) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let repository = Repository::parse(&std::fs::read(path)?)?;
+    let identity = OnceCell::new();
+
+    let handle = match handle {
+        Some(handle) => handle,
+        None => {
+            &identity
+                .get_or_try_init(|| BskyIdentity::resolve(&repository.did))
+                .await?
+                .handle
+        }
+    };
+
+    let BskyExport { mut entries } = BskyExport::read(&repository, handle)?;
+
+    let mut tx = chronicle.begin().await?;
+    let skipped = archive::retain_new(&mut tx, &mut entries, |entry| &entry.details).await?;
+    tx.rollback().await?;
+
+    let mut summary = ImportSummary {
+        skipped,
+        ..Default::default()
+    };
+
+    let bar = progress_bar(entries.len());
+    let policy = author_policy(resolution, Some(bar.clone()));
+
+    for entry in &entries {
+        bar.set_message(entry.blob.to_string());
+
+        match import_blob(chronicle, &repository.did, &identity, blobs, entry, &policy).await {
+            Ok(true) => summary.imported += 1,
+            Ok(false) => summary.skipped += 1,
+            Err(err) => {
+                bar.println(
+                    ERROR_STYLE
+                        .apply_to(format!("Unable to import '{}': {err}", entry.blob))
+                        .to_string(),
+                );
+                summary.failed += 1;
+            }
+        }
+
+        bar.inc(1);
+    }
+
+    bar.finish_and_clear();
+
+    summary.report()
+}
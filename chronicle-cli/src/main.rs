@@ -1,7 +1,10 @@
 mod args;
 mod author;
 mod bulk;
+mod db;
+mod import;
 mod logging;
+mod rule;
 mod table;
 mod tag;
 mod utils;
@@ -23,11 +26,14 @@ use chronicle::{
 use clap::{CommandFactory, Parser};
 use clap_complete::{generate, Shell};
 use console::{Style, Term};
+use db::db_command;
 use dialoguer::{Password, Select};
 use directories::ProjectDirs;
+use import::import_command;
 use indicatif::ProgressStyle;
 use lazy_static::lazy_static;
 use logging::initialize_logging;
+use rule::rule_command;
 use tag::tag_command;
 use tokio::sync::OnceCell;
 use tracing::error;
@@ -144,7 +150,10 @@ async fn fallible() -> anyhow::Result<ExitCode> {
                 Ok(ExitCode::SUCCESS)
             }
         },
+        Command::Import { command } => import_command(command).await,
         Command::Bulk { command, tasks } => bulk::bulk(*tasks, command).await,
+        Command::Rule { command } => rule_command(command).await,
+        Command::Db { command } => db_command(command).await,
         Command::Completions { shell, output } => {
             let Some(shell) = shell.or_else(|| Shell::from_env()) else {
                 write_failure("Failed detecting shell from environment, please provide a shell")?;
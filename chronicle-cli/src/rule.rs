@@ -0,0 +1,124 @@
+use std::process::ExitCode;
+
+use chronicle::{
+    models::{Rule, RuleId},
+    tag::DiscriminatedTag,
+};
+use console::style;
+
+use crate::{
+    args::RuleCommand,
+    get_chronicle,
+    table::{ColumnBehavior, Table},
+    tag::format_tags,
+    write_failure, write_success, TERMINAL,
+};
+
+pub async fn rule_command(command: &RuleCommand) -> anyhow::Result<ExitCode> {
+    match command {
+        RuleCommand::Add { query, tags } => add_rule(query, tags).await,
+        RuleCommand::List => list_rules().await,
+        RuleCommand::Remove { id } => remove_rule(RuleId(*id)).await,
+        RuleCommand::Run => run_rules().await,
+    }
+}
+
+pub async fn add_rule(query: &str, tags: &[DiscriminatedTag]) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    let rule = match Rule::create(&mut tx, query, tags).await {
+        Ok(rule) => rule,
+        Err(err) => {
+            write_failure(&format!("Failed {err}"))?;
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+
+    tx.commit().await?;
+
+    write_success(&format!("Added rule {}", rule.rule_id))?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub async fn list_rules() -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    let mut rules = Vec::new();
+
+    for rule in Rule::get_all(&mut tx).await? {
+        let tags = rule.tags(&mut tx).await?;
+        rules.push((rule, tags));
+    }
+
+    tx.commit().await?;
+
+    let mut table = Table::new(
+        &TERMINAL,
+        vec![
+            ColumnBehavior {
+                size: 6,
+                grow: false,
+                min_size: 3,
+            },
+            ColumnBehavior {
+                size: 32,
+                grow: true,
+                min_size: 12,
+            },
+            ColumnBehavior {
+                size: 32,
+                grow: true,
+                min_size: 12,
+            },
+        ],
+        TERMINAL.size().1.into(),
+    );
+
+    table.push_left(style("ID").bold())?;
+    table.push_left(style("QUERY").bold())?;
+    table.push_left(style("TAGS").bold())?;
+
+    for (rule, tags) in &rules {
+        table.push_left(rule.rule_id)?;
+        table.push_left(&rule.query)?;
+        table.push_left(format_tags(tags))?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub async fn remove_rule(id: RuleId) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    if !Rule::remove(&mut tx, id).await? {
+        write_failure(&format!("Failure finding rule {id}"))?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    tx.commit().await?;
+
+    write_success(&format!("Removed rule {id}"))?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub async fn run_rules() -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    let total = Rule::run_all(&mut tx).await?;
+
+    tx.commit().await?;
+
+    write_success(&format!("Tagged {total} connections"))?;
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -38,10 +38,15 @@ impl WorkColumn {
                 min_size: 8,
             },
             WorkColumn::AuthorId => ColumnBehavior {
-                size: 8,
+                size: 12,
                 grow: false,
                 min_size: 8,
             },
+            WorkColumn::Authors => ColumnBehavior {
+                size: 32,
+                grow: true,
+                min_size: 12,
+            },
             WorkColumn::Caption => ColumnBehavior {
                 size: 42,
                 grow: true,
@@ -57,6 +62,11 @@ impl WorkColumn {
                 grow: false,
                 min_size: 12,
             },
+            WorkColumn::Tags => ColumnBehavior {
+                size: 32,
+                grow: true,
+                min_size: 12,
+            },
         }
     }
 }
@@ -79,6 +89,16 @@ impl AuthorColumn {
                 grow: true,
                 min_size: 24,
             },
+            AuthorColumn::Works => ColumnBehavior {
+                size: 8,
+                grow: false,
+                min_size: 8,
+            },
+            AuthorColumn::Roles => ColumnBehavior {
+                size: 24,
+                grow: true,
+                min_size: 12,
+            },
         }
     }
 }
@@ -1,21 +1,23 @@
 use std::{
-    iter,
+    fs, iter,
+    path::Path,
     process::{ExitCode, ExitStatus},
     time::Duration,
 };
 
-use anyhow::Ok;
 use chronicle::{
-    models::{Tag, Work},
-    tag::{DiscriminatedTag, TagExpression, TagPart},
+    import::{Service, FALLBACK_SERVICE, SERVICES},
+    models::{MappingAction, Tag, TagMapping, Work},
+    tag::{graph::TagGraph, DiscriminatedTag, TagCount, TagExpression, TagPart, TagPlan},
 };
-use console::style;
-use dialoguer::{Input, Select};
+use console::{style, Style};
+use dialoguer::{Editor, Input, Select};
 use indicatif::ProgressBar;
 use itertools::Itertools;
+use sqlx::{Sqlite, Transaction};
 
 use crate::{
-    args::TagCommand,
+    args::{ConflictPolicy, GraphFormat, OutputFormat, TagCommand, TagSort},
     get_chronicle,
     table::{ColumnBehavior, Table},
     write_failure, write_success, PREFIX_STYLE, SPINNER_STYLE, TERMINAL,
@@ -23,72 +25,669 @@ use crate::{
 
 pub async fn tag_command(command: &TagCommand) -> anyhow::Result<ExitCode> {
     match command {
-        TagCommand::Apply { expression } => execute_tag_expression(expression).await,
+        TagCommand::Apply {
+            expression,
+            dry_run,
+            on_conflict,
+        } => {
+            if *dry_run {
+                plan_tag_expression(expression, *on_conflict).await
+            } else {
+                execute_tag_expression(expression, *on_conflict).await
+            }
+        }
+        TagCommand::Rediscriminate {
+            tag,
+            discriminator,
+            merge,
+        } => rediscriminate_tag(tag, Some(&discriminator.0), *merge).await,
+        TagCommand::Undiscriminate { tag, merge } => rediscriminate_tag(tag, None, *merge).await,
+        TagCommand::Family { name } => tag_family(&name.0).await,
         TagCommand::Info { tag } => display_tag_info(tag).await,
         TagCommand::Discriminate { tag, discriminator } => {
             discriminate_tag(tag, discriminator).await
         }
+        TagCommand::Alias { tag, alias } => alias_tag(tag, alias).await,
+        TagCommand::Untag { tag, parent } => untag_tag(tag, parent).await,
+        TagCommand::Map {
+            service,
+            source,
+            tag,
+            ignore,
+            pass: _,
+        } => {
+            let action = match (tag, ignore) {
+                (Some(_), _) => MappingAction::Map,
+                (None, true) => MappingAction::Ignore,
+                (None, false) => MappingAction::Pass,
+            };
+
+            map_tag(service, source, action, tag.as_ref()).await
+        }
+        TagCommand::Unmap { service, source } => unmap_tag(service, source).await,
+        TagCommand::Mappings { service } => list_mappings(service.as_deref()).await,
+        TagCommand::Describe { tag, description } => {
+            describe_tag(tag, description.as_deref()).await
+        }
+        TagCommand::Search { text } => search_tags(text).await,
+        TagCommand::List {
+            prefix,
+            sort,
+            reverse,
+            format,
+        } => list_tags(prefix.as_deref(), *sort, *reverse, *format).await,
+        TagCommand::Prune { dry_run } => prune_tags(*dry_run).await,
+        TagCommand::Graph {
+            root,
+            format,
+            depth,
+            output,
+        } => export_tag_graph(root.as_ref(), *format, *depth, output.as_deref()).await,
     }
 }
 
-pub async fn discriminate_tag(
-    tag_name: &TagPart,
-    discriminator: &TagPart,
+pub async fn export_tag_graph(
+    root: Option<&DiscriminatedTag>,
+    format: GraphFormat,
+    depth: Option<u32>,
+    output: Option<&Path>,
 ) -> anyhow::Result<ExitCode> {
     let chronicle = get_chronicle().await;
 
     let mut tx = chronicle.begin().await?;
 
-    let Some(mut tag) = Tag::try_get_discriminated(&mut tx, &tag_name.0, None).await? else {
-        write_failure(&format!("Failure finding {tag_name}"))?;
+    let root = match root {
+        Some(root) => {
+            let Some(tag) = select_tag(&mut tx, root, "Select which tag to export").await? else {
+                write_failure(&format!("Failure finding {root}"))?;
+                return Ok(ExitCode::FAILURE);
+            };
+
+            Some(tag)
+        }
+        None => None,
+    };
+
+    let graph = TagGraph::load(&mut tx, root.as_ref(), depth).await?;
+
+    tx.commit().await?;
+
+    let rendered = match format {
+        GraphFormat::Dot => graph.to_dot(),
+        GraphFormat::Mermaid => graph.to_mermaid(),
+        GraphFormat::Json => serde_json::to_string_pretty(&graph)?,
+    };
+
+    match output {
+        Some(output) => {
+            fs::write(output, rendered)?;
+
+            write_success(&format!(
+                "Exported {} tags to {}",
+                graph.nodes.len(),
+                output.to_string_lossy()
+            ))?;
+        }
+        None => TERMINAL.write_line(rendered.trim_end())?,
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Prints a table of tags along with their counts.
+pub fn print_tag_counts(tags: &[TagCount]) -> anyhow::Result<()> {
+    let max_length = tags
+        .iter()
+        .map(|t| t.tag.to_string().len())
+        .max()
+        .unwrap_or_default()
+        .max(3)
+        + 1;
+
+    let count_column = || ColumnBehavior {
+        size: 8,
+        grow: false,
+        min_size: 8,
+    };
+
+    let mut table = Table::new(
+        &TERMINAL,
+        vec![
+            ColumnBehavior {
+                size: max_length,
+                grow: true,
+                min_size: max_length.min(24),
+            },
+            count_column(),
+            count_column(),
+            count_column(),
+            count_column(),
+        ],
+        TERMINAL.size().1.into(),
+    );
+
+    table.push_left(style("TAG").bold())?;
+    table.push_right(style("WORKS").bold())?;
+    table.push_right(style("IMPLIED").bold())?;
+    table.push_right(style("PARENTS").bold())?;
+    table.push_right(style("CHILDREN").bold())?;
+
+    for tag in tags {
+        table.push_left(style_tag(&tag.tag))?;
+        table.push_right(tag.direct_works)?;
+        table.push_right(tag.implied_works)?;
+        table.push_right(tag.parents)?;
+        table.push_right(tag.children)?;
+    }
+
+    Ok(())
+}
+
+pub async fn list_tags(
+    prefix: Option<&str>,
+    sort: TagSort,
+    reverse: bool,
+    format: OutputFormat,
+) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    let mut tags = Tag::list_with_counts(&mut tx, prefix).await?;
+
+    tx.commit().await?;
+
+    match sort {
+        TagSort::Name => tags.sort_by_key(|t| {
+            (
+                t.tag.name.to_lowercase(),
+                t.tag.discriminator.as_ref().map(|d| d.to_lowercase()),
+            )
+        }),
+        TagSort::Works => tags.sort_by_key(|t| std::cmp::Reverse(t.direct_works)),
+        TagSort::Implied => tags.sort_by_key(|t| std::cmp::Reverse(t.implied_works)),
+        TagSort::Parents => tags.sort_by_key(|t| std::cmp::Reverse(t.parents)),
+        TagSort::Children => tags.sort_by_key(|t| std::cmp::Reverse(t.children)),
+    }
+
+    if reverse {
+        tags.reverse();
+    }
+
+    match format {
+        OutputFormat::Json => {
+            TERMINAL.write_line(&serde_json::to_string_pretty(&tags)?)?;
+        }
+        OutputFormat::Table => print_tag_counts(&tags)?,
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub async fn prune_tags(dry_run: bool) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    let pruned = Tag::prune(&mut tx).await?;
+
+    for tag in &pruned {
+        TERMINAL.write_line(&style_tag(tag))?;
+    }
+
+    if dry_run {
+        tx.rollback().await?;
+
+        write_success(&format!("Would prune {} tags", pruned.len()))?;
+    } else {
+        tx.commit().await?;
+
+        write_success(&format!("Pruned {} tags", pruned.len()))?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub async fn describe_tag(
+    tag: &DiscriminatedTag,
+    description: Option<&str>,
+) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    let Some(mut tag) = select_tag(&mut tx, tag, "Select which tag you want to describe").await?
+    else {
+        write_failure(&format!("Failure finding {tag}"))?;
         return Ok(ExitCode::FAILURE);
     };
 
-    tag.discriminate(&mut tx, &discriminator.0).await?;
+    let description = match description {
+        Some(description) => description.to_owned(),
+        None => {
+            let Some(description) =
+                Editor::new().edit(tag.description.as_deref().unwrap_or_default())?
+            else {
+                write_failure("Aborted editing the description")?;
+                return Ok(ExitCode::FAILURE);
+            };
+
+            description
+        }
+    };
+
+    let description = description.trim();
+
+    tag.describe(
+        &mut tx,
+        if description.is_empty() {
+            None
+        } else {
+            Some(description)
+        },
+    )
+    .await?;
 
     tx.commit().await?;
 
-    write_success(&format!(
-        "Discriminated {tag_name} -> {tag_name}#{discriminator}"
-    ))?;
+    write_success(&format!("Described {tag}"))?;
 
     Ok(ExitCode::SUCCESS)
 }
 
-pub async fn display_tag_info(tag: &DiscriminatedTag) -> anyhow::Result<ExitCode> {
+pub async fn search_tags(text: &str) -> anyhow::Result<ExitCode> {
     let chronicle = get_chronicle().await;
 
     let mut tx = chronicle.begin().await?;
 
-    let tags = match tag {
+    let tags = Tag::search(&mut tx, text).await?;
+
+    tx.commit().await?;
+
+    let max_length = tags
+        .iter()
+        .map(|tag| tag.to_string().len())
+        .max()
+        .unwrap_or_default()
+        .max(3)
+        + 1;
+
+    let mut table = Table::new(
+        &TERMINAL,
+        vec![
+            ColumnBehavior {
+                size: max_length,
+                grow: false,
+                min_size: max_length.min(24),
+            },
+            ColumnBehavior {
+                size: 32,
+                grow: true,
+                min_size: 12,
+            },
+        ],
+        TERMINAL.size().1.into(),
+    );
+
+    table.push_left(style("TAG").bold())?;
+    table.push_left(style("DESCRIPTION").bold())?;
+
+    for tag in &tags {
+        table.push_left(style_tag(tag))?;
+        table.push_left(tag.description.as_deref().unwrap_or_default())?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn category_style(category: Option<&str>) -> Style {
+    match category.map(str::to_lowercase).as_deref() {
+        None => Style::new(),
+        Some("character") => Style::new().green(),
+        Some("series") | Some("copyright") => Style::new().magenta(),
+        Some("species") => Style::new().yellow(),
+        Some("artist") => Style::new().red(),
+        Some("meta") => Style::new().cyan(),
+        Some(_) => Style::new().blue(),
+    }
+}
+
+pub fn style_tag(tag: &Tag) -> String {
+    category_style(tag.category.as_deref())
+        .apply_to(tag)
+        .to_string()
+}
+
+/// Formats tags grouped by category, with uncategorized tags last.
+pub fn format_tags(tags: &[Tag]) -> String {
+    tags.iter()
+        .sorted_by_key(|tag| {
+            (
+                tag.category.is_none(),
+                tag.category.as_ref().map(|c| c.to_lowercase()),
+                tag.name.to_lowercase(),
+            )
+        })
+        .chunk_by(|tag| tag.category.as_ref().map(|c| c.to_lowercase()))
+        .into_iter()
+        .map(|(category, group)| {
+            let style = category_style(category.as_deref());
+
+            let names = group
+                .map(|tag| match &tag.discriminator {
+                    Some(discriminator) => format!("{}#{discriminator}", tag.name),
+                    None => tag.name.clone(),
+                })
+                .join(", ");
+
+            match category {
+                Some(category) => style.apply_to(format!("{category}: {names}")).to_string(),
+                None => style.apply_to(names).to_string(),
+            }
+        })
+        .join("; ")
+}
+
+async fn select_tag(
+    tx: &mut Transaction<'_, Sqlite>,
+    tag: &DiscriminatedTag,
+    prompt: &str,
+) -> anyhow::Result<Option<Tag>> {
+    let mut tags = match tag {
         DiscriminatedTag {
             name,
             discriminator: Some(discriminator),
-        } => Tag::try_get_discriminated(&mut tx, name, Some(discriminator))
+            ..
+        } => Tag::try_get_discriminated(tx, name, Some(discriminator))
             .await?
             .into_iter()
             .collect(),
         DiscriminatedTag {
             name,
             discriminator: None,
-        } => Tag::get(&mut tx, name).await?,
+            ..
+        } => Tag::get(tx, name).await?,
     };
 
-    let tag = match tags.len() {
-        0 => {
-            write_failure(&format!("Error finding {tag}"))?;
-            return Ok(ExitCode::FAILURE);
+    let selected = match tags.len() {
+        0 => return Ok(None),
+        1 => 0,
+        _ => Select::new().with_prompt(prompt).items(&tags).interact()?,
+    };
+
+    Ok(Some(tags.swap_remove(selected)))
+}
+
+pub async fn alias_tag(
+    tag: &DiscriminatedTag,
+    alias: &DiscriminatedTag,
+) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    let Some(tag) = select_tag(&mut tx, tag, "Select which tag you want to alias").await? else {
+        write_failure(&format!("Failure finding {tag}"))?;
+        return Ok(ExitCode::FAILURE);
+    };
+
+    if let Err(err) = tag.add_alias(&mut tx, alias).await {
+        write_failure(&format!("Failed {err}"))?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    tx.commit().await?;
+
+    write_success(&format!("Aliased {alias} -> {tag}"))?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub async fn map_tag(
+    service: &str,
+    source: &str,
+    action: MappingAction,
+    tag: Option<&DiscriminatedTag>,
+) -> anyhow::Result<ExitCode> {
+    if !SERVICES.iter().any(|s| s.name() == service) && FALLBACK_SERVICE.name() != service {
+        write_failure(&format!("Unknown service {service}"))?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    let tag = match tag {
+        Some(tag) => Some(Tag::get_or_create(&mut tx, tag).await?),
+        None => None,
+    };
+
+    TagMapping::set(&mut tx, service, source, action, tag.as_ref()).await?;
+
+    tx.commit().await?;
+
+    match tag {
+        Some(tag) => write_success(&format!("Mapped {service} '{source}' -> {tag}"))?,
+        None => write_success(&format!("Set {service} '{source}' to {action}"))?,
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub async fn unmap_tag(service: &str, source: &str) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    if !TagMapping::remove(&mut tx, service, source).await? {
+        write_failure(&format!("Failure finding mapping for {service} '{source}'"))?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    tx.commit().await?;
+
+    write_success(&format!("Removed mapping for {service} '{source}'"))?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub async fn list_mappings(service: Option<&str>) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    let mut mappings = Vec::new();
+
+    for mapping in TagMapping::list(&mut tx, service).await? {
+        let tag = match mapping.tag {
+            Some(id) => Tag::get_by_id(&mut tx, id).await?,
+            None => None,
+        };
+
+        mappings.push((mapping, tag));
+    }
+
+    tx.commit().await?;
+
+    let mut table = Table::new(
+        &TERMINAL,
+        vec![
+            ColumnBehavior {
+                size: 10,
+                grow: false,
+                min_size: 7,
+            },
+            ColumnBehavior {
+                size: 24,
+                grow: true,
+                min_size: 12,
+            },
+            ColumnBehavior {
+                size: 24,
+                grow: true,
+                min_size: 12,
+            },
+        ],
+        TERMINAL.size().1.into(),
+    );
+
+    table.push_left(style("SERVICE").bold())?;
+    table.push_left(style("SOURCE").bold())?;
+    table.push_left(style("TAG").bold())?;
+
+    for (mapping, tag) in &mappings {
+        table.push_left(&mapping.service)?;
+        table.push_left(&mapping.source)?;
+
+        match tag {
+            Some(tag) => table.push_left(style_tag(tag))?,
+            None => table.push_left(style(mapping.action.to_string()).dim())?,
         }
-        1 => &tags[0],
-        _ => {
-            let selected = Select::new()
-                .with_prompt("Select which tag you want information for")
-                .items(&tags)
-                .interact()?;
-            &tags[selected]
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub async fn untag_tag(
+    tag: &DiscriminatedTag,
+    parent: &DiscriminatedTag,
+) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    let Some(tag) = select_tag(&mut tx, tag, "Select which tag you want to untag").await? else {
+        write_failure(&format!("Failure finding {tag}"))?;
+        return Ok(ExitCode::FAILURE);
+    };
+
+    let Some(parent) = select_tag(&mut tx, parent, "Select which tag to remove").await? else {
+        write_failure(&format!("Failure finding {parent}"))?;
+        return Ok(ExitCode::FAILURE);
+    };
+
+    if !tag.untag(&mut tx, &parent).await? {
+        write_failure(&format!("Failure {tag} is not tagged with {parent}"))?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    tx.commit().await?;
+
+    write_success(&format!("Removed {parent} from {tag}"))?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub async fn rediscriminate_tag(
+    tag: &DiscriminatedTag,
+    discriminator: Option<&str>,
+    merge: bool,
+) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    let Some(tag) =
+        Tag::try_get_unaliased(&mut tx, &tag.name, tag.discriminator.as_deref()).await?
+    else {
+        write_failure(&format!("Failure finding {tag}"))?;
+        return Ok(ExitCode::FAILURE);
+    };
+
+    let result = match discriminator {
+        Some(discriminator) => tag.rediscriminate(&mut tx, discriminator, merge).await,
+        None => tag.undiscriminate(&mut tx, merge).await,
+    };
+
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
+            write_failure(&format!("Failed {err}"))?;
+            return Ok(ExitCode::FAILURE);
         }
     };
 
+    tx.commit().await?;
+
+    if result.id == tag.id {
+        write_success(&format!("Renamed {tag} -> {result}"))?;
+    } else {
+        write_success(&format!("Merged {tag} into {result}"))?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub async fn tag_family(name: &str) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    let tags = Tag::family(&mut tx, name).await?;
+
+    tx.commit().await?;
+
+    if tags.is_empty() {
+        write_failure(&format!("Failure finding {name}"))?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    print_tag_counts(&tags)?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub async fn discriminate_tag(
+    tag_name: &TagPart,
+    discriminator: &TagPart,
+) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    let Some(mut tag) = Tag::try_get_unaliased(&mut tx, &tag_name.0, None).await? else {
+        write_failure(&format!("Failure finding {tag_name}"))?;
+        return Ok(ExitCode::FAILURE);
+    };
+
+    tag.discriminate(&mut tx, &discriminator.0).await?;
+
+    tx.commit().await?;
+
+    write_success(&format!(
+        "Discriminated {tag_name} -> {tag_name}#{discriminator}"
+    ))?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub async fn display_tag_info(tag: &DiscriminatedTag) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    let Some(tag) = select_tag(&mut tx, tag, "Select which tag you want information for").await?
+    else {
+        write_failure(&format!("Error finding {tag}"))?;
+        return Ok(ExitCode::FAILURE);
+    };
+
+    if let Some(description) = &tag.description {
+        TERMINAL.write_line(description)?;
+        TERMINAL.write_line("")?;
+    }
+
+    let aliases = tag.aliases(&mut tx).await?;
+
+    if !aliases.is_empty() {
+        TERMINAL.write_line(&format!(
+            "{} {}",
+            style("Aliases:").bold(),
+            aliases.iter().join(", ")
+        ))?;
+    }
+
     let mut ancestors = tag.ancestors(&mut tx).await?;
     let mut descendants = tag.descendants(&mut tx).await?;
 
@@ -135,14 +734,18 @@ pub async fn display_tag_info(tag: &DiscriminatedTag) -> anyhow::Result<ExitCode
         .chunk_by(|a| a.depth)
         .into_iter()
     {
-        let joined = group.into_iter().map(|t| t.tag.to_string()).join(", ");
+        let joined = group.into_iter().map(|t| style_tag(&t.tag)).join(", ");
 
         table.push_right(depth.to_string())?;
         table.push_left(joined)?;
     }
 
     table.push_right("0")?;
-    table.push_left(&style(tag.to_string()).bold().to_string())?;
+    table.push_left(
+        category_style(tag.category.as_deref())
+            .bold()
+            .apply_to(&tag),
+    )?;
 
     for (depth, group) in ancestors
         .into_iter()
@@ -151,7 +754,7 @@ pub async fn display_tag_info(tag: &DiscriminatedTag) -> anyhow::Result<ExitCode
         .chunk_by(|a| a.depth)
         .into_iter()
     {
-        let joined = group.into_iter().map(|t| t.tag.to_string()).join(", ");
+        let joined = group.into_iter().map(|t| style_tag(&t.tag)).join(", ");
 
         table.push_right(depth.to_string())?;
         table.push_left(joined)?;
@@ -162,23 +765,146 @@ pub async fn display_tag_info(tag: &DiscriminatedTag) -> anyhow::Result<ExitCode
     Ok(ExitCode::SUCCESS)
 }
 
-pub async fn execute_tag_expression(expression: &TagExpression) -> anyhow::Result<ExitCode> {
+/// Formats the changes in `plan` one per line.
+pub fn format_plan(plan: &TagPlan) -> Vec<String> {
+    let added = style("+").green().bold();
+    let existing = style("=").dim();
+    let failed = style("!").red().bold();
+    let pending = style("?").yellow().bold();
+
+    let mut lines = Vec::new();
+
+    for tag in &plan.discriminator_conflicts {
+        lines.push(format!(
+            "{pending} discriminate {} before tagging",
+            style_tag(tag)
+        ));
+    }
+
+    for tag in &plan.created_tags {
+        lines.push(format!("{added} create {}", style_tag(tag)));
+    }
+
+    for link in &plan.work_links {
+        lines.push(format!(
+            "{} work {} -> {}",
+            if link.exists { &existing } else { &added },
+            link.from,
+            style_tag(&link.to)
+        ));
+    }
+
+    for link in &plan.tag_links {
+        lines.push(format!(
+            "{} {} -> {}",
+            if link.exists { &existing } else { &added },
+            style_tag(&link.from),
+            style_tag(&link.to)
+        ));
+    }
+
+    for cycle in &plan.cycles {
+        lines.push(format!(
+            "{failed} cycle {}",
+            cycle.iter().map(style_tag).join(" -> ")
+        ));
+    }
+
+    for conflict in &plan.category_conflicts {
+        lines.push(format!(
+            "{failed} category {} is not {}",
+            style_tag(&conflict.tag),
+            conflict.requested
+        ));
+    }
+
+    lines
+}
+
+pub async fn plan_tag_expression(
+    expression: &TagExpression,
+    on_conflict: ConflictPolicy,
+) -> anyhow::Result<ExitCode> {
     let chronicle = get_chronicle().await;
 
     let mut tx = chronicle.begin().await?;
 
-    for tag in expression.hierarchy.iter().flatten() {
-        if tag.discriminator.is_some() {
-            if let Some(mut other) = Tag::try_get_discriminated(&mut tx, &tag.name, None).await? {
+    let plan = expression.plan(&mut tx).await?;
+
+    tx.rollback().await?;
+
+    for line in format_plan(&plan) {
+        TERMINAL.write_line(&line)?;
+    }
+
+    if let (ConflictPolicy::Fail, Some(other)) = (on_conflict, plan.discriminator_conflicts.first())
+    {
+        write_failure(&format!(
+            "Failed tag '{other}' already exists and is not discriminated"
+        ))?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    if !plan.cycles.is_empty() {
+        write_failure(&format!(
+            "Failure {} implications would form a cycle",
+            plan.cycles.len()
+        ))?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    if !plan.category_conflicts.is_empty() {
+        write_failure(&format!(
+            "Failure {} tags already have a different category",
+            plan.category_conflicts.len()
+        ))?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    write_success(&format!("Would tag {} connections", plan.new_connections()))?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Discriminates the existing undiscriminated tags which conflict with `expression` according to
+/// `policy`, returning the first conflicting tag if the policy is to fail.
+pub async fn resolve_discriminator_conflicts(
+    tx: &mut Transaction<'_, Sqlite>,
+    expression: &TagExpression,
+    policy: ConflictPolicy,
+) -> anyhow::Result<Option<Tag>> {
+    for mut other in expression.discriminator_conflicts(tx).await? {
+        match policy {
+            ConflictPolicy::Prompt => {
                 let other_discriminant = Input::<TagPart>::new()
                     .with_prompt(format!("Tag '{other}' already exists and is not discriminated, please provide a discriminator for it"))
                     .interact()?;
 
-                other.discriminate(&mut tx, &other_discriminant.0).await?;
+                other.discriminate(tx, &other_discriminant.0).await?;
             }
+            ConflictPolicy::Fail => return Ok(Some(other)),
+            ConflictPolicy::Auto => other.discriminate_automatically(tx).await?,
         }
     }
 
+    Ok(None)
+}
+
+pub async fn execute_tag_expression(
+    expression: &TagExpression,
+    on_conflict: ConflictPolicy,
+) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    if let Some(other) = resolve_discriminator_conflicts(&mut tx, expression, on_conflict).await? {
+        write_failure(&format!(
+            "Failed tag '{other}' already exists and is not discriminated"
+        ))?;
+        return Ok(ExitCode::FAILURE);
+    }
+
     let spinner = ProgressBar::new_spinner().with_style(SPINNER_STYLE.clone());
     spinner.enable_steady_tick(Duration::from_millis(100));
     spinner.set_prefix(PREFIX_STYLE.apply_to("Tagging").to_string());
@@ -187,7 +913,16 @@ pub async fn execute_tag_expression(expression: &TagExpression) -> anyhow::Resul
         expression.approximate_connections()
     ));
 
-    let total = expression.execute(&mut tx).await?;
+    let total = match expression.execute(&mut tx).await {
+        Ok(total) => total,
+        Err(err) => {
+            spinner.finish_and_clear();
+
+            write_failure(&format!("Failed {err}"))?;
+
+            return Ok(ExitCode::FAILURE);
+        }
+    };
 
     tx.commit().await?;
 
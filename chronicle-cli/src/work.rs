@@ -5,24 +5,41 @@ use std::{
     time::Duration,
 };
 
-use chronicle::{models::Work, record::Record, search::Query};
+use chronicle::{
+    author::DEFAULT_ROLE,
+    models::{Author, Tag, Work, WorkAuthor, WorkId},
+    record::Record,
+    search::Query,
+};
 use console::style;
-use dialoguer::Input;
+use dialoguer::{Input, MultiSelect};
 use indicatif::{BinaryBytes, ProgressBar};
+use itertools::Itertools;
+use sqlx::{Sqlite, Transaction};
 use uuid::Uuid;
 
 use crate::{
-    args::{WorkColumn, WorkCommand, WorkDetails, WorkDisplayOptions},
+    args::{AuthorResolution, WorkColumn, WorkCommand, WorkDetails, WorkDisplayOptions},
+    author::author_policy,
     get_chronicle,
     table::Table,
+    tag::{format_tags, style_tag},
     utils::format_hash,
     write_failure, write_success, PREFIX_STYLE, SPINNER_STYLE, TERMINAL,
 };
 
 pub async fn work_command(command: &WorkCommand) -> anyhow::Result<ExitCode> {
     match command {
-        WorkCommand::Add { path, details } => work_add(path, details).await,
-        WorkCommand::Import { source, details } => work_import(source, details).await,
+        WorkCommand::Add {
+            path,
+            details,
+            author_policy,
+        } => work_add(path, details, *author_policy).await,
+        WorkCommand::Import {
+            source,
+            details,
+            author_policy,
+        } => work_import(source, details, *author_policy).await,
         WorkCommand::Search {
             display_options,
             query,
@@ -38,10 +55,57 @@ pub async fn work_command(command: &WorkCommand) -> anyhow::Result<ExitCode> {
             work_search(query, display_options).await
         }
         WorkCommand::List { display_options } => work_list(display_options).await,
+        WorkCommand::SuggestTags { id, limit } => work_suggest_tags(WorkId(*id), *limit).await,
+    }
+}
+
+pub async fn work_suggest_tags(id: WorkId, limit: u32) -> anyhow::Result<ExitCode> {
+    let chronicle = get_chronicle().await;
+
+    let mut tx = chronicle.begin().await?;
+
+    let Some(work) = Work::get_by_id(&mut tx, id).await? else {
+        write_failure(&format!("Failure finding work {id}"))?;
+        return Ok(ExitCode::FAILURE);
+    };
+
+    let suggestions = Tag::suggest_for_work(&mut tx, &work, limit).await?;
+
+    if suggestions.is_empty() {
+        write_failure(&format!("Failure no suggestions for work {id}"))?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let items: Vec<_> = suggestions
+        .iter()
+        .map(|suggestion| format!("{} ({:.1})", style_tag(&suggestion.tag), suggestion.score))
+        .collect();
+
+    let selected = MultiSelect::new()
+        .with_prompt("Select tags to apply")
+        .items(&items)
+        .interact()?;
+
+    let mut total = 0;
+
+    for index in selected {
+        if work.tag(&mut tx, &suggestions[index].tag).await? {
+            total += 1;
+        }
     }
+
+    tx.commit().await?;
+
+    write_success(&format!("Tagged {total} connections"))?;
+
+    Ok(ExitCode::SUCCESS)
 }
 
-pub async fn work_import(source: &url::Url, details: &WorkDetails) -> anyhow::Result<ExitCode> {
+pub async fn work_import(
+    source: &url::Url,
+    details: &WorkDetails,
+    resolution: AuthorResolution,
+) -> anyhow::Result<ExitCode> {
     let spinner = ProgressBar::new_spinner();
 
     spinner.enable_steady_tick(Duration::from_millis(100));
@@ -52,9 +116,16 @@ pub async fn work_import(source: &url::Url, details: &WorkDetails) -> anyhow::Re
     let chronicle = get_chronicle().await;
     let mut tx = chronicle.begin().await?;
 
-    let works =
-        Work::import_works_from_url(chronicle, &mut tx, source, Some(&details.clone().into()))
-            .await;
+    let policy = author_policy(resolution, Some(spinner.clone()));
+
+    let works = Work::import_works_from_url(
+        chronicle,
+        &mut tx,
+        source,
+        Some(&details.clone().into()),
+        &policy,
+    )
+    .await;
 
     tx.commit().await?;
 
@@ -90,9 +161,49 @@ pub fn display_work_header(table: &mut Table, options: &WorkDisplayOptions) -> a
     Ok(())
 }
 
+/// An author credited on a work along with the name to display for them.
+pub struct Credit {
+    pub author: WorkAuthor,
+    pub name: Option<String>,
+}
+
+impl Credit {
+    fn role_suffix(&self) -> String {
+        if self.author.role == DEFAULT_ROLE {
+            String::new()
+        } else {
+            format!(" ({})", self.author.role)
+        }
+    }
+}
+
+pub async fn work_credits(
+    tx: &mut Transaction<'_, Sqlite>,
+    work: &Work,
+) -> anyhow::Result<Vec<Credit>> {
+    let mut credits = Vec::new();
+
+    for author in work.authors(tx).await? {
+        let name = Author {
+            author_id: author.author_id,
+        }
+        .get_author_names(tx)
+        .await?
+        .into_iter()
+        .next()
+        .map(|name| name.name);
+
+        credits.push(Credit { author, name });
+    }
+
+    Ok(credits)
+}
+
 pub fn display_work(
     table: &mut Table,
     work: &Work,
+    tags: &[Tag],
+    credits: &[Credit],
     options: &WorkDisplayOptions,
 ) -> anyhow::Result<()> {
     for column in &options.columns {
@@ -110,7 +221,31 @@ pub fn display_work(
                 table.push_left(work.title.as_ref().map(|t| t.clone()).unwrap_or_default())?;
             }
             WorkColumn::AuthorId => {
-                table.push_left(work.author_id.map(|id| id.to_string()).unwrap_or_default())?;
+                table.push_left(
+                    credits
+                        .iter()
+                        .map(|credit| {
+                            format!("{}{}", credit.author.author_id, credit.role_suffix())
+                        })
+                        .join(", "),
+                )?;
+            }
+            WorkColumn::Authors => {
+                table.push_left(
+                    credits
+                        .iter()
+                        .map(|credit| {
+                            format!(
+                                "{}{}",
+                                credit
+                                    .name
+                                    .clone()
+                                    .unwrap_or_else(|| credit.author.author_id.to_string()),
+                                credit.role_suffix()
+                            )
+                        })
+                        .join(", "),
+                )?;
             }
             WorkColumn::Caption => {
                 table.push_left(work.caption.as_ref().map(|c| c.clone()).unwrap_or_default())?;
@@ -121,13 +256,29 @@ pub fn display_work(
             WorkColumn::Size => {
                 table.push_left(&format!("{}", BinaryBytes(work.size)))?;
             }
+            WorkColumn::Tags => {
+                table.push_left(format_tags(tags))?;
+            }
         }
     }
 
     Ok(())
 }
 
-pub fn print_works(works: &Vec<Work>, options: &WorkDisplayOptions) -> anyhow::Result<()> {
+pub async fn print_works(
+    tx: &mut Transaction<'_, Sqlite>,
+    works: &Vec<Work>,
+    options: &WorkDisplayOptions,
+) -> anyhow::Result<()> {
+    let show_tags = options
+        .columns
+        .iter()
+        .any(|column| matches!(column, WorkColumn::Tags));
+    let show_authors = options
+        .columns
+        .iter()
+        .any(|column| matches!(column, WorkColumn::AuthorId | WorkColumn::Authors));
+
     let mut console = TERMINAL.clone();
     let width = console.size().1 as usize;
 
@@ -140,7 +291,19 @@ pub fn print_works(works: &Vec<Work>, options: &WorkDisplayOptions) -> anyhow::R
     display_work_header(&mut table, options)?;
 
     for work in works {
-        display_work(&mut table, &work, options)?;
+        let tags = if show_tags {
+            work.tags(tx).await?
+        } else {
+            Vec::new()
+        };
+
+        let credits = if show_authors {
+            work_credits(tx, work).await?
+        } else {
+            Vec::new()
+        };
+
+        display_work(&mut table, &work, &tags, &credits, options)?;
     }
 
     Ok(())
@@ -151,9 +314,9 @@ pub async fn work_list(options: &WorkDisplayOptions) -> anyhow::Result<ExitCode>
 
     let works = Work::get_all(&mut tx).await?;
 
-    tx.commit().await?;
+    print_works(&mut tx, &works, options).await?;
 
-    print_works(&works, options)?;
+    tx.commit().await?;
 
     Ok(ExitCode::SUCCESS)
 }
@@ -163,14 +326,18 @@ pub async fn work_search(query: &Query, options: &WorkDisplayOptions) -> anyhow:
 
     let works = Work::search(&mut tx, query).await?;
 
-    tx.commit().await?;
+    print_works(&mut tx, &works, options).await?;
 
-    print_works(&works, options)?;
+    tx.commit().await?;
 
     Ok(ExitCode::SUCCESS)
 }
 
-pub async fn work_add(path: impl AsRef<Path>, details: &WorkDetails) -> anyhow::Result<ExitCode> {
+pub async fn work_add(
+    path: impl AsRef<Path>,
+    details: &WorkDetails,
+    resolution: AuthorResolution,
+) -> anyhow::Result<ExitCode> {
     let original_path = path.as_ref();
 
     let file_name = if let Some(extension) = original_path.extension() {
@@ -193,14 +360,15 @@ pub async fn work_add(path: impl AsRef<Path>, details: &WorkDetails) -> anyhow::
 
     let mut tx = chronicle.begin().await?;
 
-    let work = match Work::create_from_record(&mut tx, &record).await {
-        Ok(work) => work,
-        Err(err) => {
-            write_failure(&format!("Failed {err}"))?;
+    let work =
+        match Work::create_from_record(&mut tx, &record, &author_policy(resolution, None)).await {
+            Ok(work) => work,
+            Err(err) => {
+                write_failure(&format!("Failed {err}"))?;
 
-            return Ok(ExitCode::FAILURE);
-        }
-    };
+                return Ok(ExitCode::FAILURE);
+            }
+        };
 
     tx.commit().await?;
 
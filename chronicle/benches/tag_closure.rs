@@ -0,0 +1,184 @@
+//! Compares searching through the `tag_closure` table against walking `meta_tags` with a
+//! recursive CTE, both as raw SQL and through `Work::search`, and removing an implication with
+//! `Tag::untag` against rebuilding the whole closure.
+//!
+//! Run with `cargo bench -p chronicle --bench tag_closure`.
+
+use std::time::{Duration, Instant};
+
+use chronicle::{
+    models::{Tag, Work},
+    search::Query,
+};
+use sqlx::{sqlite::SqlitePoolOptions, Sqlite, SqlitePool, Transaction};
+
+const BRANCHING: usize = 4;
+const DEPTH: usize = 5;
+const ITERATIONS: u32 = 50;
+
+const CTE_QUERY: &str = r#"
+    WITH RECURSIVE implied(tag_id) AS (
+        SELECT id FROM tags WHERE name = ?
+        UNION
+        SELECT target FROM meta_tags JOIN implied ON meta_tags.tag = implied.tag_id
+    ) SELECT * FROM works WHERE work_id IN (SELECT work_id FROM work_tags JOIN implied ON work_tags.tag = implied.tag_id);
+"#;
+
+const CLOSURE_QUERY: &str = r#"
+    SELECT * FROM works WHERE work_id IN (
+        SELECT work_tags.work_id FROM work_tags JOIN tag_closure ON work_tags.tag = tag_closure.descendant
+        WHERE tag_closure.ancestor IN (SELECT id FROM tags WHERE name = ?)
+    );
+"#;
+
+/// Builds a tree of tags `BRANCHING` wide and `DEPTH` deep with a single work on every leaf.
+async fn populate(tx: &mut Transaction<'_, Sqlite>) -> Result<(), chronicle::Error> {
+    let mut level = vec![Tag::create(tx, "t", None).await?];
+    let mut work = 0;
+
+    for _ in 0..DEPTH {
+        let mut next = Vec::with_capacity(level.len() * BRANCHING);
+
+        for parent in &level {
+            for branch in 0..BRANCHING {
+                let child = Tag::create(tx, &format!("{}_{branch}", parent.name), None).await?;
+                child.tag(tx, parent).await?;
+                next.push(child);
+            }
+        }
+
+        level = next;
+    }
+
+    for leaf in &level {
+        let (work_id,): (i64,) = sqlx::query_as(
+            "INSERT INTO works(path, size, hash) VALUES (?1, 0, ?2) RETURNING work_id;",
+        )
+        .bind(format!("{work}"))
+        .bind(work)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        sqlx::query("INSERT INTO work_tags(tag, work_id) VALUES (?, ?);")
+            .bind(leaf.id)
+            .bind(work_id)
+            .execute(&mut **tx)
+            .await?;
+
+        work += 1;
+    }
+
+    Ok(())
+}
+
+async fn time<F, Fut>(name: &str, unit: &str, mut f: F) -> Result<(), chronicle::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<usize, chronicle::Error>>,
+{
+    let mut total = Duration::ZERO;
+    let mut found = 0;
+
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        found = f().await?;
+        total += start.elapsed();
+    }
+
+    println!(
+        "{name:<36} {:>10.3?} per {unit} ({found})",
+        total / ITERATIONS
+    );
+
+    Ok(())
+}
+
+async fn bench(pool: &SqlitePool, name: &str) -> Result<(), chronicle::Error> {
+    let query: Query = name.parse()?;
+
+    time(&format!("Work::search `{name}`"), "search", || async {
+        let mut tx = pool.begin().await?;
+        Ok(Work::search(&mut tx, &query).await?.len())
+    })
+    .await?;
+
+    for (kind, sql) in [("closure", CLOSURE_QUERY), ("recursive cte", CTE_QUERY)] {
+        time(&format!("{kind} `{name}`"), "search", || async {
+            let mut tx = pool.begin().await?;
+            Ok(sqlx::query(sql).bind(name).fetch_all(&mut *tx).await?.len())
+        })
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Times removing the implication between `child` and `parent`, rolling it back after each run,
+/// reporting how many closure rows are left.
+async fn bench_untag(pool: &SqlitePool, parent: &str, child: &str) -> Result<(), chronicle::Error> {
+    let mut tx = pool.begin().await?;
+    let parent = Tag::get_discriminated(&mut tx, parent, None).await?;
+    let child = Tag::get_discriminated(&mut tx, child, None).await?;
+    tx.rollback().await?;
+
+    time(&format!("Tag::untag `{}`", child.name), "untag", || async {
+        let mut tx = pool.begin().await?;
+        child.untag(&mut tx, &parent).await?;
+        closure_rows(&mut tx).await
+    })
+    .await?;
+
+    time(
+        &format!("rebuild closure `{}`", child.name),
+        "untag",
+        || async {
+            let mut tx = pool.begin().await?;
+
+            sqlx::query("DELETE FROM meta_tags WHERE tag = ? AND target = ?;")
+                .bind(parent.id)
+                .bind(child.id)
+                .execute(&mut *tx)
+                .await?;
+            Tag::rebuild_closure(&mut tx).await?;
+
+            closure_rows(&mut tx).await
+        },
+    )
+    .await
+}
+
+async fn closure_rows(tx: &mut Transaction<'_, Sqlite>) -> Result<usize, chronicle::Error> {
+    let (rows,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tag_closure;")
+        .fetch_one(&mut **tx)
+        .await?;
+
+    Ok(rows as usize)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), chronicle::Error> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+
+    sqlx::migrate!().run(&pool).await?;
+
+    let mut tx = pool.begin().await?;
+    populate(&mut tx).await?;
+    tx.commit().await?;
+
+    for name in ["t", "t_0", "t_0_0_0", "t_0_0_0_0_0"] {
+        bench(&pool, name).await?;
+    }
+
+    for (parent, child) in [
+        ("t", "t_0"),
+        ("t_0_0", "t_0_0_0"),
+        ("t_0_0_0_0", "t_0_0_0_0_0"),
+    ] {
+        bench_untag(&pool, parent, child).await?;
+    }
+
+    Ok(())
+}
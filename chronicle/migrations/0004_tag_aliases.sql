@@ -0,0 +1,11 @@
+CREATE TABLE "tag_aliases" (
+    "tag_id" INTEGER NOT NULL,
+    "name" TEXT NOT NULL COLLATE NOCASE,
+    "discriminator" TEXT COLLATE NOCASE,
+    FOREIGN KEY("tag_id") REFERENCES "tags"("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE UNIQUE INDEX "tag_aliases_unique" ON "tag_aliases" (
+    "name",
+    IFNULL("discriminator", '') COLLATE NOCASE
+);
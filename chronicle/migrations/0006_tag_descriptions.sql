@@ -0,0 +1,4 @@
+ALTER TABLE
+    "tags"
+ADD
+    COLUMN "description" TEXT;
@@ -0,0 +1,30 @@
+DROP TRIGGER "cycle_check";
+
+CREATE TRIGGER "cycle_check" BEFORE
+INSERT
+    ON "meta_tags" FOR EACH ROW
+BEGIN
+SELECT
+    RAISE(ABORT, 'Tag Cycle Detected')
+WHERE
+    NEW."tag" = NEW."target"
+    OR EXISTS (
+        WITH RECURSIVE "reachable"("tag_id") AS (
+            SELECT
+                NEW."target"
+            UNION
+            SELECT
+                "meta_tags"."target"
+            FROM
+                "meta_tags"
+                JOIN "reachable" ON "meta_tags"."tag" = "reachable"."tag_id"
+        )
+        SELECT
+            1
+        FROM
+            "reachable"
+        WHERE
+            "tag_id" = NEW."tag"
+    );
+
+END;
@@ -0,0 +1,58 @@
+CREATE TABLE "tag_closure" (
+    "ancestor" INTEGER NOT NULL,
+    "descendant" INTEGER NOT NULL,
+    "depth" INTEGER NOT NULL,
+    FOREIGN KEY("ancestor") REFERENCES "tags"("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY("descendant") REFERENCES "tags"("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    PRIMARY KEY ("ancestor", "descendant")
+);
+
+CREATE INDEX "tag_closure_descendant" ON "tag_closure" ("descendant");
+
+INSERT INTO
+    "tag_closure" ("ancestor", "descendant", "depth") WITH RECURSIVE "closure"("ancestor", "descendant", "depth") AS (
+        SELECT
+            "id",
+            "id",
+            0
+        FROM
+            "tags"
+        UNION
+        SELECT
+            "closure"."ancestor",
+            "meta_tags"."target",
+            "closure"."depth" + 1
+        FROM
+            "meta_tags"
+            JOIN "closure" ON "meta_tags"."tag" = "closure"."descendant"
+    )
+SELECT
+    "ancestor",
+    "descendant",
+    MIN("depth")
+FROM
+    "closure"
+GROUP BY
+    "ancestor",
+    "descendant";
+
+DROP TRIGGER "cycle_check";
+
+CREATE TRIGGER "cycle_check" BEFORE
+INSERT
+    ON "meta_tags" FOR EACH ROW
+BEGIN
+SELECT
+    RAISE(ABORT, 'Tag Cycle Detected')
+WHERE
+    EXISTS (
+        SELECT
+            1
+        FROM
+            "tag_closure"
+        WHERE
+            "ancestor" = NEW."target"
+            AND "descendant" = NEW."tag"
+    );
+
+END;
@@ -0,0 +1,13 @@
+CREATE TABLE "rules" (
+    "rule_id" INTEGER NOT NULL,
+    "query" TEXT NOT NULL,
+    PRIMARY KEY("rule_id" AUTOINCREMENT)
+);
+
+CREATE TABLE "rule_tags" (
+    "rule_id" INTEGER NOT NULL,
+    "tag" INTEGER NOT NULL,
+    FOREIGN KEY("rule_id") REFERENCES "rules"("rule_id") ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY("tag") REFERENCES "tags"("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    PRIMARY KEY ("rule_id", "tag")
+);
@@ -0,0 +1,9 @@
+CREATE TABLE "tag_mappings" (
+    "service" TEXT NOT NULL COLLATE NOCASE,
+    "source" TEXT NOT NULL COLLATE NOCASE,
+    "action" TEXT NOT NULL CHECK ("action" IN ('map', 'ignore', 'pass')),
+    "tag" INTEGER,
+    FOREIGN KEY("tag") REFERENCES "tags"("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    PRIMARY KEY ("service", "source"),
+    CHECK (("action" = 'map') = ("tag" IS NOT NULL))
+);
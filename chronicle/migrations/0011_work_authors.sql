@@ -0,0 +1,119 @@
+-- Migrations run inside a transaction with foreign keys enforced, so "works" can not be dropped
+-- while "work_tags" references it without cascading, both are copied aside and rebuilt.
+CREATE TABLE "old_works" AS
+SELECT
+    *
+FROM
+    "works";
+
+-- Dropping "works" also drops its AUTOINCREMENT counter, which is kept so ids of deleted works
+-- are not reused.
+CREATE TABLE "old_works_sequence" AS
+SELECT
+    "seq"
+FROM
+    "sqlite_sequence"
+WHERE
+    "name" = 'works';
+
+CREATE TABLE "old_work_tags" AS
+SELECT
+    *
+FROM
+    "work_tags";
+
+DROP TABLE "work_tags";
+
+DROP TABLE "works";
+
+CREATE TABLE "works" (
+    "path" TEXT NOT NULL UNIQUE,
+    "work_id" INTEGER NOT NULL,
+    "size" INTEGER NOT NULL,
+    "title" TEXT,
+    "url" TEXT,
+    "caption" TEXT,
+    "hash" INTEGER NOT NULL UNIQUE,
+    PRIMARY KEY("work_id" AUTOINCREMENT)
+);
+
+CREATE TABLE "work_tags" (
+    "tag" INTEGER NOT NULL,
+    "work_id" INTEGER NOT NULL,
+    FOREIGN KEY("work_id") REFERENCES "works"("work_id") ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY("tag") REFERENCES "tags"("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    UNIQUE("tag", "work_id"),
+    PRIMARY KEY ("tag", "work_id")
+);
+
+CREATE TABLE "work_authors" (
+    "work_id" INTEGER NOT NULL,
+    "author_id" INTEGER NOT NULL,
+    "role" TEXT NOT NULL DEFAULT 'author' COLLATE NOCASE,
+    FOREIGN KEY("work_id") REFERENCES "works"("work_id") ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY("author_id") REFERENCES "authors"("author_id") ON DELETE CASCADE ON UPDATE CASCADE,
+    PRIMARY KEY ("work_id", "author_id", "role")
+);
+
+CREATE INDEX "work_authors_author" ON "work_authors" ("author_id");
+
+INSERT INTO
+    "works" ("path", "work_id", "size", "title", "url", "caption", "hash")
+SELECT
+    "path",
+    "work_id",
+    "size",
+    "title",
+    "url",
+    "caption",
+    "hash"
+FROM
+    "old_works";
+
+INSERT INTO
+    "work_tags" ("tag", "work_id")
+SELECT
+    "tag",
+    "work_id"
+FROM
+    "old_work_tags";
+
+INSERT INTO
+    "work_authors" ("work_id", "author_id")
+SELECT
+    "work_id",
+    "author_id"
+FROM
+    "old_works"
+WHERE
+    "author_id" IS NOT NULL;
+
+DELETE FROM "sqlite_sequence"
+WHERE
+    "name" = 'works';
+
+INSERT INTO
+    "sqlite_sequence" ("name", "seq")
+SELECT
+    'works',
+    MAX("seq")
+FROM
+    (
+        SELECT
+            "seq"
+        FROM
+            "old_works_sequence"
+        UNION ALL
+        SELECT
+            MAX("work_id")
+        FROM
+            "works"
+    )
+HAVING
+    MAX("seq") IS NOT NULL;
+
+DROP TABLE "old_works_sequence";
+
+DROP TABLE "old_work_tags";
+
+DROP TABLE "old_works";
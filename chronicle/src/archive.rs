@@ -0,0 +1,238 @@
+pub mod bsky;
+pub mod tumblr;
+pub mod twitter;
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use sqlx::{Sqlite, Transaction};
+use uuid::Uuid;
+use zip::ZipArchive;
+
+use crate::{
+    author::AuthorPolicy,
+    models::Work,
+    record::{Record, RecordDetails},
+    Chronicle,
+};
+
+/// A file in an export along with the details of the post it was attached to.
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    /// The path of the file inside the export
+    pub name: String,
+    pub details: RecordDetails,
+}
+
+/// An export zip which works are read out of.
+pub struct Archive {
+    zip: ZipArchive<BufReader<File>>,
+    /// Set for archives extracted out of another archive, which are removed once read
+    temporary: Option<PathBuf>,
+}
+
+impl Drop for Archive {
+    fn drop(&mut self) {
+        if let Some(path) = &self.temporary {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl Archive {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, crate::Error> {
+        Ok(Self {
+            zip: ZipArchive::new(BufReader::new(File::open(path)?))?,
+            temporary: None,
+        })
+    }
+
+    /// Opens a zip stored inside this archive, extracting it into a temporary file as zips can
+    /// not be read without seeking.
+    pub fn open_nested(&mut self, name: &str) -> Result<Self, crate::Error> {
+        let path = std::env::temp_dir().join(format!("chronicle-{}.zip", Uuid::new_v4()));
+
+        let mut writer = BufWriter::new(File::create_new(&path)?);
+        io::copy(&mut self.zip.by_name(name)?, &mut writer)?;
+        writer.flush()?;
+
+        let mut nested = Self::open(&path).inspect_err(|_| {
+            let _ = std::fs::remove_file(&path);
+        })?;
+
+        nested.temporary = Some(path);
+
+        Ok(nested)
+    }
+
+    /// The paths of every file in the export.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.zip.file_names()
+    }
+
+    pub fn read(&mut self, name: &str) -> Result<Vec<u8>, crate::Error> {
+        let mut file = self.zip.by_name(name)?;
+        let mut data = Vec::with_capacity(file.size() as usize);
+
+        file.read_to_end(&mut data)?;
+
+        Ok(data)
+    }
+
+    pub fn read_to_string(&mut self, name: &str) -> Result<String, crate::Error> {
+        String::from_utf8(self.read(name)?)
+            .map_err(|_| crate::Error::Generic(format!("{name} is not valid utf-8")))
+    }
+
+    /// Creates a work from an entry with [`import_data`].
+    pub async fn import_entry(
+        &mut self,
+        chronicle: &Chronicle,
+        tx: &mut Transaction<'_, Sqlite>,
+        service: &str,
+        entry: &ArchiveEntry,
+        policy: &AuthorPolicy,
+    ) -> Result<Option<Work>, crate::Error> {
+        let data = self.read(&entry.name)?;
+
+        let extension = Path::new(&entry.name)
+            .extension()
+            .map(|s| format!(".{}", s.to_string_lossy()))
+            .unwrap_or_default();
+
+        import_data(
+            chronicle,
+            tx,
+            service,
+            &data,
+            &extension,
+            &entry.details,
+            policy,
+        )
+        .await
+    }
+}
+
+/// Creates a work from a file read out of an export, translating native tags through the mappings
+/// of `service`.
+///
+/// Returns `None` without storing the file if a work with the same contents already exists, so an
+/// export can be imported again after it has grown. A different file which only shares its hash
+/// with a work can not be stored and fails instead.
+pub async fn import_data(
+    chronicle: &Chronicle,
+    tx: &mut Transaction<'_, Sqlite>,
+    service: &str,
+    data: &[u8],
+    extension: &str,
+    details: &RecordDetails,
+    policy: &AuthorPolicy,
+) -> Result<Option<Work>, crate::Error> {
+    let hash: i32 = bytemuck::cast(crc32fast::hash(data));
+
+    if let Some(existing) = Work::get_by_hash(tx, hash).await? {
+        if is_stored(&chronicle.config.data_path, &existing, data) {
+            return Ok(None);
+        }
+
+        return Err(crate::Error::Generic(format!(
+            "file has the same hash as work {} but different contents",
+            existing.work_id
+        )));
+    }
+
+    let file_name = format!("{}{extension}", Uuid::new_v4());
+    let mut writer = BufWriter::new(File::create_new(
+        chronicle.config.data_path.join(&file_name),
+    )?);
+
+    writer.write_all(data)?;
+    writer.flush()?;
+
+    let record = Record {
+        path: PathBuf::from(&file_name),
+        size: data.len(),
+        hash,
+        details: details.clone(),
+    };
+
+    match Work::create_from_service_records(tx, service, vec![record], policy).await {
+        Ok(works) => Ok(works.into_iter().next()),
+        Err(error) => {
+            std::fs::remove_file(chronicle.config.data_path.join(&file_name))?;
+            Err(error)
+        }
+    }
+}
+
+/// Whether `data` is the file of `work`, comparing against the stored file when it is still there.
+fn is_stored(data_path: &Path, work: &Work, data: &[u8]) -> bool {
+    if work.size != data.len() as u64 {
+        return false;
+    }
+
+    match std::fs::read(data_path.join(&work.path)) {
+        Ok(stored) => stored == data,
+        Err(_) => true,
+    }
+}
+
+/// Removes the entries of posts which already have works with their url, returning how many were
+/// removed.
+///
+/// This is checked once before importing so every file of a post which was only partly imported
+/// is still attempted.
+pub async fn retain_new<T>(
+    tx: &mut Transaction<'_, Sqlite>,
+    entries: &mut Vec<T>,
+    details: impl Fn(&T) -> &RecordDetails,
+) -> Result<usize, crate::Error> {
+    let mut imported = HashMap::new();
+
+    for entry in entries.iter() {
+        if let Some(url) = &details(entry).url {
+            if !imported.contains_key(url) {
+                let exists = !Work::get_by_url(tx, url).await?.is_empty();
+                imported.insert(url.clone(), exists);
+            }
+        }
+    }
+
+    let before = entries.len();
+
+    entries.retain(|entry| details(entry).url.as_ref().is_none_or(|url| !imported[url]));
+
+    Ok(before - entries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::WorkId;
+
+    use super::*;
+
+    #[test]
+    fn test_is_stored() {
+        let data_path = tempfile::tempdir().unwrap();
+
+        std::fs::write(data_path.path().join("stored.png"), b"stored").unwrap();
+
+        let work = Work {
+            path: String::from("stored.png"),
+            work_id: WorkId(1),
+            size: 6,
+            title: None,
+            caption: None,
+            url: None,
+            hash: 0,
+        };
+
+        assert!(is_stored(data_path.path(), &work, b"stored"));
+        assert!(!is_stored(data_path.path(), &work, b"stolen"));
+        assert!(!is_stored(data_path.path(), &work, b"longer file"));
+    }
+}
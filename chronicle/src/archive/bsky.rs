@@ -0,0 +1,581 @@
+use std::collections::HashMap;
+
+use ciborium::Value;
+use ipld_core::cid::Cid;
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    import::{
+        bsky::{post_details, PostRecord},
+        caption_with_alt,
+        opengraph::extension,
+    },
+    record::RecordDetails,
+    HTTP_CLIENT,
+};
+
+const POST_COLLECTION: &str = "app.bsky.feed.post";
+
+/// The multicodec of blocks encoded as dag-cbor, the only kind repositories contain.
+const DAG_CBOR: u64 = 0x71;
+
+/// The tag dag-cbor stores links to other blocks with.
+const CID_TAG: u64 = 42;
+
+/// A repository exported as a CAR file by `com.atproto.sync.getRepo`.
+pub struct Repository {
+    pub did: String,
+    blocks: HashMap<Cid, Vec<u8>>,
+    /// The root of the merkle search tree holding the records
+    data: Cid,
+}
+
+/// A file embedded in a post in a repository, which is stored as a blob outside of the repository.
+#[derive(Debug, Clone)]
+pub struct BskyEntry {
+    pub blob: Cid,
+    /// The extension to store the blob with, including the leading `.`
+    pub extension: String,
+    pub details: RecordDetails,
+}
+
+/// The works found in a repository.
+#[derive(Debug, Default)]
+pub struct BskyExport {
+    pub entries: Vec<BskyEntry>,
+}
+
+/// Where an account's blobs can be downloaded from, resolved from its DID document.
+pub struct BskyIdentity {
+    pub did: String,
+    pub handle: String,
+    pub pds: Url,
+}
+
+fn invalid(message: &str) -> crate::Error {
+    crate::Error::Generic(format!("invalid repository: {message}"))
+}
+
+/// Reads an unsigned LEB128 varint, which CAR files prefix each section with.
+fn read_varint(data: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = data.split_first()?;
+        *data = rest;
+
+        value |= u64::from(byte & 0x7f) << shift;
+
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+fn decode(data: &[u8]) -> Result<Value, crate::Error> {
+    ciborium::from_reader(data).map_err(|err| invalid(&err.to_string()))
+}
+
+fn field<'v>(value: &'v Value, key: &str) -> Option<&'v Value> {
+    value
+        .as_map()?
+        .iter()
+        .find(|(k, _)| k.as_text() == Some(key))
+        .map(|(_, v)| v)
+}
+
+fn text<'v>(value: &'v Value, key: &str) -> Option<&'v str> {
+    field(value, key)?.as_text()
+}
+
+/// Reads a link to another block, which dag-cbor stores as the cid's bytes after a zero byte.
+fn link(value: &Value) -> Option<Cid> {
+    let bytes = match value {
+        Value::Tag(CID_TAG, inner) => inner.as_bytes()?,
+        _ => return None,
+    };
+
+    Cid::try_from(bytes.strip_prefix(&[0])?).ok()
+}
+
+impl Repository {
+    pub fn parse(mut car: &[u8]) -> Result<Self, crate::Error> {
+        let car = &mut car;
+
+        let header_length = read_varint(car).ok_or_else(|| invalid("missing header"))? as usize;
+
+        if car.len() < header_length {
+            return Err(invalid("truncated header"));
+        }
+
+        let (header, rest) = car.split_at(header_length);
+        *car = rest;
+
+        let header = decode(header)?;
+
+        let root = field(&header, "roots")
+            .and_then(Value::as_array)
+            .and_then(|roots| roots.first())
+            .and_then(link)
+            .ok_or_else(|| invalid("header has no root"))?;
+
+        let mut blocks = HashMap::new();
+
+        while !car.is_empty() {
+            let length = read_varint(car).ok_or_else(|| invalid("truncated block"))? as usize;
+
+            if car.len() < length {
+                return Err(invalid("truncated block"));
+            }
+
+            let (mut block, rest) = car.split_at(length);
+            *car = rest;
+
+            let cid = Cid::read_bytes(&mut block).map_err(|err| invalid(&err.to_string()))?;
+
+            if cid.codec() == DAG_CBOR {
+                blocks.insert(cid, block.to_vec());
+            }
+        }
+
+        let mut repository = Self {
+            did: String::new(),
+            blocks,
+            data: root,
+        };
+
+        let commit = repository.block(&root)?;
+
+        repository.did = text(&commit, "did")
+            .ok_or_else(|| invalid("commit has no did"))?
+            .to_owned();
+        repository.data = field(&commit, "data")
+            .and_then(link)
+            .ok_or_else(|| invalid("commit has no data"))?;
+
+        Ok(repository)
+    }
+
+    fn block_data(&self, cid: &Cid) -> Result<&[u8], crate::Error> {
+        self.blocks
+            .get(cid)
+            .map(Vec::as_slice)
+            .ok_or_else(|| invalid(&format!("missing block {cid}")))
+    }
+
+    fn block(&self, cid: &Cid) -> Result<Value, crate::Error> {
+        decode(self.block_data(cid)?)
+    }
+
+    /// Returns the key and cid of every record, walking the merkle search tree in key order.
+    pub fn records(&self) -> Result<Vec<(String, Cid)>, crate::Error> {
+        let mut records = Vec::new();
+
+        self.walk(&self.data, &mut records)?;
+
+        Ok(records)
+    }
+
+    fn walk(&self, node: &Cid, records: &mut Vec<(String, Cid)>) -> Result<(), crate::Error> {
+        let node = self.block(node)?;
+
+        if let Some(left) = field(&node, "l").and_then(link) {
+            self.walk(&left, records)?;
+        }
+
+        // Each key only stores what it does not share with the key before it.
+        let mut key = Vec::new();
+
+        for entry in field(&node, "e")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let prefix = field(entry, "p")
+                .and_then(Value::as_integer)
+                .and_then(|prefix| usize::try_from(prefix).ok())
+                .unwrap_or_default();
+            let suffix = field(entry, "k")
+                .and_then(Value::as_bytes)
+                .ok_or_else(|| invalid("tree entry has no key"))?;
+
+            key.truncate(prefix);
+            key.extend_from_slice(suffix);
+
+            if let Some(value) = field(entry, "v").and_then(link) {
+                records.push((String::from_utf8_lossy(&key).into_owned(), value));
+            }
+
+            if let Some(right) = field(entry, "t").and_then(link) {
+                self.walk(&right, records)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A file embedded in a post, which is stored as a blob outside of the repository.
+struct EmbeddedBlob {
+    cid: Cid,
+    mime_type: String,
+    alt: Option<String>,
+}
+
+impl EmbeddedBlob {
+    fn parse(blob: &Value, alt: Option<&str>) -> Option<Self> {
+        // Blobs written before the current format only store the cid as text.
+        let cid = field(blob, "ref")
+            .and_then(link)
+            .or_else(|| text(blob, "cid").and_then(|cid| Cid::try_from(cid).ok()))?;
+
+        Some(Self {
+            cid,
+            mime_type: text(blob, "mimeType").unwrap_or_default().to_owned(),
+            alt: alt.map(str::to_owned),
+        })
+    }
+}
+
+/// Returns every image, video and link thumbnail embedded in a post, quoted posts are separate
+/// records so their files are imported with them.
+fn embedded_blobs(embed: &Value) -> Vec<EmbeddedBlob> {
+    match text(embed, "$type") {
+        Some("app.bsky.embed.images") => field(embed, "images")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|image| EmbeddedBlob::parse(field(image, "image")?, text(image, "alt")))
+            .collect(),
+        Some("app.bsky.embed.video") => field(embed, "video")
+            .and_then(|video| EmbeddedBlob::parse(video, text(embed, "alt")))
+            .into_iter()
+            .collect(),
+        Some("app.bsky.embed.external") => field(embed, "external")
+            .and_then(|external| field(external, "thumb"))
+            .and_then(|thumb| EmbeddedBlob::parse(thumb, None))
+            .into_iter()
+            .collect(),
+        Some("app.bsky.embed.recordWithMedia") => field(embed, "media")
+            .map(embedded_blobs)
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+impl BskyExport {
+    /// Reads the posts with embedded files from a repository, crediting them to `handle`.
+    pub fn read(repository: &Repository, handle: &str) -> Result<Self, crate::Error> {
+        let handle = handle.to_lowercase();
+
+        let mut export = Self::default();
+
+        for (key, cid) in repository.records()? {
+            let Some((POST_COLLECTION, rkey)) = key.split_once('/') else {
+                continue;
+            };
+
+            let record = repository.block(&cid)?;
+
+            let blobs = field(&record, "embed")
+                .map(embedded_blobs)
+                .unwrap_or_default();
+
+            if blobs.is_empty() {
+                continue;
+            }
+
+            let post: PostRecord = ciborium::from_reader(repository.block_data(&cid)?)
+                .map_err(|err| invalid(&err.to_string()))?;
+
+            let url = Url::parse(&format!("https://bsky.app/profile/{handle}/post/{rkey}"))?;
+
+            let details = post_details(&handle, url, post);
+
+            for EmbeddedBlob {
+                cid,
+                mime_type,
+                alt,
+            } in blobs
+            {
+                let blob_url = Url::parse(&format!("blob:{cid}"))?;
+
+                export.entries.push(BskyEntry {
+                    blob: cid,
+                    extension: extension(&blob_url, &mime_type),
+                    details: RecordDetails {
+                        caption: caption_with_alt(details.caption.as_deref(), alt.as_deref()),
+                        ..details.clone()
+                    },
+                });
+            }
+        }
+
+        Ok(export)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidDocument {
+    #[serde(default)]
+    also_known_as: Vec<String>,
+    #[serde(default)]
+    service: Vec<DidService>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidService {
+    id: String,
+    service_endpoint: String,
+}
+
+impl BskyIdentity {
+    /// Looks up the handle and personal data server of an account from its DID document.
+    pub async fn resolve(did: &str) -> Result<Self, crate::Error> {
+        let document_url = if did.starts_with("did:plc:") {
+            format!("https://plc.directory/{did}")
+        } else if let Some(host) = did.strip_prefix("did:web:") {
+            format!("https://{host}/.well-known/did.json")
+        } else {
+            return Err(crate::Error::Generic(format!("unsupported did {did}")));
+        };
+
+        let document: DidDocument = HTTP_CLIENT
+            .get(document_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let handle = document
+            .also_known_as
+            .iter()
+            .find_map(|alias| alias.strip_prefix("at://"))
+            .unwrap_or(did)
+            .to_owned();
+
+        let Some(pds) = document
+            .service
+            .iter()
+            .find(|service| service.id.ends_with("#atproto_pds"))
+        else {
+            return Err(crate::Error::Generic(format!(
+                "{did} does not have a personal data server"
+            )));
+        };
+
+        Ok(Self {
+            did: did.to_owned(),
+            handle,
+            pds: Url::parse(&pds.service_endpoint)?,
+        })
+    }
+
+    /// The url to download a blob of this account from its server.
+    pub fn blob_url(&self, blob: &Cid) -> Result<Url, crate::Error> {
+        let mut url = self.pds.join("xrpc/com.atproto.sync.getBlob")?;
+
+        url.query_pairs_mut()
+            .append_pair("did", &self.did)
+            .append_pair("cid", &blob.to_string());
+
+        Ok(url)
+    }
+
+    pub async fn fetch_blob(&self, blob: &Cid) -> Result<Vec<u8>, crate::Error> {
+        Ok(HTTP_CLIENT
+            .get(self.blob_url(blob)?)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?
+            .to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ipld_core::cid::multihash::Multihash;
+
+    use super::*;
+
+    fn cid(seed: u8) -> Cid {
+        Cid::new_v1(DAG_CBOR, Multihash::wrap(0x12, &[seed; 32]).unwrap())
+    }
+
+    fn cid_link(cid: &Cid) -> Value {
+        let mut bytes = vec![0];
+        bytes.extend(cid.to_bytes());
+
+        Value::Tag(CID_TAG, Box::new(Value::Bytes(bytes)))
+    }
+
+    fn map(fields: Vec<(&str, Value)>) -> Value {
+        Value::Map(
+            fields
+                .into_iter()
+                .map(|(key, value)| (Value::Text(key.to_owned()), value))
+                .collect(),
+        )
+    }
+
+    fn encode(value: &Value) -> Vec<u8> {
+        let mut data = Vec::new();
+        ciborium::into_writer(value, &mut data).unwrap();
+        data
+    }
+
+    fn push_varint(car: &mut Vec<u8>, mut value: usize) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+
+            if value == 0 {
+                car.push(byte);
+                break;
+            }
+
+            car.push(byte | 0x80);
+        }
+    }
+
+    fn car(root: &Cid, blocks: &[(Cid, Value)]) -> Vec<u8> {
+        let mut car = Vec::new();
+
+        let header = encode(&map(vec![
+            ("version", Value::Integer(1.into())),
+            ("roots", Value::Array(vec![cid_link(root)])),
+        ]));
+
+        push_varint(&mut car, header.len());
+        car.extend(header);
+
+        for (cid, block) in blocks {
+            let mut section = cid.to_bytes();
+            section.extend(encode(block));
+
+            push_varint(&mut car, section.len());
+            car.extend(section);
+        }
+
+        car
+    }
+
+    #[test]
+    fn test_read() {
+        let image = Cid::new_v1(0x55, Multihash::wrap(0x12, &[9; 32]).unwrap());
+
+        let post = map(vec![
+            ("$type", Value::Text(String::from("app.bsky.feed.post"))),
+            ("text", Value::Text(String::from("new sketch #art"))),
+            (
+                "facets",
+                Value::Array(vec![map(vec![(
+                    "features",
+                    Value::Array(vec![map(vec![
+                        (
+                            "$type",
+                            Value::Text(String::from("app.bsky.richtext.facet#tag")),
+                        ),
+                        ("tag", Value::Text(String::from("art"))),
+                    ])]),
+                )])]),
+            ),
+            (
+                "embed",
+                map(vec![
+                    ("$type", Value::Text(String::from("app.bsky.embed.images"))),
+                    (
+                        "images",
+                        Value::Array(vec![map(vec![
+                            ("alt", Value::Text(String::from("a sketch"))),
+                            (
+                                "image",
+                                map(vec![
+                                    ("$type", Value::Text(String::from("blob"))),
+                                    ("ref", cid_link(&image)),
+                                    ("mimeType", Value::Text(String::from("image/jpeg"))),
+                                ]),
+                            ),
+                        ])]),
+                    ),
+                ]),
+            ),
+        ]);
+
+        let text_post = map(vec![("text", Value::Text(String::from("no images")))]);
+
+        let tree = map(vec![
+            ("l", Value::Null),
+            (
+                "e",
+                Value::Array(vec![
+                    map(vec![
+                        ("p", Value::Integer(0.into())),
+                        ("k", Value::Bytes(b"app.bsky.feed.post/3aaa".to_vec())),
+                        ("v", cid_link(&cid(2))),
+                        ("t", Value::Null),
+                    ]),
+                    map(vec![
+                        ("p", Value::Integer(21.into())),
+                        ("k", Value::Bytes(b"bb".to_vec())),
+                        ("v", cid_link(&cid(3))),
+                        ("t", Value::Null),
+                    ]),
+                ]),
+            ),
+        ]);
+
+        let commit = map(vec![
+            ("did", Value::Text(String::from("did:plc:someone"))),
+            ("version", Value::Integer(3.into())),
+            ("data", cid_link(&cid(1))),
+        ]);
+
+        let car = car(
+            &cid(0),
+            &[
+                (cid(0), commit),
+                (cid(1), tree),
+                (cid(2), post),
+                (cid(3), text_post),
+            ],
+        );
+
+        let repository = Repository::parse(&car).unwrap();
+
+        assert_eq!(repository.did, "did:plc:someone");
+        assert_eq!(
+            repository.records().unwrap(),
+            vec![
+                (String::from("app.bsky.feed.post/3aaa"), cid(2)),
+                (String::from("app.bsky.feed.post/3abb"), cid(3)),
+            ]
+        );
+
+        let BskyExport { entries } = BskyExport::read(&repository, "Someone.bsky.social").unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].blob, image);
+        assert_eq!(entries[0].extension, ".jpg");
+
+        let details = &entries[0].details;
+
+        assert_eq!(
+            details.url.as_ref().map(Url::as_str),
+            Some("https://bsky.app/profile/someone.bsky.social/post/3aaa")
+        );
+        assert_eq!(
+            details.caption.as_deref(),
+            Some("new sketch #art\n\na sketch")
+        );
+        assert_eq!(details.native_tags, vec!["art"]);
+    }
+}
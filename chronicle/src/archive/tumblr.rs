@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use url::Url;
+
+use crate::import::{html_to_text, tumblr::post_details, unescape_html};
+
+use super::{Archive, ArchiveEntry};
+
+lazy_static! {
+    static ref IMAGE_SOURCE: Regex = Regex::new(r#"(?i)<img[^>]*\ssrc\s*=\s*"([^"]*)""#).unwrap();
+    static ref TAG: Regex = Regex::new(r#"(?is)<span class="tag">(.*?)</span>"#).unwrap();
+    static ref FOOTER: Regex = Regex::new(r#"(?is)<div id="footer">.*"#).unwrap();
+    static ref HEAD: Regex = Regex::new(r#"(?is)<head>.*?</head>"#).unwrap();
+}
+
+/// The works found in a Tumblr blog export.
+pub struct TumblrExport {
+    /// The archive the entries are read from, which is the `posts.zip` inside the export if it has
+    /// one
+    pub archive: Archive,
+    pub entries: Vec<ArchiveEntry>,
+}
+
+/// The parts of a post read from its page in the export.
+#[derive(Debug, PartialEq, Eq)]
+struct ExportedPost {
+    /// The file names of the images in the post, in order
+    images: Vec<String>,
+    text: String,
+    tags: Vec<String>,
+}
+
+/// Returns the final path segment of a path or url.
+fn basename(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+impl ExportedPost {
+    fn parse(html: &str) -> Self {
+        let images = IMAGE_SOURCE
+            .captures_iter(html)
+            .map(|captures| basename(&captures[1]).to_owned())
+            .collect();
+
+        let tags = TAG
+            .captures_iter(html)
+            .map(|captures| unescape_html(captures[1].trim()))
+            .filter(|tag| !tag.is_empty())
+            .collect();
+
+        let body = HEAD.replace(html, "");
+        let body = FOOTER.replace(&body, "");
+
+        Self {
+            images,
+            text: html_to_text(&body),
+            tags,
+        }
+    }
+}
+
+/// Groups the files in the media folder by the id of the post they belong to, which is the start
+/// of their name up to an `_` for posts with several images.
+fn media_files(archive: &Archive) -> HashMap<String, Vec<String>> {
+    let mut files: HashMap<String, Vec<String>> = HashMap::new();
+
+    for name in archive.names() {
+        let mut segments = name.rsplit('/');
+
+        let (Some(file), Some("media")) = (segments.next(), segments.next()) else {
+            continue;
+        };
+
+        let stem = file.split('.').next().unwrap_or(file);
+        let id = stem.split('_').next().unwrap_or(stem);
+
+        files
+            .entry(id.to_owned())
+            .or_default()
+            .push(name.to_owned());
+    }
+
+    for files in files.values_mut() {
+        files.sort();
+    }
+
+    files
+}
+
+impl TumblrExport {
+    /// Reads the posts of `blog` from a blog export, which may still contain the `posts.zip` it
+    /// was downloaded with.
+    pub fn read(mut archive: Archive, blog: &str) -> Result<Self, crate::Error> {
+        let nested = archive
+            .names()
+            .find(|name| basename(name) == "posts.zip")
+            .map(str::to_owned);
+
+        if let Some(nested) = nested {
+            return Self::read(archive.open_nested(&nested)?, blog);
+        }
+
+        let blog = blog.to_lowercase();
+        let blog_url = Url::parse(&format!("https://{blog}.tumblr.com/")).ok();
+
+        let mut media_files = media_files(&archive);
+
+        let mut pages: Vec<(String, String)> = archive
+            .names()
+            .filter(|name| name.ends_with(".html") && name.contains("html/"))
+            .filter_map(|name| {
+                let id = basename(name).trim_end_matches(".html");
+
+                id.chars()
+                    .all(|c| c.is_ascii_digit())
+                    .then(|| (id.to_owned(), name.to_owned()))
+            })
+            .collect();
+
+        pages.sort();
+
+        let mut entries = Vec::new();
+
+        for (id, page) in pages {
+            let Some(mut files) = media_files.remove(&id) else {
+                continue;
+            };
+
+            let post = ExportedPost::parse(&archive.read_to_string(&page)?);
+
+            // The page links the images in order, any files it does not link go last.
+            files.sort_by_key(|name| {
+                post.images
+                    .iter()
+                    .position(|image| image == basename(name))
+                    .unwrap_or(usize::MAX)
+            });
+
+            let summary = post.text.lines().next().unwrap_or_default().to_owned();
+
+            let details = post_details(
+                blog.clone(),
+                blog_url.clone(),
+                Url::parse(&format!("https://{blog}.tumblr.com/post/{id}")).ok(),
+                summary,
+                post.text,
+                post.tags,
+            );
+
+            entries.extend(files.into_iter().map(|name| ArchiveEntry {
+                name,
+                details: details.clone(),
+            }));
+        }
+
+        Ok(Self { archive, entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_post() {
+        let page = r#"<!DOCTYPE HTML><html><head><meta charset="utf-8"><title>ignored</title></head><body><img src="../../media/123_1.png"/><img src="../../media/123_0.jpg"/><p>Two sketches &amp; more</p><p>second line</p><div id="footer"><span id="timestamp"> March 3rd, 2024 11:01pm </span><span class="tag">art</span><span class="tag">my oc</span></div></body></html>"#;
+
+        assert_eq!(
+            ExportedPost::parse(page),
+            ExportedPost {
+                images: vec![String::from("123_1.png"), String::from("123_0.jpg")],
+                text: String::from("Two sketches & more\n\nsecond line"),
+                tags: vec![String::from("art"), String::from("my oc")],
+            }
+        );
+    }
+}
@@ -0,0 +1,454 @@
+use std::collections::HashMap;
+
+use serde::{de::DeserializeOwned, Deserialize};
+use url::Url;
+
+use crate::{
+    author::{AuthorCredit, AuthorQuery},
+    import::unescape_html,
+    record::RecordDetails,
+};
+
+use super::{Archive, ArchiveEntry};
+
+/// The works found in a Twitter archive.
+#[derive(Debug, Default)]
+pub struct TwitterArchive {
+    /// The media of the archive owner's tweets and of liked tweets which the archive has media for
+    pub entries: Vec<ArchiveEntry>,
+    /// Liked tweets whose media is not in the archive, these can only be imported online
+    pub liked: Vec<Url>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountEntry {
+    account: Account,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Account {
+    username: String,
+}
+
+#[derive(Deserialize)]
+struct TweetEntry {
+    tweet: Tweet,
+}
+
+#[derive(Deserialize)]
+struct Tweet {
+    id_str: String,
+    full_text: String,
+    #[serde(default)]
+    entities: Entities,
+    extended_entities: Option<Entities>,
+}
+
+#[derive(Deserialize, Default)]
+struct Entities {
+    #[serde(default)]
+    hashtags: Vec<Hashtag>,
+    #[serde(default)]
+    urls: Vec<UrlEntity>,
+    #[serde(default)]
+    media: Vec<Media>,
+}
+
+#[derive(Deserialize)]
+struct Hashtag {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct UrlEntity {
+    url: String,
+    expanded_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Media {
+    url: String,
+    media_url_https: String,
+    video_info: Option<VideoInfo>,
+}
+
+#[derive(Deserialize)]
+struct VideoInfo {
+    variants: Vec<VideoVariant>,
+}
+
+#[derive(Deserialize)]
+struct VideoVariant {
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct LikeEntry {
+    like: Like,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Like {
+    tweet_id: String,
+    full_text: Option<String>,
+}
+
+/// Parses one of the `window.YTD.<name>.part0 = [...]` files the archive stores its data in.
+fn parse_data_file<T: DeserializeOwned>(text: &str) -> Result<Vec<T>, crate::Error> {
+    let json = text.find('[').map(|start| &text[start..]).unwrap_or(text);
+
+    Ok(serde_json::from_str(json)?)
+}
+
+/// Returns the final path segment of a url, without its query.
+fn basename(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+impl Tweet {
+    /// The text of the tweet with media links removed and other links expanded, as it is shown on
+    /// Twitter.
+    fn text(&self) -> String {
+        let mut text = self.full_text.clone();
+
+        for media in self.media() {
+            text = text.replace(&media.url, "");
+        }
+
+        for url in self.entities.urls.iter() {
+            if let Some(expanded) = &url.expanded_url {
+                text = text.replace(&url.url, expanded);
+            }
+        }
+
+        unescape_html(text.trim())
+    }
+
+    fn media(&self) -> &[Media] {
+        match &self.extended_entities {
+            Some(extended) if !extended.media.is_empty() => &extended.media,
+            _ => &self.entities.media,
+        }
+    }
+
+    fn is_retweet(&self) -> bool {
+        self.full_text.starts_with("RT @")
+    }
+}
+
+impl Media {
+    /// The names the archive may have stored this media under, after the `<tweet id>-` prefix.
+    fn file_names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(basename(&self.media_url_https)).chain(
+            self.video_info
+                .iter()
+                .flat_map(|info| info.variants.iter().map(|variant| basename(&variant.url))),
+        )
+    }
+}
+
+/// Groups the files in the media folders by the id of the tweet they belong to.
+fn media_files(archive: &Archive) -> HashMap<String, Vec<String>> {
+    let mut files: HashMap<String, Vec<String>> = HashMap::new();
+
+    for name in archive.names() {
+        let mut segments = name.rsplit('/');
+
+        let (Some(file), Some(folder)) = (segments.next(), segments.next()) else {
+            continue;
+        };
+
+        if !matches!(folder, "tweets_media" | "tweet_media" | "likes_media") {
+            continue;
+        }
+
+        if let Some((id, _)) = file.split_once('-') {
+            files
+                .entry(id.to_owned())
+                .or_default()
+                .push(name.to_owned());
+        }
+    }
+
+    files
+}
+
+/// Finds the data file of the archive with the given name, archives have put it in different
+/// folders over time.
+fn data_file(archive: &Archive, file: &str) -> Option<String> {
+    archive
+        .names()
+        .find(|name| *name == file || name.ends_with(&format!("/{file}")))
+        .map(str::to_owned)
+}
+
+/// Orders the files of a tweet to match the order of its media, leaving unknown files at the end.
+fn order_files(files: &mut [String], tweet: &Tweet) {
+    let position = |name: &str| {
+        let stored = basename(name)
+            .split_once('-')
+            .map(|(_, stored)| stored)
+            .unwrap_or_default();
+
+        tweet
+            .media()
+            .iter()
+            .position(|media| media.file_names().any(|file_name| file_name == stored))
+            .unwrap_or(usize::MAX)
+    };
+
+    files.sort_by_key(|name| position(name));
+}
+
+impl TwitterArchive {
+    /// Reads the tweets and likes of a Twitter archive, pairing them with the media bundled in it.
+    pub fn read(archive: &mut Archive) -> Result<Self, crate::Error> {
+        let mut media_files = media_files(archive);
+
+        let Some(account) = data_file(archive, "account.js") else {
+            return Err(crate::Error::Generic(String::from(
+                "archive does not contain account.js",
+            )));
+        };
+
+        let Some(AccountEntry {
+            account: Account { username },
+        }) = parse_data_file(&archive.read_to_string(&account)?)?
+            .into_iter()
+            .next()
+        else {
+            return Err(crate::Error::Generic(String::from(
+                "account.js does not contain an account",
+            )));
+        };
+
+        let author = AuthorCredit {
+            url: Url::parse(&format!("https://x.com/{username}")).ok(),
+            ..AuthorCredit::new(AuthorQuery::Name(username.clone()))
+        };
+
+        let mut result = Self::default();
+
+        if let Some(tweets) = data_file(archive, "tweets.js").or(data_file(archive, "tweet.js")) {
+            let tweets: Vec<TweetEntry> = parse_data_file(&archive.read_to_string(&tweets)?)?;
+
+            for TweetEntry { tweet } in tweets {
+                if tweet.is_retweet() {
+                    continue;
+                }
+
+                let Some(mut files) = media_files.remove(&tweet.id_str) else {
+                    continue;
+                };
+
+                order_files(&mut files, &tweet);
+
+                let text = tweet.text();
+
+                let details = RecordDetails {
+                    title: None,
+                    url: Url::parse(&format!("https://x.com/{username}/status/{}", tweet.id_str))
+                        .ok(),
+                    authors: vec![author.clone()],
+                    caption: (!text.is_empty()).then_some(text),
+                    tags: Vec::new(),
+                    native_tags: tweet
+                        .entities
+                        .hashtags
+                        .iter()
+                        .map(|hashtag| hashtag.text.clone())
+                        .collect(),
+                };
+
+                result
+                    .entries
+                    .extend(files.into_iter().map(|name| ArchiveEntry {
+                        name,
+                        details: details.clone(),
+                    }));
+            }
+        }
+
+        if let Some(likes) = data_file(archive, "like.js") {
+            let likes: Vec<LikeEntry> = parse_data_file(&archive.read_to_string(&likes)?)?;
+
+            for LikeEntry { like } in likes {
+                // Likes do not record who wrote the tweet, which twitter resolves by itself.
+                let url = Url::parse(&format!("https://x.com/i/status/{}", like.tweet_id))?;
+
+                let Some(files) = media_files.remove(&like.tweet_id) else {
+                    result.liked.push(url);
+                    continue;
+                };
+
+                let details = RecordDetails {
+                    url: Some(url),
+                    caption: like.full_text.map(|text| unescape_html(&text)),
+                    ..Default::default()
+                };
+
+                result
+                    .entries
+                    .extend(files.into_iter().map(|name| ArchiveEntry {
+                        name,
+                        details: details.clone(),
+                    }));
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use crate::{
+        archive::retain_new,
+        models::Work,
+        utils::{test_pool, test_work},
+    };
+
+    use super::*;
+
+    const ACCOUNT: &str = r#"window.YTD.account.part0 = [
+  { "account": { "email": "someone@example.com", "username": "someone", "accountId": "1" } }
+]"#;
+
+    const TWEETS: &str = r#"window.YTD.tweets.part0 = [
+  {
+    "tweet": {
+      "id_str": "100",
+      "full_text": "Two sketches &amp; a link https://t.co/link #art https://t.co/media",
+      "entities": {
+        "hashtags": [{ "text": "art" }],
+        "urls": [{ "url": "https://t.co/link", "expanded_url": "https://example.com" }],
+        "media": [{ "url": "https://t.co/media", "media_url_https": "https://pbs.twimg.com/media/First.jpg" }]
+      },
+      "extended_entities": {
+        "media": [
+          { "url": "https://t.co/media", "media_url_https": "https://pbs.twimg.com/media/First.jpg" },
+          { "url": "https://t.co/media", "media_url_https": "https://pbs.twimg.com/media/Second.png" }
+        ]
+      }
+    }
+  },
+  { "tweet": { "id_str": "101", "full_text": "no media", "entities": {} } },
+  { "tweet": { "id_str": "102", "full_text": "RT @other: shared", "entities": {} } }
+]"#;
+
+    const LIKES: &str = r#"window.YTD.like.part0 = [
+  { "like": { "tweetId": "200", "fullText": "liked", "expandedUrl": "https://twitter.com/i/web/status/200" } },
+  { "like": { "tweetId": "201" } }
+]"#;
+
+    fn archive() -> (tempfile::NamedTempFile, Archive) {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut zip = ZipWriter::new(file.reopen().unwrap());
+
+        for (name, contents) in [
+            ("data/account.js", ACCOUNT),
+            ("data/tweets.js", TWEETS),
+            ("data/like.js", LIKES),
+            ("data/tweets_media/100-Second.png", "second"),
+            ("data/tweets_media/100-First.jpg", "first"),
+            ("data/tweets_media/102-Shared.jpg", "shared"),
+            ("data/likes_media/200-Liked.jpg", "liked"),
+        ] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+
+        zip.finish().unwrap();
+
+        let archive = Archive::open(file.path()).unwrap();
+
+        (file, archive)
+    }
+
+    #[test]
+    fn test_read() {
+        let (_file, mut archive) = archive();
+
+        let TwitterArchive { entries, liked } = TwitterArchive::read(&mut archive).unwrap();
+
+        let names: Vec<_> = entries.iter().map(|entry| entry.name.as_str()).collect();
+
+        assert_eq!(
+            names,
+            vec![
+                "data/tweets_media/100-First.jpg",
+                "data/tweets_media/100-Second.png",
+                "data/likes_media/200-Liked.jpg",
+            ]
+        );
+
+        let details = &entries[0].details;
+
+        assert_eq!(
+            details.caption.as_deref(),
+            Some("Two sketches & a link https://example.com #art")
+        );
+        assert_eq!(
+            details.url.as_ref().map(Url::as_str),
+            Some("https://x.com/someone/status/100")
+        );
+        assert_eq!(details.native_tags, vec!["art"]);
+        assert!(matches!(
+            &details.authors[0].query,
+            AuthorQuery::Name(name) if name == "someone"
+        ));
+
+        assert!(entries[2].details.authors.is_empty());
+        assert_eq!(entries[2].details.caption.as_deref(), Some("liked"));
+
+        assert_eq!(
+            liked,
+            vec![Url::parse("https://x.com/i/status/201").unwrap()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reimport_likes() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        // Likes fetched online are stored with the url the service returns, which names the author.
+        for url in [
+            "https://x.com/Liked/status/200",
+            "https://twitter.com/other/status/201",
+        ] {
+            let details = RecordDetails {
+                url: Some(Url::parse(url).unwrap()),
+                ..Default::default()
+            };
+
+            test_work(&mut tx, url, details).await;
+        }
+
+        let (_file, mut archive) = archive();
+
+        let TwitterArchive { mut entries, liked } = TwitterArchive::read(&mut archive).unwrap();
+
+        let skipped = retain_new(&mut tx, &mut entries, |entry| &entry.details)
+            .await
+            .unwrap();
+
+        assert_eq!(skipped, 1);
+        assert!(entries
+            .iter()
+            .all(|entry| !entry.name.starts_with("data/likes_media/")));
+
+        for url in liked {
+            assert!(!Work::get_by_url(&mut tx, &url).await.unwrap().is_empty());
+        }
+    }
+}
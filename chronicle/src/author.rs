@@ -1,10 +1,11 @@
-use std::{convert::Infallible, str::FromStr};
+use std::{convert::Infallible, fmt::Display, str::FromStr, sync::Arc};
 
 use sqlx::{Sqlite, Transaction};
 use url::Url;
 
 use crate::{
-    models::{Author, AuthorId, AuthorName, AuthorUrl},
+    canonical::{canonicalize, path_segments},
+    models::{Author, AuthorId, AuthorName, AuthorUrl, ModelKind, Tag, Work, WorkAuthor},
     Chronicle,
 };
 
@@ -29,6 +30,231 @@ impl FromStr for AuthorQuery {
     }
 }
 
+impl Display for AuthorQuery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthorQuery::Name(name) => write!(f, "{name}"),
+            AuthorQuery::Id(id) => write!(f, "{id}"),
+            AuthorQuery::Url(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+/// The role an author is credited with when none is given.
+pub const DEFAULT_ROLE: &str = "author";
+
+/// An author to credit on a work along with their role, and optionally a url to add to them.
+#[derive(Debug, Clone)]
+pub struct AuthorCredit {
+    pub query: AuthorQuery,
+    pub role: String,
+    pub url: Option<Url>,
+}
+
+impl AuthorCredit {
+    pub fn new(query: AuthorQuery) -> Self {
+        Self {
+            query,
+            role: DEFAULT_ROLE.to_owned(),
+            url: None,
+        }
+    }
+}
+
+impl FromStr for AuthorCredit {
+    type Err = Infallible;
+
+    /// Parses either `query` or `role=query`.
+    fn from_str(credit: &str) -> Result<Self, Self::Err> {
+        if let Some((role, query)) = credit.split_once('=') {
+            if !role.is_empty()
+                && role
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Ok(Self {
+                    role: role.to_lowercase(),
+                    ..Self::new(AuthorQuery::from_str(query)?)
+                });
+            }
+        }
+
+        Ok(Self::new(AuthorQuery::from_str(credit)?))
+    }
+}
+
+/// An author which an [`AuthorCredit`] could refer to, passed to [`AuthorPolicy::Callback`].
+pub struct AuthorCandidate {
+    pub author: Author,
+    pub names: Vec<String>,
+    pub urls: Vec<String>,
+}
+
+/// The decision made by an [`AuthorPolicy::Callback`].
+pub enum AuthorChoice {
+    /// Credit an existing author
+    Existing(AuthorId),
+    /// Create a new author for the credit
+    Create,
+    /// Leave the credit off the work
+    Skip,
+}
+
+pub type AuthorCallback =
+    dyn Fn(&AuthorCredit, &[AuthorCandidate]) -> Result<AuthorChoice, crate::Error> + Send + Sync;
+
+/// How a credit is resolved when its query matches several authors, or an id or url matches none.
+///
+/// Names which match no author always create a new author.
+#[derive(Clone, Default)]
+pub enum AuthorPolicy {
+    /// Fail with [`crate::Error::Ambiguous`] or [`crate::Error::NotFound`]
+    #[default]
+    Error,
+    /// Pick the only candidate which already has the credit's url, failing otherwise
+    PickByUrl,
+    /// Create a new author, ids can not be created and still fail
+    Create,
+    /// Let the callback decide, it is also called with no candidates when nothing matched
+    Callback(Arc<AuthorCallback>),
+}
+
+impl AuthorCredit {
+    /// Finds the author this credit refers to, following `policy` unless exactly one author
+    /// matches. Returns `None` if the policy chose to skip the credit.
+    pub async fn resolve(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        policy: &AuthorPolicy,
+    ) -> Result<Option<Author>, crate::Error> {
+        let mut authors = Author::get(tx, &self.query).await?;
+
+        if authors.len() == 1 {
+            return Ok(authors.pop());
+        }
+
+        if authors.is_empty() {
+            if let AuthorQuery::Name(name) = &self.query {
+                return Ok(Some(Author::create(tx, name).await?));
+            }
+        }
+
+        match policy {
+            AuthorPolicy::Error => Err(self.unresolved(&authors)),
+            AuthorPolicy::PickByUrl => {
+                let Some(url) = &self.url else {
+                    return Err(self.unresolved(&authors));
+                };
+
+                let url = canonicalize(url).to_string();
+
+                let mut matching = Vec::new();
+
+                for author in authors.iter() {
+                    if author
+                        .get_author_urls(tx)
+                        .await?
+                        .iter()
+                        .any(|author_url| author_url.url == url)
+                    {
+                        matching.push(author.author_id);
+                    }
+                }
+
+                match matching.as_slice() {
+                    [author_id] => Author::get_by_id(tx, author_id).await,
+                    _ => Err(self.unresolved(&authors)),
+                }
+            }
+            AuthorPolicy::Create => self.create(tx).await.map(Some),
+            AuthorPolicy::Callback(callback) => {
+                let mut candidates = Vec::with_capacity(authors.len());
+
+                for author in authors {
+                    let names = author.get_author_names(tx).await?;
+                    let urls = author.get_author_urls(tx).await?;
+
+                    candidates.push(AuthorCandidate {
+                        author,
+                        names: names.into_iter().map(|name| name.name).collect(),
+                        urls: urls.into_iter().map(|url| url.url).collect(),
+                    });
+                }
+
+                match callback(self, &candidates)? {
+                    AuthorChoice::Existing(author_id) => Author::get_by_id(tx, &author_id).await,
+                    AuthorChoice::Create => self.create(tx).await.map(Some),
+                    AuthorChoice::Skip => Ok(None),
+                }
+            }
+        }
+    }
+
+    fn unresolved(&self, authors: &[Author]) -> crate::Error {
+        if authors.is_empty() {
+            crate::Error::NotFound {
+                kind: ModelKind::Author,
+            }
+        } else {
+            crate::Error::Ambiguous {
+                kind: ModelKind::Author,
+                identifier: self.query.to_string(),
+                candidates: authors.iter().map(|author| author.author_id.0).collect(),
+            }
+        }
+    }
+
+    /// Creates a new author for this credit, named after the query or the last part of its url.
+    async fn create(&self, tx: &mut Transaction<'_, Sqlite>) -> Result<Author, crate::Error> {
+        let name = match &self.query {
+            AuthorQuery::Name(name) => name.clone(),
+            AuthorQuery::Url(url) => path_segments(url)
+                .last()
+                .copied()
+                .or(url.host_str())
+                .unwrap_or(url.as_str())
+                .to_owned(),
+            AuthorQuery::Id(_) => {
+                return Err(crate::Error::NotFound {
+                    kind: ModelKind::Author,
+                })
+            }
+        };
+
+        let author = Author::create(tx, &name).await?;
+
+        if let AuthorQuery::Url(url) = &self.query {
+            author.add_url(tx, url).await?;
+        }
+
+        Ok(author)
+    }
+}
+
+/// What happens to an author's works when the author is deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthorWorks {
+    /// Keep the works, only removing the author's credits
+    Orphan,
+    /// Delete the works only this author is credited on along with the author
+    Delete,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct AuthorTag {
+    #[sqlx(flatten)]
+    pub tag: Tag,
+    /// The number of the author's works tagged with this tag
+    pub works: i64,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct AuthorRole {
+    pub role: String,
+    /// The number of works the author is credited on with this role
+    pub works: i64,
+}
+
 impl Author {
     pub async fn create(
         tx: &mut Transaction<'_, Sqlite>,
@@ -76,7 +302,7 @@ impl Author {
             },
             AuthorQuery::Url(url) => {
                 sqlx::query_as("SELECT * FROM authors JOIN author_urls ON authors.author_id = author_urls.author_id WHERE author_urls.url = ?;")
-                    .bind(url.to_string())
+                    .bind(canonicalize(url).to_string())
                     .fetch_all(&mut **tx)
                     .await?
             },
@@ -101,7 +327,7 @@ impl Author {
         tx: &mut Transaction<'_, Sqlite>,
         url: &Url,
     ) -> Result<AuthorUrl, crate::Error> {
-        let url = url.to_string();
+        let url = canonicalize(url).to_string();
 
         Ok(sqlx::query_as(
             r#"
@@ -141,16 +367,463 @@ impl Author {
         tx: &mut Transaction<'_, Sqlite>,
     ) -> Result<Vec<AuthorName>, crate::Error> {
         Ok(
-            sqlx::query_as("SELECT * FROM author_names WHERE author_id = ?;")
+            sqlx::query_as("SELECT * FROM author_names WHERE author_id = ? ORDER BY rowid;")
                 .bind(&self.author_id)
                 .fetch_all(&mut **tx)
                 .await?,
         )
     }
 
+    pub async fn works(&self, tx: &mut Transaction<'_, Sqlite>) -> Result<Vec<Work>, crate::Error> {
+        Ok(sqlx::query_as(
+            "SELECT * FROM works WHERE work_id IN (SELECT work_id FROM work_authors WHERE author_id = ?);",
+        )
+            .bind(self.author_id)
+            .fetch_all(&mut **tx)
+            .await?)
+    }
+
+    /// Returns the roles this author is credited with, most common first.
+    pub async fn roles(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+    ) -> Result<Vec<AuthorRole>, crate::Error> {
+        Ok(sqlx::query_as(
+            "SELECT role, COUNT(*) AS works FROM work_authors WHERE author_id = ? GROUP BY role ORDER BY works DESC, role;",
+        )
+        .bind(self.author_id)
+        .fetch_all(&mut **tx)
+        .await?)
+    }
+
+    /// Returns the tags most often applied directly to this author's works.
+    pub async fn top_tags(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        limit: u32,
+    ) -> Result<Vec<AuthorTag>, crate::Error> {
+        Ok(sqlx::query_as(
+            r#"
+            SELECT tags.*, COUNT(*) AS works FROM tags
+                JOIN work_tags ON tags.id = work_tags.tag
+            WHERE work_tags.work_id IN (SELECT work_id FROM work_authors WHERE author_id = ?)
+            GROUP BY tags.id ORDER BY works DESC, tags.name LIMIT ?;
+        "#,
+        )
+        .bind(self.author_id)
+        .bind(limit)
+        .fetch_all(&mut **tx)
+        .await?)
+    }
+
+    /// Removes a name from this author, returning whether it existed.
+    pub async fn remove_alias(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        alias: &str,
+    ) -> Result<bool, crate::Error> {
+        Ok(
+            sqlx::query("DELETE FROM author_names WHERE author_id = ? AND name = ?;")
+                .bind(self.author_id)
+                .bind(alias)
+                .execute(&mut **tx)
+                .await?
+                .rows_affected()
+                > 0,
+        )
+    }
+
+    /// Removes a url from this author, returning whether it existed.
+    pub async fn remove_url(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        url: &Url,
+    ) -> Result<bool, crate::Error> {
+        Ok(
+            sqlx::query("DELETE FROM author_urls WHERE author_id = ? AND url = ?;")
+                .bind(self.author_id)
+                .bind(canonicalize(url).to_string())
+                .execute(&mut **tx)
+                .await?
+                .rows_affected()
+                > 0,
+        )
+    }
+
+    /// Deletes this author along with their names and urls, returning the works which were
+    /// deleted. Works credited to other authors as well are kept.
+    ///
+    /// The files of deleted works are left for the caller to remove once the transaction is
+    /// committed.
+    pub async fn delete(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        works: AuthorWorks,
+    ) -> Result<Vec<Work>, crate::Error> {
+        let deleted = match works {
+            // The author's credits are removed along with the author.
+            AuthorWorks::Orphan => Vec::new(),
+            AuthorWorks::Delete => {
+                sqlx::query_as("DELETE FROM works WHERE work_id IN (SELECT work_id FROM work_authors WHERE author_id = ?1) AND work_id NOT IN (SELECT work_id FROM work_authors WHERE author_id != ?1) RETURNING *;")
+                    .bind(self.author_id)
+                    .fetch_all(&mut **tx)
+                    .await?
+            }
+        };
+
+        sqlx::query("DELETE FROM authors WHERE author_id = ?;")
+            .bind(self.author_id)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(deleted)
+    }
+
+    /// Moves every work, name and url of this author onto `target` and deletes this author.
+    pub async fn merge(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        target: &Author,
+    ) -> Result<(), crate::Error> {
+        if self.author_id == target.author_id {
+            return Ok(());
+        }
+
+        sqlx::query(
+            r#"
+                INSERT OR IGNORE INTO work_authors (work_id, author_id, role) SELECT work_id, ?1, role FROM work_authors WHERE author_id = ?2;
+                INSERT OR IGNORE INTO author_names (author_id, name) SELECT ?1, name FROM author_names WHERE author_id = ?2;
+                UPDATE author_urls SET author_id = ?1 WHERE author_id = ?2;
+                DELETE FROM authors WHERE author_id = ?2;
+            "#,
+        )
+        .bind(target.author_id)
+        .bind(self.author_id)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn get_all(tx: &mut Transaction<'_, Sqlite>) -> Result<Vec<Self>, crate::Error> {
         Ok(sqlx::query_as("SELECT * FROM authors;")
             .fetch_all(&mut **tx)
             .await?)
     }
 }
+
+impl Work {
+    /// Returns the authors credited on this work in the order they were credited.
+    pub async fn authors(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+    ) -> Result<Vec<WorkAuthor>, crate::Error> {
+        Ok(
+            sqlx::query_as("SELECT * FROM work_authors WHERE work_id = ? ORDER BY rowid;")
+                .bind(self.work_id)
+                .fetch_all(&mut **tx)
+                .await?,
+        )
+    }
+
+    /// Credits `author` on this work with `role`, returning whether the credit is new.
+    pub async fn credit(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        author: &Author,
+        role: &str,
+    ) -> Result<bool, crate::Error> {
+        Ok(sqlx::query(
+            "INSERT OR IGNORE INTO work_authors (work_id, author_id, role) VALUES (?, ?, ?);",
+        )
+        .bind(self.work_id)
+        .bind(author.author_id)
+        .bind(role)
+        .execute(&mut **tx)
+        .await?
+        .rows_affected()
+            > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        record::RecordDetails,
+        utils::{test_pool, test_record, test_work},
+    };
+
+    use super::*;
+
+    fn credits(names: &[&str]) -> RecordDetails {
+        RecordDetails {
+            authors: names
+                .iter()
+                .map(|name| AuthorCredit::new(AuthorQuery::Name(name.to_string())))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delete_keeps_shared_works() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let solo = test_work(&mut tx, "solo", credits(&["hazel"])).await;
+        let shared = test_work(&mut tx, "shared", credits(&["hazel", "marina"])).await;
+
+        let hazel = Author::get(&mut tx, &AuthorQuery::Name(String::from("hazel")))
+            .await
+            .unwrap()
+            .remove(0);
+
+        let deleted = hazel.delete(&mut tx, AuthorWorks::Delete).await.unwrap();
+
+        assert_eq!(
+            deleted.iter().map(|work| work.work_id).collect::<Vec<_>>(),
+            vec![solo.work_id]
+        );
+
+        assert!(Work::get_by_id(&mut tx, solo.work_id)
+            .await
+            .unwrap()
+            .is_none());
+
+        let remaining = Work::get_by_id(&mut tx, shared.work_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let credits = remaining.authors(&mut tx).await.unwrap();
+
+        assert_eq!(credits.len(), 1);
+        assert_ne!(credits[0].author_id, hazel.author_id);
+    }
+
+    #[tokio::test]
+    async fn test_merge() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let source = Author::create(&mut tx, "hazel").await.unwrap();
+        let target = Author::create(&mut tx, "hazel").await.unwrap();
+
+        source.add_alias(&mut tx, "witch").await.unwrap();
+        source
+            .add_url(
+                &mut tx,
+                &Url::parse("https://bsky.app/profile/hazel").unwrap(),
+            )
+            .await
+            .unwrap();
+        target
+            .add_url(&mut tx, &Url::parse("https://x.com/hazel").unwrap())
+            .await
+            .unwrap();
+
+        let by_id = |author: &Author| RecordDetails {
+            authors: vec![AuthorCredit::new(AuthorQuery::Id(
+                author.author_id.0 as i32,
+            ))],
+            ..Default::default()
+        };
+
+        let only_source = test_work(&mut tx, "only source", by_id(&source)).await;
+        let mut shared = by_id(&source);
+        shared.authors.extend(by_id(&target).authors);
+        let shared = test_work(&mut tx, "shared", shared).await;
+
+        source.merge(&mut tx, &target).await.unwrap();
+
+        assert!(Author::get_by_id(&mut tx, &source.author_id)
+            .await
+            .unwrap()
+            .is_none());
+
+        for work in [&only_source, &shared] {
+            let credits = work.authors(&mut tx).await.unwrap();
+
+            assert_eq!(credits.len(), 1);
+            assert_eq!(credits[0].author_id, target.author_id);
+        }
+
+        let names: Vec<String> = target
+            .get_author_names(&mut tx)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|name| name.name)
+            .collect();
+        assert_eq!(names, ["hazel", "witch"]);
+
+        let mut urls: Vec<String> = target
+            .get_author_urls(&mut tx)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|url| url.url)
+            .collect();
+        urls.sort();
+        assert_eq!(
+            urls,
+            ["https://bsky.app/profile/hazel", "https://x.com/hazel"]
+        );
+
+        let (orphans,): (i64,) = sqlx::query_as(
+            "SELECT (SELECT COUNT(*) FROM author_names WHERE author_id = ?1) + (SELECT COUNT(*) FROM author_urls WHERE author_id = ?1);",
+        )
+        .bind(source.author_id)
+        .fetch_one(&mut *tx)
+        .await
+        .unwrap();
+        assert_eq!(orphans, 0);
+    }
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    async fn two_hazels(tx: &mut Transaction<'_, Sqlite>) -> (Author, Author) {
+        let first = Author::create(tx, "hazel").await.unwrap();
+        let second = Author::create(tx, "hazel").await.unwrap();
+
+        second
+            .add_url(tx, &url("https://x.com/hazel"))
+            .await
+            .unwrap();
+
+        (first, second)
+    }
+
+    #[tokio::test]
+    async fn test_resolve_error() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let (first, second) = two_hazels(&mut tx).await;
+
+        let credit = AuthorCredit::new(AuthorQuery::Name(String::from("hazel")));
+
+        match credit.resolve(&mut tx, &AuthorPolicy::Error).await {
+            Err(crate::Error::Ambiguous { candidates, .. }) => {
+                assert_eq!(candidates, vec![first.author_id.0, second.author_id.0])
+            }
+            _ => panic!("expected hazel to be ambiguous"),
+        }
+
+        for query in [
+            AuthorQuery::Id(1000),
+            AuthorQuery::Url(url("https://x.com/someone")),
+        ] {
+            assert!(matches!(
+                AuthorCredit::new(query)
+                    .resolve(&mut tx, &AuthorPolicy::Error)
+                    .await,
+                Err(crate::Error::NotFound { .. })
+            ));
+        }
+
+        // The work is not created rather than losing the credit.
+        let record = test_record(
+            "work",
+            RecordDetails {
+                authors: vec![AuthorCredit::new(AuthorQuery::Url(url(
+                    "https://x.com/someone",
+                )))],
+                ..Default::default()
+            },
+        );
+
+        assert!(
+            Work::create_from_record(&mut tx, &record, &AuthorPolicy::Error)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_pick_by_url() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let (_, second) = two_hazels(&mut tx).await;
+
+        let credit = AuthorCredit {
+            url: Some(url("https://twitter.com/Hazel")),
+            ..AuthorCredit::new(AuthorQuery::Name(String::from("hazel")))
+        };
+
+        let picked = credit
+            .resolve(&mut tx, &AuthorPolicy::PickByUrl)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(picked.author_id, second.author_id);
+
+        let credit = AuthorCredit {
+            url: Some(url("https://bsky.app/profile/hazel")),
+            ..credit
+        };
+
+        assert!(matches!(
+            credit.resolve(&mut tx, &AuthorPolicy::PickByUrl).await,
+            Err(crate::Error::Ambiguous { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_create() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let (first, second) = two_hazels(&mut tx).await;
+
+        let created = AuthorCredit::new(AuthorQuery::Name(String::from("hazel")))
+            .resolve(&mut tx, &AuthorPolicy::Create)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(created.author_id, first.author_id);
+        assert_ne!(created.author_id, second.author_id);
+
+        let created = AuthorCredit::new(AuthorQuery::Url(url("https://x.com/marina")))
+            .resolve(&mut tx, &AuthorPolicy::Create)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            created.get_author_names(&mut tx).await.unwrap()[0].name,
+            "marina"
+        );
+        assert_eq!(
+            created.get_author_urls(&mut tx).await.unwrap()[0].url,
+            "https://x.com/marina"
+        );
+
+        assert!(AuthorCredit::new(AuthorQuery::Id(1000))
+            .resolve(&mut tx, &AuthorPolicy::Create)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_callback_skip() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        two_hazels(&mut tx).await;
+
+        let policy = AuthorPolicy::Callback(Arc::new(|_, candidates| {
+            assert_eq!(candidates.len(), 2);
+            Ok(AuthorChoice::Skip)
+        }));
+
+        let record = test_record("work", credits(&["hazel"]));
+
+        let work = Work::create_from_record(&mut tx, &record, &policy)
+            .await
+            .unwrap();
+
+        assert!(work.authors(&mut tx).await.unwrap().is_empty());
+    }
+}
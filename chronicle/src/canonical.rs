@@ -0,0 +1,316 @@
+use sqlx::{Sqlite, Transaction};
+use url::Url;
+
+use crate::{
+    import::SERVICES,
+    models::{Author, AuthorId, AuthorUrl},
+};
+
+/// Query parameters which only track where a link was shared from.
+const TRACKING_PARAMETERS: &[&str] = &[
+    "fbclid", "gclid", "igshid", "mc_cid", "mc_eid", "ref", "ref_src", "ref_url", "si",
+];
+
+/// Converts a url into the single form it is stored and queried as.
+///
+/// The first service which recognises the url decides its canonical form, any other url only has
+/// its fragment, tracking parameters and trailing slash removed.
+pub fn canonicalize(url: &Url) -> Url {
+    let url = strip_tracking(url);
+
+    SERVICES
+        .iter()
+        .find_map(|service| service.canonicalize(&url))
+        .unwrap_or(url)
+}
+
+/// Canonicalizes a url which is stored as text, leaving it untouched if it does not parse.
+pub fn canonicalize_str(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(url) => canonicalize(&url).to_string(),
+        Err(_) => url.to_owned(),
+    }
+}
+
+/// Removes the fragment, tracking parameters and trailing slash from a url.
+pub fn strip_tracking(url: &Url) -> Url {
+    let mut url = url.clone();
+
+    url.set_fragment(None);
+
+    let query: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| !key.starts_with("utm_") && !TRACKING_PARAMETERS.contains(&&**key))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+
+    if query.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(query);
+    }
+
+    if url.path().len() > 1 && url.path().ends_with('/') {
+        let path = url.path().trim_end_matches('/').to_owned();
+        url.set_path(&path);
+    }
+
+    url
+}
+
+/// Returns the non empty path segments of a url.
+pub fn path_segments(url: &Url) -> Vec<&str> {
+    url.path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// Two authors which ended up sharing a url once it was canonicalized.
+pub struct AuthorUrlMerge {
+    pub url: String,
+    pub source: Author,
+    pub target: Author,
+}
+
+/// The changes made by [`canonicalize_stored_urls`].
+#[derive(Default)]
+pub struct CanonicalizeReport {
+    pub work_urls: u64,
+    pub author_urls: u64,
+    pub merges: Vec<AuthorUrlMerge>,
+}
+
+/// Canonicalizes every stored work and author url.
+///
+/// Authors which end up with the same url are merged into the author which already had it.
+pub async fn canonicalize_stored_urls(
+    tx: &mut Transaction<'_, Sqlite>,
+) -> Result<CanonicalizeReport, crate::Error> {
+    let mut report = CanonicalizeReport::default();
+
+    let work_urls: Vec<(String,)> =
+        sqlx::query_as("SELECT DISTINCT url FROM works WHERE url IS NOT NULL;")
+            .fetch_all(&mut **tx)
+            .await?;
+
+    for (url,) in work_urls {
+        let canonical = canonicalize_str(&url);
+
+        if canonical != url {
+            report.work_urls += sqlx::query("UPDATE works SET url = ? WHERE url = ?;")
+                .bind(&canonical)
+                .bind(&url)
+                .execute(&mut **tx)
+                .await?
+                .rows_affected();
+        }
+    }
+
+    let author_urls: Vec<AuthorUrl> = sqlx::query_as("SELECT * FROM author_urls;")
+        .fetch_all(&mut **tx)
+        .await?;
+
+    for AuthorUrl { url, .. } in author_urls {
+        let canonical = canonicalize_str(&url);
+
+        if canonical == url {
+            continue;
+        }
+
+        report.author_urls += 1;
+
+        // Looked up again as an earlier merge may have moved the url to another author.
+        let (current,): (AuthorId,) =
+            sqlx::query_as("SELECT author_id FROM author_urls WHERE url = ?;")
+                .bind(&url)
+                .fetch_one(&mut **tx)
+                .await?;
+
+        let existing: Option<(AuthorId,)> =
+            sqlx::query_as("SELECT author_id FROM author_urls WHERE url = ?;")
+                .bind(&canonical)
+                .fetch_optional(&mut **tx)
+                .await?;
+
+        match existing {
+            None => {
+                sqlx::query("UPDATE author_urls SET url = ? WHERE url = ?;")
+                    .bind(&canonical)
+                    .bind(&url)
+                    .execute(&mut **tx)
+                    .await?;
+            }
+            Some((existing,)) => {
+                if existing != current {
+                    let source = Author::get_by_id(tx, &current)
+                        .await?
+                        .expect("author url references a missing author");
+                    let target = Author::get_by_id(tx, &existing)
+                        .await?
+                        .expect("author url references a missing author");
+
+                    source.merge(tx, &target).await?;
+
+                    report.merges.push(AuthorUrlMerge {
+                        url: canonical,
+                        source,
+                        target,
+                    });
+                }
+
+                sqlx::query("DELETE FROM author_urls WHERE url = ?;")
+                    .bind(&url)
+                    .execute(&mut **tx)
+                    .await?;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        models::Work,
+        record::RecordDetails,
+        utils::{test_pool, test_work},
+    };
+
+    use super::*;
+
+    fn canonical(url: &str) -> String {
+        canonicalize(&Url::parse(url).unwrap()).to_string()
+    }
+
+    #[test]
+    fn test_generic() {
+        assert_eq!(
+            canonical("https://example.com/a/b/?utm_source=x&page=2#top"),
+            "https://example.com/a/b?page=2"
+        );
+        assert_eq!(canonical("https://example.com/"), "https://example.com/");
+    }
+
+    #[test]
+    fn test_twitter() {
+        let canonical_tweet = "https://x.com/i/status/1234";
+
+        for url in [
+            "https://x.com/i/status/1234",
+            "https://x.com/i/web/status/1234",
+            "https://twitter.com/someone/status/1234",
+            "https://mobile.twitter.com/Someone/status/1234?s=20&t=abc",
+            "https://fxtwitter.com/someone/status/1234/photo/1",
+            "https://vxtwitter.com/someone/status/1234",
+            "http://www.x.com/someone/status/1234/",
+        ] {
+            assert_eq!(canonical(url), canonical_tweet, "{url}");
+        }
+
+        assert_eq!(
+            canonical("https://twitter.com/Someone"),
+            "https://x.com/someone"
+        );
+    }
+
+    #[test]
+    fn test_tumblr() {
+        let canonical_post = "https://someone.tumblr.com/post/1234";
+
+        for url in [
+            "https://www.tumblr.com/someone/1234",
+            "https://www.tumblr.com/someone/1234/some-slug?source=share",
+            "https://someone.tumblr.com/post/1234/some-slug",
+            "http://Someone.tumblr.com/post/1234",
+        ] {
+            assert_eq!(canonical(url), canonical_post, "{url}");
+        }
+
+        assert_eq!(
+            canonical("https://www.tumblr.com/someone"),
+            "https://someone.tumblr.com/"
+        );
+    }
+
+    #[test]
+    fn test_deviantart() {
+        let canonical_deviation = "https://www.deviantart.com/someone/art/Sunset-123456";
+
+        for url in [
+            "https://www.deviantart.com/Someone/art/Sunset-123456",
+            "https://someone.deviantart.com/art/Sunset-123456",
+            "https://deviantart.com/someone/art/Sunset-123456/",
+        ] {
+            assert_eq!(canonical(url), canonical_deviation, "{url}");
+        }
+
+        assert_eq!(
+            canonical("https://Someone.deviantart.com/"),
+            "https://www.deviantart.com/someone"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_canonicalize_stored_urls() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let old = Author::create(&mut tx, "Someone").await.unwrap();
+        let new = Author::create(&mut tx, "someone").await.unwrap();
+
+        // Stored before urls were canonicalized, which adding them now would do.
+        for (author, url) in [
+            (&old, "https://twitter.com/Someone"),
+            (&new, "https://x.com/someone"),
+        ] {
+            sqlx::query("INSERT INTO author_urls(author_id, url) VALUES (?, ?);")
+                .bind(author.author_id)
+                .bind(url)
+                .execute(&mut *tx)
+                .await
+                .unwrap();
+        }
+
+        let work = test_work(&mut tx, "work", RecordDetails::default()).await;
+
+        sqlx::query("UPDATE works SET url = ? WHERE work_id = ?;")
+            .bind("https://twitter.com/Someone/status/1234?s=20")
+            .bind(work.work_id)
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+
+        let report = canonicalize_stored_urls(&mut tx).await.unwrap();
+
+        assert_eq!(report.work_urls, 1);
+        assert_eq!(report.author_urls, 1);
+        assert_eq!(report.merges.len(), 1);
+        assert_eq!(report.merges[0].url, "https://x.com/someone");
+        assert_eq!(report.merges[0].source.author_id, old.author_id);
+        assert_eq!(report.merges[0].target.author_id, new.author_id);
+
+        let author_urls: Vec<AuthorUrl> = sqlx::query_as("SELECT * FROM author_urls;")
+            .fetch_all(&mut *tx)
+            .await
+            .unwrap();
+
+        assert_eq!(author_urls.len(), 1);
+        assert_eq!(author_urls[0].author_id, new.author_id);
+        assert_eq!(author_urls[0].url, "https://x.com/someone");
+
+        assert!(Author::get_by_id(&mut tx, &old.author_id)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            Work::get_by_id(&mut tx, work.work_id)
+                .await
+                .unwrap()
+                .unwrap()
+                .url
+                .as_deref(),
+            Some("https://x.com/i/status/1234")
+        );
+    }
+}
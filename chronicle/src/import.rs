@@ -1,4 +1,8 @@
 pub mod bsky;
+pub mod deviantart;
+pub mod mastodon;
+pub mod opengraph;
+pub mod pixiv;
 pub mod tumblr;
 pub mod twitter;
 
@@ -12,10 +16,11 @@ use twitter::Twitter;
 use url::Url;
 
 use crate::{
-    author::{self, AuthorQuery},
-    models::{Author, Tag, Work},
+    author::AuthorPolicy,
+    canonical::canonicalize,
+    models::{Rule, Tag, TagMapping, Work},
     record::{Record, RecordDetails},
-    Chronicle, ServiceError,
+    Chronicle,
 };
 
 pub const SERVICE_NAME: &str = "chronicle";
@@ -31,6 +36,17 @@ pub trait Service {
     fn host_matches(&self, host: &str) -> bool;
     fn name(&self) -> &str;
     fn secrets(&self) -> &[&str];
+    /// Returns the canonical form of `url` if it belongs to this service.
+    ///
+    /// The url has already had its fragment, tracking parameters and trailing slash removed.
+    fn canonicalize(&self, _url: &Url) -> Option<Url> {
+        None
+    }
+    /// Checks whether a url on a host [`Service::host_matches`] rejected still belongs to this
+    /// service, for services which are not tied to a fixed set of hosts.
+    async fn probe(&self, _chronicle: &Chronicle, _url: &Url) -> bool {
+        false
+    }
     async fn authenticate(
         &self,
         secrets: &HashMap<String, String>,
@@ -47,7 +63,44 @@ pub trait Service {
 }
 
 lazy_static::lazy_static! {
-    pub static ref SERVICES: Vec<Box<dyn Service + Send + Sync + 'static>> = vec![Box::new(bsky::Bsky::default()), Box::new(tumblr::Tumblr::default()), Box::new(Twitter)];
+    pub static ref SERVICES: Vec<Box<dyn Service + Send + Sync + 'static>> = vec![Box::new(bsky::Bsky::default()), Box::new(deviantart::DeviantArt), Box::new(mastodon::Mastodon::default()), Box::new(pixiv::Pixiv::default()), Box::new(tumblr::Tumblr::default()), Box::new(Twitter)];
+    static ref HTML_TAG: regex::Regex = regex::Regex::new(r#"<[^>]*>"#).unwrap();
+    static ref HTML_BREAK: regex::Regex = regex::Regex::new(r#"(?i)<br\s*/?>"#).unwrap();
+    static ref HTML_PARAGRAPH: regex::Regex = regex::Regex::new(r#"(?i)</p>\s*<p[^>]*>"#).unwrap();
+}
+
+/// Used for urls on hosts which none of [`SERVICES`] match.
+pub static FALLBACK_SERVICE: opengraph::OpenGraph = opengraph::OpenGraph;
+
+/// Converts the html services return for post text into plain text.
+pub(crate) fn html_to_text(html: &str) -> String {
+    let text = HTML_PARAGRAPH.replace_all(html, "\n\n");
+    let text = HTML_BREAK.replace_all(&text, "\n");
+    let text = HTML_TAG.replace_all(&text, "");
+
+    unescape_html(text.trim())
+}
+
+/// Appends the alt text of a file to the text of the post it was attached to.
+pub(crate) fn caption_with_alt(text: Option<&str>, alt: Option<&str>) -> Option<String> {
+    let text = text.map(str::trim).filter(|text| !text.is_empty());
+    let alt = alt.map(str::trim).filter(|alt| !alt.is_empty());
+
+    match (text, alt) {
+        (Some(text), Some(alt)) => Some(format!("{text}\n\n{alt}")),
+        (text, alt) => text.or(alt).map(str::to_owned),
+    }
+}
+
+/// Replaces the entities html escapes text with.
+pub(crate) fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
 }
 
 pub fn write_secrets(
@@ -82,8 +135,13 @@ impl Work {
         tx: &mut Transaction<'_, Sqlite>,
         url: &Url,
         provided_details: Option<&RecordDetails>,
+        policy: &AuthorPolicy,
     ) -> Result<Vec<Work>, crate::Error> {
-        let Some(host) = url.host_str() else {
+        // The url is fetched as given as canonicalizing it can remove parts the site needs, it is
+        // only used to find the service and is stored canonicalized.
+        let canonical = canonicalize(url);
+
+        let Some(host) = canonical.host_str() else {
             return Err(crate::Error::Generic(String::from(
                 "url does not have a host",
             )));
@@ -91,11 +149,18 @@ impl Work {
 
         let mut records = Vec::with_capacity(6);
 
-        let Some(service) = SERVICES.iter().find(|s| s.host_matches(&host)) else {
-            return Err(crate::Error::Generic(format!(
-                "could not find service for {host}"
-            )));
-        };
+        let mut service: &(dyn Service + Send + Sync) = &FALLBACK_SERVICE;
+
+        if let Some(matched) = SERVICES.iter().find(|s| s.host_matches(host)) {
+            service = matched.as_ref();
+        } else {
+            for probed in SERVICES.iter() {
+                if probed.probe(chronicle, url).await {
+                    service = probed.as_ref();
+                    break;
+                }
+            }
+        }
 
         let (secrets, previous) = if !service.secrets().is_empty() {
             let user = whoami::username();
@@ -153,12 +218,33 @@ impl Work {
             }
         }
 
+        Self::create_from_service_records(tx, service.name(), records, policy).await
+    }
+
+    /// Creates works from the records a service produced, translating their native tags through
+    /// the service's tag mappings. Either every work is created or none are.
+    pub async fn create_from_service_records(
+        tx: &mut Transaction<'_, Sqlite>,
+        service: &str,
+        mut records: Vec<Record>,
+        policy: &AuthorPolicy,
+    ) -> Result<Vec<Work>, crate::Error> {
         let mut works = Vec::with_capacity(records.len());
 
         let mut tx = tx.begin().await?;
 
+        for record in records.iter_mut() {
+            let native_tags = std::mem::take(&mut record.details.native_tags);
+
+            for tag in TagMapping::resolve(&mut tx, service, &native_tags).await? {
+                if !record.details.tags.contains(&tag) {
+                    record.details.tags.push(tag);
+                }
+            }
+        }
+
         for record in records {
-            works.push(Self::create_from_record(&mut tx, &record).await?);
+            works.push(Self::create_from_record(&mut tx, &record, policy).await?);
         }
 
         tx.commit().await?;
@@ -169,31 +255,11 @@ impl Work {
     pub async fn create_from_record(
         tx: &mut Transaction<'_, Sqlite>,
         record: &Record,
+        policy: &AuthorPolicy,
     ) -> Result<Work, crate::Error> {
-        let author_id = if let Some(author_query) = &record.details.author {
-            let mut authors = Author::get(tx, author_query).await?;
-
-            if authors.len() == 1 {
-                Some(authors.remove(0).author_id)
-            } else if authors.is_empty() {
-                if let AuthorQuery::Name(name) = author_query {
-                    let author = Author::create(tx, name).await?;
-
-                    Some(author.author_id)
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        } else {
-            None
-        };
-
-        let work: Work = sqlx::query_as("INSERT INTO works(path, url, author_id, title, caption, hash, size) VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING *;")
+        let work: Work = sqlx::query_as("INSERT INTO works(path, url, title, caption, hash, size) VALUES (?, ?, ?, ?, ?, ?) RETURNING *;")
             .bind(&record.path.to_string_lossy())
-            .bind(&record.details.url.as_ref().map(|url| url.to_string()))
-            .bind(&author_id)
+            .bind(&record.details.url.as_ref().map(|url| canonicalize(url).to_string()))
             .bind(&record.details.title)
             .bind(&record.details.caption)
             .bind(&record.hash)
@@ -201,22 +267,59 @@ impl Work {
             .fetch_one(&mut **tx)
             .await?;
 
-        if let Some(author_id) = author_id {
-            if let Some(author_url) = &record.details.author_url {
-                let author = Author::get_by_id(tx, &author_id)
-                    .await?
-                    .expect("author id exists but not found");
+        for credit in record.details.authors.iter() {
+            let Some(author) = credit.resolve(tx, policy).await? else {
+                continue;
+            };
 
-                author.add_url(tx, author_url).await?;
+            if let Some(url) = &credit.url {
+                author.add_url(tx, url).await?;
             }
+
+            work.credit(tx, &author, &credit.role).await?;
         }
 
         for tag in record.details.tags.iter() {
-            let tag = Tag::get_discriminated_or_create(tx, &tag.name, tag.discriminator.as_deref())
-                .await?;
+            let tag = Tag::get_or_create(tx, tag).await?;
             work.tag(tx, &tag).await?;
         }
 
+        Rule::apply_to_work(tx, &work).await?;
+
         Ok(work)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_text() {
+        assert_eq!(
+            html_to_text(r#"first<br />second &amp; <a href="https://example.com">link</a>"#),
+            "first\nsecond & link"
+        );
+        assert_eq!(
+            html_to_text(r#"<p>one</p><p>two<br>three</p>"#),
+            "one\n\ntwo\nthree"
+        );
+    }
+
+    #[test]
+    fn test_caption_with_alt() {
+        assert_eq!(
+            caption_with_alt(Some("text"), Some("alt")).as_deref(),
+            Some("text\n\nalt")
+        );
+        assert_eq!(
+            caption_with_alt(Some(" "), Some("alt")).as_deref(),
+            Some("alt")
+        );
+        assert_eq!(
+            caption_with_alt(Some("text"), None).as_deref(),
+            Some("text")
+        );
+        assert_eq!(caption_with_alt(None, Some("")), None);
+    }
+}
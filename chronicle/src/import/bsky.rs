@@ -9,10 +9,19 @@ use std::{
 use async_trait::async_trait;
 use atrium_api::{
     agent::{store::MemorySessionStore, AtpAgent, Session},
-    app::bsky::feed::{defs::PostViewEmbedRefs, get_post_thread},
+    app::bsky::{
+        embed::{
+            external, images,
+            record::{self, ViewRecord, ViewRecordEmbedsItem, ViewRecordRefs},
+            record_with_media::ViewMediaRefs,
+            video,
+        },
+        feed::{defs::PostViewEmbedRefs, get_post_thread},
+    },
     types::{TryFromUnknown, Union},
 };
 use atrium_xrpc_client::reqwest::ReqwestClient;
+use ipld_core::cid::Cid;
 use lazy_static::lazy_static;
 use nom::{
     bytes::complete::{tag, take_while},
@@ -20,6 +29,7 @@ use nom::{
     IResult,
 };
 use regex::Regex;
+use reqwest::header::CONTENT_TYPE;
 use serde::Deserialize;
 use tokio::sync::{OnceCell, RwLock};
 use tracing::{error, warn};
@@ -27,12 +37,14 @@ use url::Url;
 use uuid::Uuid;
 
 use crate::{
-    author::AuthorQuery,
+    archive::bsky::BskyIdentity,
+    author::{AuthorCredit, AuthorQuery},
+    canonical::path_segments,
     record::{Record, RecordDetails},
-    ServiceError,
+    ServiceError, HTTP_CLIENT,
 };
 
-use super::Service;
+use super::{caption_with_alt, opengraph::extension, Service};
 
 lazy_static! {
     static ref BSKY_POST_REGEX: Regex = Regex::new(r#"\/profile\/([^\/]+)\/post\/(.+)"#).unwrap();
@@ -70,6 +82,22 @@ impl Service for Bsky {
         &[BSKY_IDENTIFIER, BSKY_PASSWORD]
     }
 
+    fn canonicalize(&self, url: &Url) -> Option<Url> {
+        if url.host_str()? != "bsky.app" {
+            return None;
+        }
+
+        let path = match path_segments(url).as_slice() {
+            ["profile", actor, "post", post, ..] => {
+                format!("/profile/{}/post/{post}", actor.to_lowercase())
+            }
+            ["profile", actor] => format!("/profile/{}", actor.to_lowercase()),
+            _ => return None,
+        };
+
+        Url::parse(&format!("https://bsky.app{path}")).ok()
+    }
+
     fn name(&self) -> &str {
         "bsky"
     }
@@ -145,54 +173,314 @@ impl Service for Bsky {
         let deserialized_post = PostRecord::try_from_unknown(post.record.clone())
             .map_err(|err| crate::Error::Generic(format!("could not parse bsky post: {err}")))?;
 
-        let caption = deserialized_post.text;
+        let details = post_details(post.author.handle.as_str(), url, deserialized_post);
 
-        let author = post.author.handle.clone().as_str().to_owned();
+        let Some(embed) = &post.embed else {
+            warn!("This post does not embed any files");
+            return Ok(());
+        };
 
-        let details = RecordDetails {
-            tags: Vec::new(),
-            title: None,
-            author: Some(AuthorQuery::Name(author.to_string())),
-            author_url: Some(
-                Url::parse(&format!("https://bsky.app/profile/{author}"))
-                    .expect("invalid author url"),
-            ),
-            url: Some(url),
-            caption: Some(caption),
+        let Embed { files, quoted } = Embed::from_post(embed, post.author.did.as_str());
+
+        let mut posts = vec![(details, files)];
+
+        if let Some(quoted) = quoted.filter(|_| chronicle.config.bsky_follow_quotes) {
+            posts.extend(quoted_post(quoted)?);
+        }
+
+        for (details, files) in posts {
+            for file in files {
+                let (data, extension) = file.source.download().await?;
+
+                let file_name = format!("{}{extension}", Uuid::new_v4());
+                let mut writer = BufWriter::new(File::create_new(
+                    chronicle.config.data_path.join(&file_name),
+                )?);
+
+                writer.write_all(&data)?;
+
+                records.push(Record {
+                    path: PathBuf::from(file_name),
+                    hash: bytemuck::cast(crc32fast::hash(&data)),
+                    size: data.len(),
+                    details: RecordDetails {
+                        caption: caption_with_alt(details.caption.as_deref(), file.alt.as_deref()),
+                        ..details.clone()
+                    },
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Where the data of an embedded file is downloaded from.
+enum FileSource {
+    Url(String),
+    /// Videos are only served as streams by the app view, so the uploaded file is downloaded from
+    /// the author's server instead
+    Blob {
+        did: String,
+        cid: Cid,
+    },
+}
+
+impl FileSource {
+    /// Downloads the file, returning its data and extension.
+    async fn download(&self) -> Result<(Vec<u8>, String), crate::Error> {
+        let url = match self {
+            FileSource::Url(url) => Url::parse(url)?,
+            FileSource::Blob { did, cid } => BskyIdentity::resolve(did).await?.blob_url(cid)?,
         };
 
-        match &post.embed {
-            Some(Union::Refs(PostViewEmbedRefs::AppBskyEmbedImagesView(view))) => {
-                for image_url in view.images.iter().map(|data| data.fullsize.clone()) {
-                    let file_name = format!("{}.jpg", Uuid::new_v4());
-                    let mut writer = BufWriter::new(File::create_new(
-                        chronicle.config.data_path.join(&file_name),
-                    )?);
+        let response = HTTP_CLIENT
+            .get(url.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_owned();
+
+        Ok((
+            response.bytes().await?.to_vec(),
+            extension(&url, &content_type),
+        ))
+    }
+}
 
-                    let request = reqwest::get(&image_url).await?;
+struct EmbeddedFile {
+    source: FileSource,
+    alt: Option<String>,
+}
 
-                    let data = request.bytes().await?;
+impl EmbeddedFile {
+    fn from_images(view: &images::View) -> Vec<Self> {
+        view.images
+            .iter()
+            .map(|image| Self {
+                source: FileSource::Url(image.fullsize.clone()),
+                alt: Some(image.alt.clone()),
+            })
+            .collect()
+    }
 
-                    writer.write_all(&data)?;
+    fn from_video(view: &video::View, did: &str) -> Self {
+        Self {
+            source: FileSource::Blob {
+                did: did.to_owned(),
+                cid: *view.cid.as_ref(),
+            },
+            alt: view.alt.clone(),
+        }
+    }
 
-                    records.push(Record {
-                        path: PathBuf::from(file_name),
-                        hash: bytemuck::cast(crc32fast::hash(&data)),
-                        size: data.len(),
-                        details: details.clone(),
-                    });
-                }
+    /// The thumbnail of a link card, if it has one.
+    fn from_external(view: &external::View) -> Option<Self> {
+        Some(Self {
+            source: FileSource::Url(view.external.thumb.clone()?),
+            alt: None,
+        })
+    }
+
+    fn from_media(media: &Union<ViewMediaRefs>, did: &str) -> Vec<Self> {
+        match media {
+            Union::Refs(ViewMediaRefs::AppBskyEmbedImagesView(view)) => Self::from_images(view),
+            Union::Refs(ViewMediaRefs::AppBskyEmbedVideoView(view)) => {
+                vec![Self::from_video(view, did)]
             }
-            _ => {
-                warn!("This post type can not be imported");
+            Union::Refs(ViewMediaRefs::AppBskyEmbedExternalView(view)) => {
+                Self::from_external(view).into_iter().collect()
             }
+            Union::Unknown(_) => Vec::new(),
         }
+    }
+}
 
-        Ok(())
+/// The files embedded in a post, along with the post it quotes.
+#[derive(Default)]
+struct Embed<'v> {
+    files: Vec<EmbeddedFile>,
+    quoted: Option<&'v ViewRecord>,
+}
+
+fn quoted_record(view: &record::View) -> Option<&ViewRecord> {
+    match &view.record {
+        Union::Refs(ViewRecordRefs::ViewRecord(record)) => Some(record),
+        _ => None,
+    }
+}
+
+impl<'v> Embed<'v> {
+    fn from_post(embed: &'v Union<PostViewEmbedRefs>, did: &str) -> Self {
+        let Union::Refs(embed) = embed else {
+            warn!("This post type can not be imported");
+            return Self::default();
+        };
+
+        match embed {
+            PostViewEmbedRefs::AppBskyEmbedImagesView(view) => Self {
+                files: EmbeddedFile::from_images(view),
+                quoted: None,
+            },
+            PostViewEmbedRefs::AppBskyEmbedVideoView(view) => Self {
+                files: vec![EmbeddedFile::from_video(view, did)],
+                quoted: None,
+            },
+            PostViewEmbedRefs::AppBskyEmbedExternalView(view) => Self {
+                files: EmbeddedFile::from_external(view).into_iter().collect(),
+                quoted: None,
+            },
+            PostViewEmbedRefs::AppBskyEmbedRecordView(view) => Self {
+                files: Vec::new(),
+                quoted: quoted_record(view),
+            },
+            PostViewEmbedRefs::AppBskyEmbedRecordWithMediaView(view) => Self {
+                files: EmbeddedFile::from_media(&view.media, did),
+                quoted: quoted_record(&view.record),
+            },
+        }
+    }
+}
+
+/// Reads the details and files of a quoted post, the files of posts it quotes in turn are not
+/// included.
+fn quoted_post(
+    record: &ViewRecord,
+) -> Result<Option<(RecordDetails, Vec<EmbeddedFile>)>, crate::Error> {
+    let Ok(post) = PostRecord::try_from_unknown(record.value.clone()) else {
+        // Quotes can embed other records such as feeds and lists, which do not have files.
+        return Ok(None);
+    };
+
+    let Some(rkey) = record.uri.rsplit('/').next() else {
+        return Ok(None);
+    };
+
+    let handle = record.author.handle.as_str();
+    let did = record.author.did.as_str();
+
+    let url = Url::parse(&format!("https://bsky.app/profile/{handle}/post/{rkey}"))?;
+
+    let files = record
+        .embeds
+        .iter()
+        .flatten()
+        .flat_map(|embed| match embed {
+            Union::Refs(ViewRecordEmbedsItem::AppBskyEmbedImagesView(view)) => {
+                EmbeddedFile::from_images(view)
+            }
+            Union::Refs(ViewRecordEmbedsItem::AppBskyEmbedVideoView(view)) => {
+                vec![EmbeddedFile::from_video(view, did)]
+            }
+            Union::Refs(ViewRecordEmbedsItem::AppBskyEmbedExternalView(view)) => {
+                EmbeddedFile::from_external(view).into_iter().collect()
+            }
+            Union::Refs(ViewRecordEmbedsItem::AppBskyEmbedRecordWithMediaView(view)) => {
+                EmbeddedFile::from_media(&view.media, did)
+            }
+            _ => Vec::new(),
+        })
+        .collect();
+
+    Ok(Some((post_details(handle, url, post), files)))
+}
+
+/// Builds the details shared by every image of a post, both from the api and from repository
+/// exports.
+pub(crate) fn post_details(handle: &str, url: Url, post: PostRecord) -> RecordDetails {
+    RecordDetails {
+        tags: Vec::new(),
+        title: None,
+        authors: vec![AuthorCredit {
+            url: Some(
+                Url::parse(&format!("https://bsky.app/profile/{handle}"))
+                    .expect("invalid author url"),
+            ),
+            ..AuthorCredit::new(AuthorQuery::Name(handle.to_owned()))
+        }],
+        url: Some(url),
+        caption: Some(post.text.clone()),
+        native_tags: post.hashtags(),
     }
 }
 
 #[derive(Deserialize)]
-struct PostRecord {
+pub(crate) struct PostRecord {
     pub text: String,
+    #[serde(default)]
+    pub facets: Vec<Facet>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl PostRecord {
+    /// The hashtags in the post's text followed by its outline tags.
+    fn hashtags(&self) -> Vec<String> {
+        self.facets
+            .iter()
+            .flat_map(|facet| facet.features.iter())
+            .filter_map(|feature| match feature {
+                FacetFeature::Tag { tag } => Some(tag.clone()),
+                FacetFeature::Other => None,
+            })
+            .chain(self.tags.iter().cloned())
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Facet {
+    pub features: Vec<FacetFeature>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "$type")]
+pub(crate) enum FacetFeature {
+    #[serde(rename = "app.bsky.richtext.facet#tag")]
+    Tag { tag: String },
+    #[serde(other)]
+    Other,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECORD_WITH_MEDIA: &str =
+        include_str!("../../tests/fixtures/bsky/record_with_media.json");
+
+    #[test]
+    fn test_record_with_media() {
+        let embed: Union<PostViewEmbedRefs> = serde_json::from_str(RECORD_WITH_MEDIA).unwrap();
+
+        let Embed { files, quoted } = Embed::from_post(&embed, "did:plc:someone");
+
+        assert_eq!(files.len(), 1);
+        assert!(matches!(
+            &files[0].source,
+            FileSource::Url(url) if url.starts_with("https://cdn.bsky.app/img/feed_fullsize/")
+        ));
+        assert_eq!(files[0].alt.as_deref(), Some("a redraw"));
+
+        let (details, files) = quoted_post(quoted.unwrap()).unwrap().unwrap();
+
+        assert_eq!(
+            details.url.as_ref().map(Url::as_str),
+            Some("https://bsky.app/profile/other.bsky.social/post/3kquoted")
+        );
+        assert_eq!(details.native_tags, vec!["art"]);
+
+        assert_eq!(files.len(), 1);
+        assert!(matches!(
+            &files[0].source,
+            FileSource::Blob { did, .. } if did == "did:plc:other"
+        ));
+        assert_eq!(files[0].alt.as_deref(), Some("a timelapse"));
+    }
 }
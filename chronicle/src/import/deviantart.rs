@@ -0,0 +1,406 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    author::{AuthorCredit, AuthorQuery},
+    canonical::path_segments,
+    record::{Record, RecordDetails},
+    ServiceError, HTTP_CLIENT,
+};
+
+use super::{html_to_text, Service};
+
+const DEVIANTART_CLIENT_ID: &str = "deviantart-client-id";
+const DEVIANTART_CLIENT_SECRET: &str = "deviantart-client-secret";
+
+const ACCESS_TOKEN: &str = "access-token";
+
+const API_BASE: &str = "https://www.deviantart.com/api/v1/oauth2";
+
+lazy_static! {
+    static ref APP_URL: Regex =
+        Regex::new(r#"(?i)DeviantArt://deviation/([0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12})"#)
+            .unwrap();
+}
+
+pub struct DeviantArt;
+
+/// Extracts the user from the hosts of the `{user}.deviantart.com` style urls.
+fn subdomain_user(url: &Url) -> Option<&str> {
+    let user = url.host_str()?.strip_suffix(".deviantart.com")?;
+
+    (user != "www" && !user.contains('.')).then_some(user)
+}
+
+/// Returns the user and the slug of a deviation url, the slug ends with the deviation's number.
+fn deviation(url: &Url) -> Option<(&str, &str)> {
+    match (subdomain_user(url), path_segments(url).as_slice()) {
+        (Some(user), ["art", slug, ..]) => Some((user, slug)),
+        (None, [user, "art", slug, ..]) => Some((user, slug)),
+        _ => None,
+    }
+}
+
+/// The uuid the api identifies deviations by, which deviation pages link the app to.
+fn app_url_uuid(html: &str) -> Option<&str> {
+    APP_URL
+        .captures(html)
+        .and_then(|captures| captures.get(1))
+        .map(|uuid| uuid.as_str())
+}
+
+fn author_url(username: &str) -> Option<Url> {
+    Url::parse(&format!(
+        "https://www.deviantart.com/{}",
+        username.to_lowercase()
+    ))
+    .ok()
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct Deviation {
+    title: Option<String>,
+    url: Option<Url>,
+    author: Option<User>,
+    content: Option<Content>,
+    #[serde(default)]
+    is_downloadable: bool,
+}
+
+#[derive(Deserialize)]
+struct User {
+    username: String,
+}
+
+#[derive(Deserialize)]
+struct Content {
+    src: Url,
+}
+
+#[derive(Deserialize)]
+struct MetadataResponse {
+    metadata: Vec<Metadata>,
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    description: Option<String>,
+    #[serde(default)]
+    tags: Vec<MetadataTag>,
+}
+
+#[derive(Deserialize)]
+struct MetadataTag {
+    tag_name: String,
+}
+
+#[derive(Deserialize)]
+struct OEmbed {
+    url: Url,
+    title: Option<String>,
+    author_name: Option<String>,
+    author_url: Option<Url>,
+    tags: Option<String>,
+}
+
+async fn api<T: for<'de> Deserialize<'de>>(
+    path: &str,
+    query: &[(&str, &str)],
+    access_token: &str,
+) -> Result<T, crate::Error> {
+    let response = HTTP_CLIENT
+        .get(format!("{API_BASE}/{path}"))
+        .query(query)
+        .query(&[("access_token", access_token), ("mature_content", "true")])
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(
+            ServiceError::DeviantArt(format!("{path} returned {}", response.status())).into(),
+        );
+    }
+
+    Ok(response.json().await?)
+}
+
+/// Looks a deviation up through the api, downloading the original if the artist allows it.
+async fn from_api(
+    uuid: &str,
+    url: &Url,
+    access_token: &str,
+) -> Result<(Url, RecordDetails), crate::Error> {
+    let deviation: Deviation = api(&format!("deviation/{uuid}"), &[], access_token).await?;
+
+    let MetadataResponse { metadata } = api(
+        "deviation/metadata",
+        &[("deviationids[]", uuid)],
+        access_token,
+    )
+    .await?;
+
+    let metadata = metadata.into_iter().next();
+
+    let mut source = None;
+
+    if deviation.is_downloadable {
+        #[derive(Deserialize)]
+        struct Download {
+            src: Url,
+        }
+
+        source = api::<Download>(&format!("deviation/download/{uuid}"), &[], access_token)
+            .await
+            .ok()
+            .map(|download| download.src);
+    }
+
+    let Some(source) = source.or(deviation.content.map(|content| content.src)) else {
+        return Err(ServiceError::DeviantArt(String::from("deviation has no image")).into());
+    };
+
+    let details = RecordDetails {
+        title: deviation.title,
+        url: Some(deviation.url.unwrap_or_else(|| url.clone())),
+        authors: deviation
+            .author
+            .map(|author| AuthorCredit {
+                url: author_url(&author.username),
+                ..AuthorCredit::new(AuthorQuery::Name(author.username))
+            })
+            .into_iter()
+            .collect(),
+        caption: metadata
+            .as_ref()
+            .and_then(|metadata| metadata.description.as_deref())
+            .map(html_to_text)
+            .filter(|description| !description.is_empty()),
+        tags: Vec::new(),
+        native_tags: metadata
+            .map(|metadata| metadata.tags.into_iter().map(|tag| tag.tag_name).collect())
+            .unwrap_or_default(),
+    };
+
+    Ok((source, details))
+}
+
+/// Looks a deviation up through oEmbed, which needs no uuid but only links the preview sized image.
+async fn from_oembed(url: &Url) -> Result<(Url, RecordDetails), crate::Error> {
+    let oembed: OEmbed = HTTP_CLIENT
+        .get("https://backend.deviantart.com/oembed")
+        .query(&[("url", url.as_str())])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let details = RecordDetails {
+        title: oembed.title,
+        url: Some(url.clone()),
+        authors: oembed
+            .author_name
+            .map(|name| AuthorCredit {
+                url: oembed.author_url.or_else(|| author_url(&name)),
+                ..AuthorCredit::new(AuthorQuery::Name(name))
+            })
+            .into_iter()
+            .collect(),
+        caption: None,
+        tags: Vec::new(),
+        native_tags: oembed
+            .tags
+            .map(|tags| {
+                tags.split(',')
+                    .map(|tag| tag.trim().to_owned())
+                    .filter(|tag| !tag.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+
+    Ok((oembed.url, details))
+}
+
+#[async_trait]
+impl Service for DeviantArt {
+    fn host_matches(&self, host: &str) -> bool {
+        host == "deviantart.com" || host.ends_with(".deviantart.com")
+    }
+    fn name(&self) -> &str {
+        "deviantart"
+    }
+    fn secrets(&self) -> &[&str] {
+        &[DEVIANTART_CLIENT_ID, DEVIANTART_CLIENT_SECRET]
+    }
+    fn canonicalize(&self, url: &Url) -> Option<Url> {
+        if !self.host_matches(url.host_str()?) {
+            return None;
+        }
+
+        if let Some((user, slug)) = deviation(url) {
+            return Url::parse(&format!(
+                "https://www.deviantart.com/{}/art/{slug}",
+                user.to_lowercase()
+            ))
+            .ok();
+        }
+
+        match (subdomain_user(url), path_segments(url).as_slice()) {
+            (Some(user), []) => author_url(user),
+            (None, [user]) => author_url(user),
+            _ => None,
+        }
+    }
+    async fn authenticate(
+        &self,
+        secrets: &HashMap<String, String>,
+        _: Option<HashMap<String, String>>,
+    ) -> Result<HashMap<String, String>, crate::Error> {
+        // Client credential tokens only last an hour and can not be refreshed, so a new one is
+        // requested for every import.
+        let response = HTTP_CLIENT
+            .post("https://www.deviantart.com/oauth2/token")
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", &secrets[DEVIANTART_CLIENT_ID]),
+                ("client_secret", &secrets[DEVIANTART_CLIENT_SECRET]),
+            ])
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(ServiceError::DeviantArt(format!(
+                "requesting an access token returned {}",
+                response.status()
+            ))
+            .into());
+        }
+
+        let token: TokenResponse = response.json().await?;
+
+        Ok(HashMap::from([(
+            ACCESS_TOKEN.to_owned(),
+            token.access_token,
+        )]))
+    }
+    async fn import(
+        &self,
+        chronicle: &crate::Chronicle,
+        url: Url,
+        records: &mut Vec<Record>,
+        _: HashMap<String, String>,
+        authentication: HashMap<String, String>,
+    ) -> Result<(), crate::Error> {
+        if deviation(&url).is_none() {
+            return Err(crate::Error::InvalidUrl {
+                service: "deviantart",
+                url,
+            });
+        }
+
+        let page = HTTP_CLIENT
+            .get(url.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let resolved = match app_url_uuid(&page) {
+            Some(uuid) => from_api(uuid, &url, &authentication[ACCESS_TOKEN]).await,
+            None => Err(ServiceError::DeviantArt(String::from(
+                "deviation page does not link its uuid",
+            ))
+            .into()),
+        };
+
+        let (source, details) = match resolved {
+            Ok(resolved) => resolved,
+            Err(error) => {
+                tracing::warn!("falling back to oembed for {url}: {error}");
+                from_oembed(&url).await?
+            }
+        };
+
+        let data = HTTP_CLIENT
+            .get(source.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        let extension = PathBuf::from(source.path())
+            .extension()
+            .map(|s| format!(".{}", s.to_string_lossy()))
+            .unwrap_or_default();
+
+        let file_name = format!("{}{extension}", Uuid::new_v4());
+        let mut writer = BufWriter::new(File::create_new(
+            chronicle.config.data_path.join(&file_name),
+        )?);
+
+        writer.write_all(&data)?;
+
+        records.push(Record {
+            path: PathBuf::from(file_name),
+            size: data.len(),
+            hash: bytemuck::cast(crc32fast::hash(&data)),
+            details,
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deviation() {
+        for url in [
+            "https://www.deviantart.com/someone/art/Sunset-Study-123456",
+            "https://someone.deviantart.com/art/Sunset-Study-123456",
+        ] {
+            assert_eq!(
+                deviation(&Url::parse(url).unwrap()),
+                Some(("someone", "Sunset-Study-123456")),
+                "{url}"
+            );
+        }
+
+        assert_eq!(
+            deviation(&Url::parse("https://www.deviantart.com/someone/gallery").unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_app_url_uuid() {
+        let page = r#"<meta property="da:appurl" content="DeviantArt://deviation/0A1B2C3D-4E5F-6789-ABCD-EF0123456789"/>"#;
+
+        assert_eq!(
+            app_url_uuid(page),
+            Some("0A1B2C3D-4E5F-6789-ABCD-EF0123456789")
+        );
+        assert_eq!(app_url_uuid("<html></html>"), None);
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+use async_trait::async_trait;
+use reqwest::header::ACCEPT;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    author::{AuthorCredit, AuthorQuery},
+    canonical::path_segments,
+    record::{Record, RecordDetails},
+    Chronicle, ServiceError, HTTP_CLIENT,
+};
+
+use super::{caption_with_alt, html_to_text, Service};
+
+/// Imports statuses from Mastodon and the other ActivityPub servers which implement its api.
+///
+/// Instances can be on any host, so besides the instances listed in the config a url is checked
+/// by asking its host for the status it links to.
+#[derive(Default)]
+pub struct Mastodon {
+    /// The status fetched while probing a url, so importing it does not fetch it again.
+    probed: Mutex<Option<(Url, Status)>>,
+}
+
+/// Extracts the status id from the url forms Mastodon links statuses with, including statuses of
+/// remote accounts viewed through another instance.
+fn status_id(url: &Url) -> Option<&str> {
+    let id = match path_segments(url).as_slice() {
+        [account, id] if account.starts_with('@') => *id,
+        ["users", _, "statuses", id] | ["web", "statuses", id] | ["notice", id] => *id,
+        ["web", account, id] if account.starts_with('@') => *id,
+        _ => return None,
+    };
+
+    // Profile tabs share the form of status urls, but every server's ids contain digits.
+    (id.chars().all(|c| c.is_ascii_alphanumeric()) && id.chars().any(|c| c.is_ascii_digit()))
+        .then_some(id)
+}
+
+fn status_api_url(url: &Url) -> Option<Url> {
+    let id = status_id(url)?;
+    let host = url.host_str()?;
+
+    Url::parse(&format!("https://{host}/api/v1/statuses/{id}")).ok()
+}
+
+#[derive(Deserialize)]
+struct Status {
+    url: Option<Url>,
+    uri: String,
+    content: String,
+    spoiler_text: String,
+    account: Account,
+    media_attachments: Vec<MediaAttachment>,
+    tags: Vec<StatusTag>,
+}
+
+#[derive(Deserialize)]
+struct Account {
+    acct: String,
+    url: Url,
+}
+
+impl Account {
+    /// The full `user@instance` handle, which local accounts leave the instance out of.
+    fn handle(&self) -> String {
+        match self.url.host_str() {
+            Some(host) if !self.acct.contains('@') => format!("{}@{host}", self.acct),
+            _ => self.acct.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct MediaAttachment {
+    url: Option<Url>,
+    remote_url: Option<Url>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StatusTag {
+    name: String,
+}
+
+async fn fetch_status(url: &Url) -> Result<Status, crate::Error> {
+    let Some(api_url) = status_api_url(url) else {
+        return Err(crate::Error::InvalidUrl {
+            service: "mastodon",
+            url: url.clone(),
+        });
+    };
+
+    let response = HTTP_CLIENT
+        .get(api_url)
+        .header(ACCEPT, "application/json")
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(ServiceError::Mastodon(format!(
+            "fetching the status returned {}",
+            response.status()
+        ))
+        .into());
+    }
+
+    Ok(response.json().await?)
+}
+
+impl Mastodon {
+    /// Takes the status of `url` if it was fetched while probing, otherwise fetching it.
+    async fn status(&self, url: &Url) -> Result<Status, crate::Error> {
+        let probed = self.probed.lock().await.take();
+
+        match probed {
+            Some((probed, status)) if probed == *url => Ok(status),
+            _ => fetch_status(url).await,
+        }
+    }
+}
+
+#[async_trait]
+impl Service for Mastodon {
+    fn host_matches(&self, _host: &str) -> bool {
+        false
+    }
+    fn name(&self) -> &str {
+        "mastodon"
+    }
+    fn secrets(&self) -> &[&str] {
+        &[]
+    }
+    async fn probe(&self, chronicle: &Chronicle, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+
+        if status_id(url).is_none() {
+            return false;
+        }
+
+        if chronicle
+            .config
+            .mastodon_instances
+            .iter()
+            .any(|instance| instance.eq_ignore_ascii_case(host))
+        {
+            return true;
+        }
+
+        match fetch_status(url).await {
+            Ok(status) => {
+                *self.probed.lock().await = Some((url.clone(), status));
+                true
+            }
+            Err(_) => false,
+        }
+    }
+    async fn authenticate(
+        &self,
+        _: &HashMap<String, String>,
+        _: Option<HashMap<String, String>>,
+    ) -> Result<HashMap<String, String>, crate::Error> {
+        Ok(HashMap::new())
+    }
+    async fn import(
+        &self,
+        chronicle: &crate::Chronicle,
+        url: Url,
+        records: &mut Vec<Record>,
+        _: HashMap<String, String>,
+        _: HashMap<String, String>,
+    ) -> Result<(), crate::Error> {
+        let status = self.status(&url).await?;
+
+        if !status
+            .media_attachments
+            .iter()
+            .any(|attachment| attachment.url.is_some() || attachment.remote_url.is_some())
+        {
+            return Err(crate::Error::Generic(format!(
+                "{url} does not have any media attached"
+            )));
+        }
+
+        let text = html_to_text(&status.content);
+        let text = match status.spoiler_text.trim() {
+            "" => text,
+            spoiler => format!("{spoiler}\n\n{text}"),
+        };
+
+        let details = RecordDetails {
+            title: None,
+            url: Some(
+                status
+                    .url
+                    .clone()
+                    .or_else(|| Url::parse(&status.uri).ok())
+                    .unwrap_or(url),
+            ),
+            authors: vec![AuthorCredit {
+                url: Some(status.account.url.clone()),
+                ..AuthorCredit::new(AuthorQuery::Name(status.account.handle()))
+            }],
+            caption: None,
+            tags: Vec::new(),
+            native_tags: status.tags.iter().map(|tag| tag.name.clone()).collect(),
+        };
+
+        for attachment in status.media_attachments.iter() {
+            let Some(media_url) = attachment.url.as_ref().or(attachment.remote_url.as_ref()) else {
+                continue;
+            };
+
+            let extension = PathBuf::from(media_url.path())
+                .extension()
+                .map(|s| format!(".{}", s.to_string_lossy()))
+                .unwrap_or_default();
+
+            let file_name = format!("{}{extension}", Uuid::new_v4());
+            let mut writer = BufWriter::new(File::create_new(
+                chronicle.config.data_path.join(&file_name),
+            )?);
+
+            let data = HTTP_CLIENT
+                .get(media_url.clone())
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?;
+
+            writer.write_all(&data)?;
+
+            records.push(Record {
+                path: PathBuf::from(file_name),
+                size: data.len(),
+                hash: bytemuck::cast(crc32fast::hash(&data)),
+                details: RecordDetails {
+                    caption: caption_with_alt(Some(&text), attachment.description.as_deref()),
+                    ..details.clone()
+                },
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_id() {
+        for url in [
+            "https://mastodon.social/@someone/112233",
+            "https://mastodon.social/@someone@other.example/112233",
+            "https://mastodon.social/users/someone/statuses/112233",
+            "https://mastodon.social/web/statuses/112233",
+            "https://mastodon.social/web/@someone/112233",
+        ] {
+            assert_eq!(
+                status_id(&Url::parse(url).unwrap()),
+                Some("112233"),
+                "{url}"
+            );
+        }
+
+        for url in [
+            "https://mastodon.social/@someone",
+            "https://mastodon.social/@someone/with_replies",
+            "https://mastodon.social/@someone/media",
+            "https://example.com/blog/112233",
+        ] {
+            assert_eq!(status_id(&Url::parse(url).unwrap()), None, "{url}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_probed_status() {
+        let url = Url::parse("https://mastodon.invalid/@someone/112233").unwrap();
+
+        let status: Status = serde_json::from_str(
+            r#"{
+                "url": "https://mastodon.invalid/@someone/112233",
+                "uri": "https://mastodon.invalid/users/someone/statuses/112233",
+                "content": "<p>art</p>",
+                "spoiler_text": "",
+                "account": { "acct": "someone", "url": "https://mastodon.invalid/@someone" },
+                "media_attachments": [],
+                "tags": []
+            }"#,
+        )
+        .unwrap();
+
+        let mastodon = Mastodon::default();
+        *mastodon.probed.lock().await = Some((url.clone(), status));
+
+        assert_eq!(mastodon.status(&url).await.unwrap().content, "<p>art</p>");
+        assert!(mastodon.probed.lock().await.is_none());
+    }
+}
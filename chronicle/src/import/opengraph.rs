@@ -0,0 +1,391 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::header::{CONTENT_TYPE, REFERER};
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    author::{AuthorCredit, AuthorQuery},
+    record::{Record, RecordDetails},
+    HTTP_CLIENT,
+};
+
+use super::{unescape_html, Service};
+
+lazy_static! {
+    static ref META_TAG: Regex = Regex::new(r#"(?is)<meta\s[^>]*>"#).unwrap();
+    static ref ATTRIBUTE: Regex =
+        Regex::new(r#"(?s)([a-zA-Z:_-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+    static ref TITLE_TAG: Regex = Regex::new(r#"(?is)<title[^>]*>(.*?)</title>"#).unwrap();
+}
+
+/// Imports from any site no other service recognises, using the metadata a page provides for link
+/// previews or downloading the url itself if it is an image or video.
+pub struct OpenGraph;
+
+/// The metadata extracted from the `<meta>` tags of a page.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PageMetadata {
+    pub images: Vec<Url>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub author: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl PageMetadata {
+    /// Extracts the open graph, twitter card and standard metadata from a page, resolving relative
+    /// image urls against `base`.
+    pub fn extract(html: &str, base: &Url) -> Self {
+        let mut meta: HashMap<String, Vec<String>> = HashMap::new();
+
+        for tag in META_TAG.find_iter(html) {
+            let mut key = None;
+            let mut content = None;
+
+            for attribute in ATTRIBUTE.captures_iter(tag.as_str()) {
+                let value = attribute
+                    .get(2)
+                    .or_else(|| attribute.get(3))
+                    .map(|value| unescape_html(value.as_str().trim()));
+
+                match attribute[1].to_ascii_lowercase().as_str() {
+                    "property" | "name" => key = value.map(|key| key.to_ascii_lowercase()),
+                    "content" => content = value,
+                    _ => {}
+                }
+            }
+
+            if let (Some(key), Some(content)) = (key, content) {
+                if !content.is_empty() {
+                    meta.entry(key).or_default().push(content);
+                }
+            }
+        }
+
+        let first = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|key| meta.get(*key).and_then(|values| values.first()))
+                .cloned()
+        };
+
+        let mut images = Vec::new();
+
+        for key in ["og:image", "og:image:url", "og:image:secure_url"] {
+            for image in meta.get(key).into_iter().flatten() {
+                if let Ok(image) = base.join(image) {
+                    if !images.contains(&image) {
+                        images.push(image);
+                    }
+                }
+            }
+        }
+
+        if images.is_empty() {
+            if let Some(image) = first(&["twitter:image", "twitter:image:src"]) {
+                images.extend(base.join(&image).ok());
+            }
+        }
+
+        let title = first(&["og:title", "twitter:title"]).or_else(|| {
+            TITLE_TAG
+                .captures(html)
+                .map(|title| unescape_html(title[1].trim()))
+                .filter(|title| !title.is_empty())
+        });
+
+        let author = first(&["author", "article:author", "twitter:creator"])
+            .map(|author| author.trim_start_matches('@').to_owned());
+
+        Self {
+            images,
+            title,
+            description: first(&["og:description", "twitter:description", "description"]),
+            author,
+            tags: meta.remove("article:tag").unwrap_or_default(),
+        }
+    }
+
+    fn details(self, url: &Url) -> RecordDetails {
+        let authors = self.author.map(author_credit).into_iter().collect();
+
+        RecordDetails {
+            title: self.title,
+            url: Some(url.clone()),
+            authors,
+            caption: self.description,
+            tags: Vec::new(),
+            native_tags: self.tags,
+        }
+    }
+}
+
+/// Credits the author named by a page. Pages often name their author with a link to their profile,
+/// which is credited by the last segment of its path so it can be created like any other name.
+fn author_credit(author: String) -> AuthorCredit {
+    let profile = Url::parse(&author)
+        .ok()
+        .filter(|url| url.scheme().starts_with("http"));
+
+    let Some(profile) = profile else {
+        return AuthorCredit::new(AuthorQuery::Name(author));
+    };
+
+    let name = profile
+        .path_segments()
+        .and_then(|mut segments| segments.rfind(|segment| !segment.is_empty()))
+        .map(|segment| segment.trim_start_matches('@').to_owned())
+        .filter(|name| !name.is_empty())
+        .or_else(|| profile.host_str().map(str::to_owned))
+        .unwrap_or(author);
+
+    AuthorCredit {
+        url: Some(profile),
+        ..AuthorCredit::new(AuthorQuery::Name(name))
+    }
+}
+
+/// Whether a response is a file which can be stored as a work as is.
+fn is_media(content_type: &str) -> bool {
+    content_type.starts_with("image/") || content_type.starts_with("video/")
+}
+
+/// Picks the extension of a downloaded file from its url, or its content type if the url has none.
+pub(crate) fn extension(url: &Url, content_type: &str) -> String {
+    if let Some(extension) = PathBuf::from(url.path()).extension() {
+        return format!(".{}", extension.to_string_lossy());
+    }
+
+    let subtype = content_type
+        .split(';')
+        .next()
+        .and_then(|mime| mime.split('/').nth(1))
+        .unwrap_or_default()
+        .trim();
+
+    match subtype {
+        "" => String::new(),
+        "jpeg" => String::from(".jpg"),
+        subtype if subtype.chars().all(|c| c.is_ascii_alphanumeric()) => format!(".{subtype}"),
+        _ => String::new(),
+    }
+}
+
+fn write_record(
+    data_path: &Path,
+    data: &[u8],
+    extension: &str,
+    details: RecordDetails,
+) -> Result<Record, crate::Error> {
+    let file_name = format!("{}{extension}", Uuid::new_v4());
+    let mut writer = BufWriter::new(File::create_new(data_path.join(&file_name))?);
+
+    writer.write_all(data)?;
+
+    Ok(Record {
+        path: PathBuf::from(file_name),
+        hash: bytemuck::cast(crc32fast::hash(data)),
+        size: data.len(),
+        details,
+    })
+}
+
+#[async_trait]
+impl Service for OpenGraph {
+    fn host_matches(&self, _host: &str) -> bool {
+        false
+    }
+
+    fn name(&self) -> &str {
+        "opengraph"
+    }
+
+    fn secrets(&self) -> &[&str] {
+        &[]
+    }
+
+    async fn authenticate(
+        &self,
+        _secrets: &HashMap<String, String>,
+        _previous_result: Option<HashMap<String, String>>,
+    ) -> Result<HashMap<String, String>, crate::Error> {
+        Ok(HashMap::new())
+    }
+
+    async fn import(
+        &self,
+        chronicle: &crate::Chronicle,
+        url: Url,
+        records: &mut Vec<Record>,
+        _secrets: HashMap<String, String>,
+        _authentication: HashMap<String, String>,
+    ) -> Result<(), crate::Error> {
+        let response = HTTP_CLIENT
+            .get(url.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        let data_path = &chronicle.config.data_path;
+
+        if is_media(&content_type) {
+            let data = response.bytes().await?;
+
+            records.push(write_record(
+                data_path,
+                &data,
+                &extension(&url, &content_type),
+                PageMetadata::default().details(&url),
+            )?);
+
+            return Ok(());
+        }
+
+        let page = PageMetadata::extract(&response.text().await?, &url);
+
+        if page.images.is_empty() {
+            return Err(crate::Error::Generic(format!(
+                "{url} does not link any images"
+            )));
+        }
+
+        let images = page.images.clone();
+        let details = page.details(&url);
+
+        for image in images {
+            let response = HTTP_CLIENT
+                .get(image.clone())
+                .header(REFERER, url.as_str())
+                .send()
+                .await?
+                .error_for_status()?;
+
+            let content_type = response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|content_type| content_type.to_str().ok())
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+
+            let data = response.bytes().await?;
+
+            records.push(write_record(
+                data_path,
+                &data,
+                &extension(&image, &content_type),
+                details.clone(),
+            )?);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Fallback Title</title>
+    <meta charset="utf-8">
+    <meta property="og:title" content="Sunset &amp; Sea" />
+    <meta property="og:description" content='A painting of the "coast"'>
+    <meta property="og:image" content="/media/sunset.png">
+    <meta property="og:image" content="https://cdn.example.com/sunset-detail.jpg">
+    <meta name="twitter:image" content="https://cdn.example.com/card.jpg">
+    <meta name="twitter:creator" content="@painter">
+    <meta property="article:tag" content="Landscape">
+    <meta property="article:tag" content="oil paint">
+</head>
+<body></body>
+</html>"#;
+
+    #[test]
+    fn test_extract() {
+        let base = Url::parse("https://example.com/gallery/sunset").unwrap();
+
+        assert_eq!(
+            PageMetadata::extract(PAGE, &base),
+            PageMetadata {
+                images: vec![
+                    Url::parse("https://example.com/media/sunset.png").unwrap(),
+                    Url::parse("https://cdn.example.com/sunset-detail.jpg").unwrap(),
+                ],
+                title: Some(String::from("Sunset & Sea")),
+                description: Some(String::from("A painting of the \"coast\"")),
+                author: Some(String::from("painter")),
+                tags: vec![String::from("Landscape"), String::from("oil paint")],
+            }
+        );
+    }
+
+    #[test]
+    fn test_extract_fallbacks() {
+        let base = Url::parse("https://example.com/").unwrap();
+        let page = r#"<title> Plain </title><meta name="twitter:image:src" content="card.jpg"><meta name="description" content="Described"><meta name="author" content="Someone">"#;
+
+        assert_eq!(
+            PageMetadata::extract(page, &base),
+            PageMetadata {
+                images: vec![Url::parse("https://example.com/card.jpg").unwrap()],
+                title: Some(String::from("Plain")),
+                description: Some(String::from("Described")),
+                author: Some(String::from("Someone")),
+                tags: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_author_credit() {
+        let credit = author_credit(String::from("https://blog.example.com/authors/@someone/"));
+
+        assert!(matches!(&credit.query, AuthorQuery::Name(name) if name == "someone"));
+        assert_eq!(
+            credit.url.as_ref().map(Url::as_str),
+            Some("https://blog.example.com/authors/@someone/")
+        );
+
+        let credit = author_credit(String::from("https://someone.example.com/"));
+
+        assert!(matches!(&credit.query, AuthorQuery::Name(name) if name == "someone.example.com"));
+
+        let credit = author_credit(String::from("Someone"));
+
+        assert!(matches!(&credit.query, AuthorQuery::Name(name) if name == "Someone"));
+        assert!(credit.url.is_none());
+    }
+
+    #[test]
+    fn test_extension() {
+        let url = Url::parse("https://example.com/image").unwrap();
+
+        assert_eq!(extension(&url, "image/jpeg"), ".jpg");
+        assert_eq!(extension(&url, "image/png; charset=binary"), ".png");
+        assert_eq!(extension(&url, "image/svg+xml"), "");
+        assert_eq!(
+            extension(
+                &Url::parse("https://example.com/a.gif").unwrap(),
+                "image/png"
+            ),
+            ".gif"
+        );
+    }
+}
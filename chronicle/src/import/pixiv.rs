@@ -0,0 +1,579 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use reqwest::header::{AUTHORIZATION, REFERER, USER_AGENT};
+use serde::Deserialize;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    author::{AuthorCredit, AuthorQuery},
+    canonical::path_segments,
+    record::{Record, RecordDetails},
+    tag::mapping::normalize_native_tag,
+    ServiceError, HTTP_CLIENT,
+};
+
+use super::{html_to_text, Service};
+
+const PIXIV_REFRESH_TOKEN: &str = "pixiv-refresh-token";
+
+const ACCESS_TOKEN: &str = "access-token";
+const REFRESH_TOKEN: &str = "refresh-token";
+
+/// The credentials of the official android app, which is the only client pixiv issues refresh
+/// tokens for.
+const CLIENT_ID: &str = "MOBrBDS8blbauoSck0ZfDbtuzpyT";
+const CLIENT_SECRET: &str = "lsACyCD94FhDUtGTXi3QzcFE2uU1hqtDaKeqrdwj";
+const APP_USER_AGENT: &str = "PixivAndroidApp/5.0.234 (Android 11; Pixel 5)";
+
+/// Pixiv refuses to serve originals without a referer from one of its own sites.
+const IMAGE_REFERER: &str = "https://app-api.pixiv.net/";
+
+pub struct Pixiv {
+    pub api_base: Url,
+    pub oauth_base: Url,
+}
+
+impl Default for Pixiv {
+    fn default() -> Self {
+        Self {
+            api_base: Url::parse("https://app-api.pixiv.net/").unwrap(),
+            oauth_base: Url::parse("https://oauth.secure.pixiv.net/").unwrap(),
+        }
+    }
+}
+
+/// Extracts the illustration id from any of the url forms pixiv links to artworks with.
+fn illust_id(url: &Url) -> Option<u64> {
+    let id = match path_segments(url).as_slice() {
+        ["artworks", id] | [_, "artworks", id] | ["i", id] => id.to_string(),
+        ["member_illust.php"] => url
+            .query_pairs()
+            .find(|(key, _)| key == "illust_id")?
+            .1
+            .into_owned(),
+        _ => return None,
+    };
+
+    id.parse().ok()
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct IllustResponse {
+    illust: Illust,
+}
+
+#[derive(Deserialize)]
+struct Illust {
+    id: u64,
+    title: String,
+    #[serde(rename = "type")]
+    kind: IllustKind,
+    caption: String,
+    user: PixivUser,
+    tags: Vec<PixivTag>,
+    meta_single_page: MetaSinglePage,
+    meta_pages: Vec<MetaPage>,
+}
+
+#[derive(Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum IllustKind {
+    Illust,
+    Manga,
+    Ugoira,
+}
+
+#[derive(Deserialize)]
+struct PixivUser {
+    id: u64,
+    account: String,
+}
+
+#[derive(Deserialize)]
+struct PixivTag {
+    name: String,
+    translated_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MetaSinglePage {
+    original_image_url: Option<Url>,
+}
+
+#[derive(Deserialize)]
+struct MetaPage {
+    image_urls: ImageUrls,
+}
+
+#[derive(Deserialize)]
+struct ImageUrls {
+    original: Url,
+}
+
+#[derive(Deserialize)]
+struct UgoiraResponse {
+    ugoira_metadata: UgoiraMetadata,
+}
+
+#[derive(Deserialize)]
+struct UgoiraMetadata {
+    zip_urls: ZipUrls,
+}
+
+#[derive(Deserialize)]
+struct ZipUrls {
+    medium: Url,
+}
+
+impl Illust {
+    /// Pixiv tags are mostly japanese, which can not be used as tag names, so their translations
+    /// are used as well whenever the original is not entirely ascii.
+    fn native_tags(&self) -> Vec<String> {
+        let mut tags = Vec::with_capacity(self.tags.len());
+
+        for tag in self.tags.iter() {
+            tags.push(tag.name.clone());
+
+            if !tag.name.is_ascii() || normalize_native_tag(&tag.name).is_none() {
+                if let Some(translated) = &tag.translated_name {
+                    tags.push(translated.clone());
+                }
+            }
+        }
+
+        tags
+    }
+
+    fn details(&self) -> RecordDetails {
+        RecordDetails {
+            title: Some(self.title.clone()),
+            url: Url::parse(&format!("https://www.pixiv.net/artworks/{}", self.id)).ok(),
+            authors: vec![AuthorCredit {
+                url: Url::parse(&format!("https://www.pixiv.net/users/{}", self.user.id)).ok(),
+                ..AuthorCredit::new(AuthorQuery::Name(self.user.account.clone()))
+            }],
+            caption: Some(html_to_text(&self.caption)),
+            tags: Vec::new(),
+            native_tags: self.native_tags(),
+        }
+    }
+}
+
+impl Pixiv {
+    async fn api<T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        illust_id: u64,
+        access_token: &str,
+    ) -> Result<T, crate::Error> {
+        let mut url = self.api_base.join(path)?;
+        url.query_pairs_mut()
+            .append_pair("illust_id", &illust_id.to_string());
+
+        let response = HTTP_CLIENT
+            .get(url)
+            .header(USER_AGENT, APP_USER_AGENT)
+            .header(AUTHORIZATION, format!("Bearer {access_token}"))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(
+                ServiceError::Pixiv(format!("{path} returned {}", response.status())).into(),
+            );
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Downloads an original into `data_path`, returning the record for it.
+    async fn download(
+        &self,
+        data_path: &Path,
+        url: &Url,
+        details: &RecordDetails,
+    ) -> Result<Record, crate::Error> {
+        let response = HTTP_CLIENT
+            .get(url.clone())
+            .header(USER_AGENT, APP_USER_AGENT)
+            .header(REFERER, IMAGE_REFERER)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(ServiceError::Pixiv(format!(
+                "downloading {url} returned {}",
+                response.status()
+            ))
+            .into());
+        }
+
+        let data = response.bytes().await?;
+
+        let extension = PathBuf::from(url.path())
+            .extension()
+            .map(|s| format!(".{}", s.to_string_lossy()))
+            .unwrap_or_default();
+
+        let file_name = format!("{}{extension}", Uuid::new_v4());
+        let mut writer = BufWriter::new(File::create_new(data_path.join(&file_name))?);
+
+        writer.write_all(&data)?;
+
+        Ok(Record {
+            path: PathBuf::from(file_name),
+            hash: bytemuck::cast(crc32fast::hash(&data)),
+            size: data.len(),
+            details: details.clone(),
+        })
+    }
+
+    /// Fetches every page of an illustration into `data_path`.
+    ///
+    /// Ugoira are stored as the zip of their frames pixiv serves.
+    async fn import_illust(
+        &self,
+        data_path: &Path,
+        illust_id: u64,
+        access_token: &str,
+        records: &mut Vec<Record>,
+    ) -> Result<(), crate::Error> {
+        let IllustResponse { illust } = self
+            .api("v1/illust/detail", illust_id, access_token)
+            .await?;
+
+        let details = illust.details();
+
+        if illust.kind == IllustKind::Ugoira {
+            let UgoiraResponse { ugoira_metadata } = self
+                .api("v1/ugoira/metadata", illust_id, access_token)
+                .await?;
+
+            let medium = ugoira_metadata.zip_urls.medium;
+
+            // The metadata only links the small frames, the originals are at the same path.
+            let original = Url::parse(&medium.as_str().replace("600x600", "1920x1080"))?;
+
+            match self.download(data_path, &original, &details).await {
+                Ok(record) => records.push(record),
+                Err(_) => records.push(self.download(data_path, &medium, &details).await?),
+            }
+
+            return Ok(());
+        }
+
+        let originals: Vec<&Url> = match &illust.meta_single_page.original_image_url {
+            Some(original) => vec![original],
+            None => illust
+                .meta_pages
+                .iter()
+                .map(|page| &page.image_urls.original)
+                .collect(),
+        };
+
+        for original in originals {
+            records.push(self.download(data_path, original, &details).await?);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Service for Pixiv {
+    fn host_matches(&self, host: &str) -> bool {
+        host == "www.pixiv.net" || host == "pixiv.net"
+    }
+
+    fn name(&self) -> &str {
+        "pixiv"
+    }
+
+    fn secrets(&self) -> &[&str] {
+        &[PIXIV_REFRESH_TOKEN]
+    }
+
+    fn canonicalize(&self, url: &Url) -> Option<Url> {
+        if !self.host_matches(url.host_str()?) {
+            return None;
+        }
+
+        if let Some(id) = illust_id(url) {
+            return Url::parse(&format!("https://www.pixiv.net/artworks/{id}")).ok();
+        }
+
+        match path_segments(url).as_slice() {
+            ["users", id] | [_, "users", id] => {
+                Url::parse(&format!("https://www.pixiv.net/users/{id}")).ok()
+            }
+            _ => None,
+        }
+    }
+
+    async fn authenticate(
+        &self,
+        secrets: &HashMap<String, String>,
+        previous_result: Option<HashMap<String, String>>,
+    ) -> Result<HashMap<String, String>, crate::Error> {
+        // Pixiv may rotate the refresh token, the latest one is kept with the access token.
+        let refresh_token = previous_result
+            .as_ref()
+            .and_then(|previous| previous.get(REFRESH_TOKEN))
+            .unwrap_or(&secrets[PIXIV_REFRESH_TOKEN]);
+
+        let response = HTTP_CLIENT
+            .post(self.oauth_base.join("auth/token")?)
+            .header(USER_AGENT, APP_USER_AGENT)
+            .form(&[
+                ("client_id", CLIENT_ID),
+                ("client_secret", CLIENT_SECRET),
+                ("grant_type", "refresh_token"),
+                ("include_policy", "true"),
+                ("refresh_token", refresh_token),
+            ])
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(ServiceError::Pixiv(format!(
+                "refreshing the access token returned {}",
+                response.status()
+            ))
+            .into());
+        }
+
+        let token: TokenResponse = response.json().await?;
+
+        let mut results = HashMap::new();
+
+        results.insert(ACCESS_TOKEN.to_string(), token.access_token);
+        results.insert(
+            REFRESH_TOKEN.to_string(),
+            token
+                .refresh_token
+                .unwrap_or_else(|| refresh_token.to_owned()),
+        );
+
+        Ok(results)
+    }
+
+    async fn import(
+        &self,
+        chronicle: &crate::Chronicle,
+        url: Url,
+        records: &mut Vec<Record>,
+        _secrets: HashMap<String, String>,
+        authentication: HashMap<String, String>,
+    ) -> Result<(), crate::Error> {
+        let Some(illust_id) = illust_id(&url) else {
+            return Err(crate::Error::InvalidUrl {
+                service: "pixiv",
+                url,
+            });
+        };
+
+        self.import_illust(
+            &chronicle.config.data_path,
+            illust_id,
+            &authentication[ACCESS_TOKEN],
+            records,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        extract::Query,
+        http::{HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::{get, post},
+        Router,
+    };
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    const ILLUST_DETAIL: &str = include_str!("../../tests/fixtures/pixiv/illust_detail.json");
+    const MANGA_DETAIL: &str = include_str!("../../tests/fixtures/pixiv/manga_detail.json");
+    const UGOIRA_DETAIL: &str = include_str!("../../tests/fixtures/pixiv/ugoira_detail.json");
+    const UGOIRA_METADATA: &str = include_str!("../../tests/fixtures/pixiv/ugoira_metadata.json");
+
+    /// Serves the recorded responses with every url pointed at `base`.
+    async fn fixture_server() -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+
+        let fixture = {
+            let base = base.clone();
+            move |body: &str| body.replace("https://i.pximg.net/", base.as_str())
+        };
+
+        let detail = {
+            let fixture = fixture.clone();
+            move |headers: HeaderMap, Query(query): Query<HashMap<String, String>>| async move {
+                if headers.get(AUTHORIZATION).map(|h| h.as_bytes()) != Some(b"Bearer access") {
+                    return (StatusCode::BAD_REQUEST, String::new());
+                }
+
+                let body = match query["illust_id"].as_str() {
+                    "100" => ILLUST_DETAIL,
+                    "101" => MANGA_DETAIL,
+                    "102" => UGOIRA_DETAIL,
+                    _ => return (StatusCode::NOT_FOUND, String::new()),
+                };
+
+                (StatusCode::OK, fixture(body))
+            }
+        };
+
+        let ugoira = move || async move { fixture(UGOIRA_METADATA) };
+
+        let image = |headers: HeaderMap, uri: axum::http::Uri| async move {
+            if headers.get(REFERER).map(|h| h.as_bytes()) != Some(IMAGE_REFERER.as_bytes()) {
+                return (StatusCode::FORBIDDEN, Vec::new()).into_response();
+            }
+
+            if uri.path().contains("1920x1080") {
+                return StatusCode::NOT_FOUND.into_response();
+            }
+
+            uri.path().as_bytes().to_vec().into_response()
+        };
+
+        let token = || async {
+            r#"{"access_token": "access", "refresh_token": "rotated", "expires_in": 3600}"#
+        };
+
+        let app = Router::new()
+            .route("/v1/illust/detail", get(detail))
+            .route("/v1/ugoira/metadata", get(ugoira))
+            .route("/auth/token", post(token))
+            .fallback(image);
+
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        base
+    }
+
+    async fn import(illust_id: u64) -> (tempfile::TempDir, Vec<Record>) {
+        let base = fixture_server().await;
+
+        let pixiv = Pixiv {
+            api_base: base.clone(),
+            oauth_base: base,
+        };
+
+        let secrets = HashMap::from([(PIXIV_REFRESH_TOKEN.to_owned(), String::from("refresh"))]);
+        let authentication = pixiv.authenticate(&secrets, None).await.unwrap();
+
+        assert_eq!(authentication[REFRESH_TOKEN], "rotated");
+
+        let data_path = tempfile::tempdir().unwrap();
+        let mut records = Vec::new();
+
+        pixiv
+            .import_illust(
+                data_path.path(),
+                illust_id,
+                &authentication[ACCESS_TOKEN],
+                &mut records,
+            )
+            .await
+            .unwrap();
+
+        (data_path, records)
+    }
+
+    #[test]
+    fn test_illust_id() {
+        for url in [
+            "https://www.pixiv.net/artworks/100",
+            "https://www.pixiv.net/en/artworks/100",
+            "https://pixiv.net/i/100",
+            "https://www.pixiv.net/member_illust.php?mode=medium&illust_id=100",
+        ] {
+            assert_eq!(illust_id(&Url::parse(url).unwrap()), Some(100), "{url}");
+        }
+
+        assert_eq!(
+            illust_id(&Url::parse("https://www.pixiv.net/users/1").unwrap()),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_single_page() {
+        let (data_path, records) = import(100).await;
+
+        assert_eq!(records.len(), 1);
+
+        let details = &records[0].details;
+
+        assert_eq!(details.title.as_deref(), Some("Inkling"));
+        assert_eq!(details.caption.as_deref(), Some("Commission\nThanks!"));
+        assert_eq!(
+            details.url.as_ref().map(Url::as_str),
+            Some("https://www.pixiv.net/artworks/100")
+        );
+        assert_eq!(
+            details.authors[0].url.as_ref().map(Url::as_str),
+            Some("https://www.pixiv.net/users/7")
+        );
+        assert!(matches!(
+            &details.authors[0].query,
+            AuthorQuery::Name(name) if name == "marina_artist"
+        ));
+        assert_eq!(
+            details.native_tags,
+            vec![
+                "スプラトゥーン",
+                "Splatoon",
+                "スプラトゥーン3",
+                "Splatoon 3",
+                "オリジナル1000users入り",
+                "original 1000+ bookmarks",
+                "inkling"
+            ]
+        );
+
+        let data = std::fs::read(data_path.path().join(&records[0].path)).unwrap();
+        assert_eq!(data, b"/img-original/img/2024/01/01/00/00/00/100_p0.png");
+        assert_eq!(records[0].path.extension().unwrap(), "png");
+    }
+
+    #[tokio::test]
+    async fn test_multi_page() {
+        let (_data_path, records) = import(101).await;
+
+        assert_eq!(records.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_ugoira() {
+        let (data_path, records) = import(102).await;
+
+        assert_eq!(records.len(), 1);
+
+        // The fixture server has no originals so the frames pixiv linked are used instead.
+        let data = std::fs::read(data_path.path().join(&records[0].path)).unwrap();
+        assert_eq!(
+            data,
+            b"/img-zip-ugoira/img/2024/01/01/00/00/00/102_ugoira600x600.zip"
+        );
+    }
+}
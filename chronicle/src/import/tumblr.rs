@@ -23,7 +23,8 @@ use url::Url;
 use uuid::Uuid;
 
 use crate::{
-    author::AuthorQuery,
+    author::{AuthorCredit, AuthorQuery},
+    canonical::path_segments,
     http::register_oauth2_handler,
     record::{Record, RecordDetails},
     HTTP_CLIENT,
@@ -37,6 +38,16 @@ const TUMBLR_SECRET: &str = "tumblr-secret";
 const ACCESS_TOKEN: &str = "access-token";
 const REFRESH_TOKEN: &str = "refresh-token";
 
+/// Pages on `www.tumblr.com` which are not blogs.
+const TUMBLR_PAGES: &[&str] = &[
+    "dashboard",
+    "explore",
+    "search",
+    "tagged",
+    "settings",
+    "likes",
+];
+
 lazy_static::lazy_static! {
     static ref TUMBLR_HOST: Regex = Regex::new(r#"^[^\.]+\.tumblr\.com$"#).unwrap();
 }
@@ -83,6 +94,8 @@ struct NpfPost {
     blog: Blog,
     post_url: String,
     summary: String,
+    #[serde(default)]
+    tags: Vec<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -106,6 +119,28 @@ struct Media {
     height: u32,
 }
 
+/// Builds the details shared by every image of a post, both from the api and from blog exports.
+pub(crate) fn post_details(
+    blog: String,
+    blog_url: Option<Url>,
+    post_url: Option<Url>,
+    summary: String,
+    caption: String,
+    tags: Vec<String>,
+) -> RecordDetails {
+    RecordDetails {
+        title: Some(summary),
+        url: post_url,
+        authors: vec![AuthorCredit {
+            url: blog_url,
+            ..AuthorCredit::new(AuthorQuery::Name(blog))
+        }],
+        caption: Some(caption),
+        tags: Vec::new(),
+        native_tags: tags,
+    }
+}
+
 #[async_trait]
 impl Service for Tumblr {
     fn host_matches(&self, host: &str) -> bool {
@@ -120,6 +155,37 @@ impl Service for Tumblr {
         &[TUMBLR_CONSUMER, TUMBLR_SECRET]
     }
 
+    fn canonicalize(&self, url: &Url) -> Option<Url> {
+        let host = url.host_str()?;
+
+        if !TUMBLR_HOST.is_match(host) && host != "tumblr.com" {
+            return None;
+        }
+
+        let segments = path_segments(url);
+
+        let canonical = match host {
+            "www.tumblr.com" | "tumblr.com" => match segments.as_slice() {
+                ["blog", "view", blog, id, ..] | [blog, id, ..]
+                    if id.chars().all(|c| c.is_ascii_digit()) =>
+                {
+                    format!("https://{}.tumblr.com/post/{id}", blog.to_lowercase())
+                }
+                [blog] if !TUMBLR_PAGES.contains(blog) => {
+                    format!("https://{}.tumblr.com/", blog.to_lowercase())
+                }
+                _ => return None,
+            },
+            _ => match segments.as_slice() {
+                ["post", id, ..] => format!("https://{host}/post/{id}"),
+                [] => format!("https://{host}/"),
+                _ => return None,
+            },
+        };
+
+        Url::parse(&canonical).ok()
+    }
+
     async fn authenticate(
         &self,
         secrets: &HashMap<String, String>,
@@ -262,14 +328,14 @@ impl Service for Tumblr {
             .collect::<Vec<_>>()
             .join("\n");
 
-        let details = RecordDetails {
-            title: Some(post.response.summary),
-            url: post.response.post_url.parse().ok(),
-            author: Some(AuthorQuery::Name(post.response.blog.name)),
-            author_url: post.response.blog.url.parse().ok(),
-            caption: Some(caption),
-            tags: Vec::new(),
-        };
+        let details = post_details(
+            post.response.blog.name,
+            post.response.blog.url.parse().ok(),
+            post.response.post_url.parse().ok(),
+            post.response.summary,
+            caption,
+            post.response.tags,
+        );
 
         for media in post.response.content.into_iter().filter_map(|content| {
             if let Content::Image { media } = content {
@@ -6,21 +6,50 @@ use std::{
 };
 
 use async_trait::async_trait;
+use lazy_static::lazy_static;
+use regex::Regex;
 use reqwest::header::USER_AGENT;
 use serde::Deserialize;
 use url::Url;
 use uuid::Uuid;
 
 use crate::{
-    author::AuthorQuery,
+    author::{AuthorCredit, AuthorQuery},
+    canonical::path_segments,
     record::{Record, RecordDetails},
     ServiceError,
 };
 
 use super::Service;
 
+lazy_static! {
+    static ref HASHTAG_REGEX: Regex = Regex::new(r#"(?:^|[^\w&])[#＃](\w+)"#).unwrap();
+}
+
+/// Hosts which serve twitter posts, including the embed fixing proxies.
+const TWITTER_HOSTS: &[&str] = &[
+    "twitter.com",
+    "www.twitter.com",
+    "mobile.twitter.com",
+    "x.com",
+    "www.x.com",
+    "mobile.x.com",
+    "fxtwitter.com",
+    "vxtwitter.com",
+    "fixupx.com",
+    "fixvx.com",
+];
+
 pub struct Twitter;
 
+/// Extracts the hashtags from the text of a tweet.
+fn hashtags(text: &str) -> Vec<String> {
+    HASHTAG_REGEX
+        .captures_iter(text)
+        .map(|captures| captures[1].to_owned())
+        .collect()
+}
+
 #[async_trait]
 impl Service for Twitter {
     fn host_matches(&self, host: &str) -> bool {
@@ -32,6 +61,24 @@ impl Service for Twitter {
     fn secrets(&self) -> &[&str] {
         &[]
     }
+    fn canonicalize(&self, url: &Url) -> Option<Url> {
+        if !TWITTER_HOSTS.contains(&url.host_str()?) {
+            return None;
+        }
+
+        let path = match path_segments(url).as_slice() {
+            // The author is left out as the id alone identifies a tweet, and tweets are also
+            // linked without it such as from the likes of an archive.
+            [_, "status", id, ..] | ["i", "web", "status", id, ..] => format!("/i/status/{id}"),
+            [user] => format!("/{}", user.to_lowercase()),
+            _ => url.path().to_owned(),
+        };
+
+        let mut url = Url::parse(&format!("https://x.com{path}")).ok()?;
+        url.set_query(None);
+
+        Some(url)
+    }
     async fn authenticate(
         &self,
         _: &HashMap<String, String>,
@@ -66,9 +113,12 @@ impl Service for Twitter {
 
         let details = RecordDetails {
             title: None,
-            author: Some(AuthorQuery::Name(response.tweet.author.screen_name.clone())),
-            author_url: Some(response.tweet.author.url),
+            authors: vec![AuthorCredit {
+                url: Some(response.tweet.author.url),
+                ..AuthorCredit::new(AuthorQuery::Name(response.tweet.author.screen_name.clone()))
+            }],
             tags: Vec::new(),
+            native_tags: hashtags(&response.tweet.text),
             url: Some(response.tweet.url),
             caption: Some(response.tweet.text),
         };
@@ -126,3 +176,17 @@ struct TweetMedia {
 struct TweetPhoto {
     pub url: Url,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::hashtags;
+
+    #[test]
+    fn test_hashtags() {
+        assert_eq!(
+            hashtags("#splatoon3 fanart of marina! #OC,#art https://t.co/x#fragment &#38;"),
+            vec!["splatoon3", "OC", "art"]
+        );
+        assert!(hashtags("no tags here").is_empty());
+    }
+}
@@ -1,10 +1,13 @@
+pub mod archive;
 pub mod author;
+pub mod canonical;
 pub mod http;
 pub mod id;
 pub mod import;
 pub mod models;
 pub(crate) mod parse;
 pub mod record;
+pub mod rule;
 pub mod search;
 pub mod tag;
 pub mod utils;
@@ -34,6 +37,14 @@ lazy_static::lazy_static! {
 pub struct Config {
     pub database_path: PathBuf,
     pub data_path: PathBuf,
+    /// Hosts of Mastodon compatible instances, urls on other hosts are only imported from
+    /// Mastodon if the host answers its status api.
+    #[serde(default)]
+    pub mastodon_instances: Vec<String>,
+    /// Whether importing a Bsky post which quotes another also imports the files of the quoted
+    /// post.
+    #[serde(default)]
+    pub bsky_follow_quotes: bool,
 }
 
 impl Config {
@@ -61,6 +72,8 @@ impl Default for Config {
         Self {
             database_path: project_dirs.data_dir().join("database.db"),
             data_path: project_dirs.data_dir().join("works"),
+            mastodon_instances: Vec::new(),
+            bsky_follow_quotes: false,
         }
     }
 }
@@ -156,8 +169,23 @@ pub enum Error {
     Url(#[from] url::ParseError),
     #[error("{0}")]
     Generic(String),
-    #[error("{kind} '{identifier}' is ambiguous")]
-    Ambiguous { kind: ModelKind, identifier: String },
+    #[error("{kind} '{identifier}' is ambiguous between {}", .candidates.iter().map(i64::to_string).collect::<Vec<_>>().join(", "))]
+    Ambiguous {
+        kind: ModelKind,
+        identifier: String,
+        /// The ids of the models the identifier could refer to
+        candidates: Vec<i64>,
+    },
+    #[error("tag implication would form a cycle: {}", .path.join(" -> "))]
+    TagCycle { path: Vec<String> },
+    #[error("tag '{tag}' is categorized as {existing}, not {requested}")]
+    CategoryConflict {
+        tag: String,
+        existing: String,
+        requested: String,
+    },
+    #[error("{kind} '{identifier}' already exists")]
+    Exists { kind: ModelKind, identifier: String },
     #[error(transparent)]
     Search(#[from] ParseError),
     #[error(transparent)]
@@ -168,6 +196,10 @@ pub enum Error {
     NotFound { kind: ModelKind },
     #[error("could not deserialize secrets")]
     Secret(#[from] bincode::Error),
+    #[error("could not read archive: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("invalid json: {0}")]
+    Json(#[from] serde_json::Error),
     #[error("oauth2 error {0}")]
     Oauth2(Box<dyn std::error::Error + Send + Sync>),
 }
@@ -178,6 +210,12 @@ pub enum ServiceError {
     Bsky(Box<dyn std::error::Error + Send + Sync>),
     #[error("fixtweet returned invalid code: {0}")]
     Twitter(u32),
+    #[error("error communicating with pixiv: {0}")]
+    Pixiv(String),
+    #[error("error communicating with mastodon: {0}")]
+    Mastodon(String),
+    #[error("error communicating with deviantart: {0}")]
+    DeviantArt(String),
 }
 
 impl<E> From<atrium_api::xrpc::Error<E>> for ServiceError
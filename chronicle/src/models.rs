@@ -1,6 +1,7 @@
 use strum::Display;
 
 mod author;
+mod rule;
 mod tag;
 mod work;
 
@@ -9,8 +10,10 @@ pub enum ModelKind {
     Work,
     Author,
     Tag,
+    Rule,
 }
 
 pub use author::*;
+pub use rule::*;
 pub use tag::*;
 pub use work::*;
@@ -0,0 +1,9 @@
+use crate::id;
+
+#[derive(sqlx::FromRow)]
+pub struct Rule {
+    pub rule_id: RuleId,
+    pub query: String,
+}
+
+id!(Rule);
@@ -1,18 +1,26 @@
 use std::fmt::Display;
 
+use serde::Serialize;
+
 use crate::id;
 
 use super::work::WorkId;
 
-#[derive(sqlx::FromRow)]
+#[derive(sqlx::FromRow, Serialize, Clone)]
 pub struct Tag {
     pub name: String,
     pub discriminator: Option<String>,
     pub id: TagId,
+    pub category: Option<String>,
+    pub description: Option<String>,
 }
 
 impl Display for Tag {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(category) = &self.category {
+            write!(f, "{category}:")?;
+        }
+
         write!(f, "{}", self.name)?;
 
         if let Some(discriminator) = &self.discriminator {
@@ -29,10 +37,49 @@ pub struct WorkTag {
     pub work_id: WorkId,
 }
 
+#[derive(sqlx::FromRow)]
+pub struct TagAlias {
+    pub tag_id: TagId,
+    pub name: String,
+    pub discriminator: Option<String>,
+}
+
+impl Display for TagAlias {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+
+        if let Some(discriminator) = &self.discriminator {
+            write!(f, "#{discriminator}")?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(sqlx::FromRow)]
 pub struct MetaTag {
     pub tag: TagId,
     pub target: TagId,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, strum::Display)]
+#[sqlx(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum MappingAction {
+    /// Replace the native tag with `tag`
+    Map,
+    /// Drop the native tag
+    Ignore,
+    /// Apply the native tag as is
+    Pass,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct TagMapping {
+    pub service: String,
+    pub source: String,
+    pub action: MappingAction,
+    pub tag: Option<TagId>,
+}
+
 id!(Tag);
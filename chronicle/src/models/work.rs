@@ -8,10 +8,16 @@ pub struct Work {
     pub work_id: WorkId,
     pub size: u64,
     pub title: Option<String>,
-    pub author_id: Option<AuthorId>,
     pub caption: Option<String>,
     pub url: Option<String>,
     pub hash: i32,
 }
 
+#[derive(sqlx::FromRow)]
+pub struct WorkAuthor {
+    pub work_id: WorkId,
+    pub author_id: AuthorId,
+    pub role: String,
+}
+
 id!(Work);
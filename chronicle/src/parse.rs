@@ -1,10 +1,10 @@
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    character::complete::{alphanumeric1, one_of, space1},
+    bytes::complete::{is_not, tag},
+    character::complete::{alphanumeric1, char},
     combinator::recognize,
     error::VerboseError,
-    multi::{many0, many1},
+    multi::many0,
     sequence::{delimited, pair},
     IResult,
 };
@@ -33,21 +33,35 @@ pub fn identifier(input: &str) -> ParseResult<&str> {
     ))(input)
 }
 
-pub fn quote(input: &str) -> ParseResult<&str> {
-    alt((tag("'"), tag("\"")))(input)
+/// Parses a string quoted with either `'` or `"`, which can hold any character besides its quote.
+fn quoted(input: &str) -> ParseResult<'_, &str> {
+    alt((
+        delimited(char('\''), is_not("'"), char('\'')),
+        delimited(char('"'), is_not("\""), char('"')),
+    ))(input)
 }
 
-pub fn special_chars(input: &str) -> ParseResult<&str> {
-    recognize(many1(one_of("$.+!*'(),;/?:@=&")))(input)
+pub fn string(input: &str) -> IResult<&str, &str, nom::error::VerboseError<&str>> {
+    alt((quoted, identifier))(input)
 }
 
-pub fn string(input: &str) -> IResult<&str, &str, nom::error::VerboseError<&str>> {
-    alt((
-        delimited(
-            quote,
-            recognize(many1(alt((space1, identifier, special_chars)))),
-            quote,
-        ),
-        identifier,
-    ))(input)
+#[cfg(test)]
+mod tests {
+    use super::string;
+
+    #[test]
+    fn test_string() {
+        for (input, expected) in [
+            ("splatoon", "splatoon"),
+            (r#""Ace Attorney""#, "Ace Attorney"),
+            (r##""#splatoon""##, "#splatoon"),
+            (r#""it's [art]!""#, "it's [art]!"),
+            (r#"'say "hi"'"#, r#"say "hi""#),
+        ] {
+            assert_eq!(string(input), Ok(("", expected)), "{input}");
+        }
+
+        assert!(string(r#""unterminated"#).is_err());
+        assert!(string(r#""""#).is_err());
+    }
 }
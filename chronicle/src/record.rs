@@ -2,7 +2,7 @@ use std::{fs, io, path::PathBuf};
 
 use bytemuck::cast;
 
-use crate::{author::AuthorQuery, tag::DiscriminatedTag, Chronicle};
+use crate::{author::AuthorCredit, tag::DiscriminatedTag, Chronicle};
 
 pub struct Record {
     pub path: PathBuf,
@@ -34,10 +34,12 @@ impl Record {
 pub struct RecordDetails {
     pub title: Option<String>,
     pub url: Option<url::Url>,
-    pub author: Option<AuthorQuery>,
-    pub author_url: Option<url::Url>,
+    pub authors: Vec<AuthorCredit>,
     pub caption: Option<String>,
     pub tags: Vec<DiscriminatedTag>,
+    /// Tags as written on the service the record was imported from, these are translated into
+    /// `tags` through the service's tag mappings
+    pub native_tags: Vec<String>,
 }
 
 impl RecordDetails {
@@ -48,15 +50,13 @@ impl RecordDetails {
         if let Some(url) = other.url {
             self.url = Some(url);
         }
-        if let Some(author) = other.author {
-            self.author = Some(author);
+        if !other.authors.is_empty() {
+            self.authors = other.authors;
         }
         if let Some(caption) = other.caption {
             self.caption = Some(caption);
         }
-        if let Some(author_url) = other.author_url {
-            self.author_url = Some(author_url);
-        }
         self.tags.extend(other.tags);
+        self.native_tags.extend(other.native_tags);
     }
 }
@@ -0,0 +1,311 @@
+use std::str::FromStr;
+
+use sqlx::{Sqlite, Transaction};
+
+use crate::{
+    models::{Rule, RuleId, Tag, Work},
+    search::Query,
+    tag::DiscriminatedTag,
+};
+
+impl Rule {
+    /// Creates a rule which applies `tags` to every work matching `query`.
+    ///
+    /// The query is stored as written, since parsed queries do not round trip.
+    pub async fn create(
+        tx: &mut Transaction<'_, Sqlite>,
+        query: &str,
+        tags: &[DiscriminatedTag],
+    ) -> Result<Rule, crate::Error> {
+        Query::from_str(query)?;
+
+        let rule: Rule = sqlx::query_as("INSERT INTO rules(query) VALUES (?) RETURNING *;")
+            .bind(query)
+            .fetch_one(&mut **tx)
+            .await?;
+
+        for tag in tags {
+            let tag = Tag::get_or_create(tx, tag).await?;
+
+            sqlx::query("INSERT OR IGNORE INTO rule_tags(rule_id, tag) VALUES (?, ?);")
+                .bind(rule.rule_id)
+                .bind(tag.id)
+                .execute(&mut **tx)
+                .await?;
+        }
+
+        Ok(rule)
+    }
+
+    pub async fn get_all(tx: &mut Transaction<'_, Sqlite>) -> Result<Vec<Rule>, crate::Error> {
+        Ok(sqlx::query_as("SELECT * FROM rules ORDER BY rule_id;")
+            .fetch_all(&mut **tx)
+            .await?)
+    }
+
+    /// Removes the rule with the given id, returning whether it existed.
+    pub async fn remove(
+        tx: &mut Transaction<'_, Sqlite>,
+        rule_id: RuleId,
+    ) -> Result<bool, crate::Error> {
+        Ok(sqlx::query("DELETE FROM rules WHERE rule_id = ?;")
+            .bind(rule_id)
+            .execute(&mut **tx)
+            .await?
+            .rows_affected()
+            > 0)
+    }
+
+    pub async fn tags(&self, tx: &mut Transaction<'_, Sqlite>) -> Result<Vec<Tag>, crate::Error> {
+        Ok(sqlx::query_as(
+            "SELECT tags.* FROM tags JOIN rule_tags ON tags.id = rule_tags.tag WHERE rule_tags.rule_id = ?;",
+        )
+        .bind(self.rule_id)
+        .fetch_all(&mut **tx)
+        .await?)
+    }
+
+    pub fn parse_query(&self) -> Result<Query, crate::Error> {
+        Ok(Query::from_str(&self.query)?)
+    }
+
+    /// Applies this rule to every matching work, returning the number of new connections.
+    pub async fn run(&self, tx: &mut Transaction<'_, Sqlite>) -> Result<u64, crate::Error> {
+        let query = self.parse_query()?;
+        let tags = self.tags(tx).await?;
+
+        let mut total = 0;
+
+        for work in Work::search(tx, &query).await? {
+            for tag in &tags {
+                if work.tag(tx, tag).await? {
+                    total += 1;
+                }
+            }
+        }
+
+        Ok(total)
+    }
+
+    /// Applies every rule in order, returning the number of new connections.
+    pub async fn run_all(tx: &mut Transaction<'_, Sqlite>) -> Result<u64, crate::Error> {
+        let mut total = 0;
+
+        for rule in Self::get_all(tx).await? {
+            total += rule.run(tx).await?;
+        }
+
+        Ok(total)
+    }
+
+    /// Applies every rule matching `work` to it, returning the number of new connections.
+    pub async fn apply_to_work(
+        tx: &mut Transaction<'_, Sqlite>,
+        work: &Work,
+    ) -> Result<u64, crate::Error> {
+        let mut total = 0;
+
+        for rule in Self::get_all(tx).await? {
+            if !work.matches(tx, &rule.parse_query()?).await? {
+                continue;
+            }
+
+            for tag in rule.tags(tx).await? {
+                if work.tag(tx, &tag).await? {
+                    total += 1;
+                }
+            }
+        }
+
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        record::RecordDetails,
+        utils::{test_pool, test_work},
+    };
+
+    use super::*;
+
+    fn tags(tags: &[&str]) -> Vec<DiscriminatedTag> {
+        tags.iter()
+            .map(|tag| DiscriminatedTag::from_str(tag).unwrap())
+            .collect()
+    }
+
+    async fn work(tx: &mut Transaction<'_, Sqlite>, path: &str, tags: &[&str]) -> Work {
+        let details = RecordDetails {
+            tags: self::tags(tags),
+            ..Default::default()
+        };
+
+        test_work(tx, path, details).await
+    }
+
+    async fn tag_names(tx: &mut Transaction<'_, Sqlite>, work: &Work) -> Vec<String> {
+        let mut names: Vec<String> = work
+            .tags(tx)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|tag| tag.name)
+            .collect();
+
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn test_create_invalid_query() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        assert!(Rule::create(&mut tx, "(splatoon", &tags(&["game"]))
+            .await
+            .is_err());
+        assert!(Rule::get_all(&mut tx).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let matching = work(&mut tx, "matching", &["splatoon"]).await;
+        let other = work(&mut tx, "other", &["pokemon"]).await;
+
+        let rule = Rule::create(&mut tx, "splatoon", &tags(&["game", "nintendo"]))
+            .await
+            .unwrap();
+
+        assert_eq!(rule.run(&mut tx).await.unwrap(), 2);
+        assert_eq!(
+            tag_names(&mut tx, &matching).await,
+            ["game", "nintendo", "splatoon"]
+        );
+        assert_eq!(tag_names(&mut tx, &other).await, ["pokemon"]);
+
+        assert_eq!(rule.run(&mut tx).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_run_own_trigger() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let work = work(&mut tx, "work", &["splatoon"]).await;
+
+        let rule = Rule::create(&mut tx, "splatoon", &tags(&["splatoon", "game"]))
+            .await
+            .unwrap();
+
+        assert_eq!(rule.run(&mut tx).await.unwrap(), 1);
+        assert_eq!(tag_names(&mut tx, &work).await, ["game", "splatoon"]);
+        assert_eq!(rule.run(&mut tx).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_run_all_in_order() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let work = work(&mut tx, "work", &["splatoon"]).await;
+
+        Rule::create(&mut tx, "splatoon", &tags(&["game"]))
+            .await
+            .unwrap();
+        Rule::create(&mut tx, "game", &tags(&["video_game"]))
+            .await
+            .unwrap();
+
+        assert_eq!(Rule::run_all(&mut tx).await.unwrap(), 2);
+        assert_eq!(
+            tag_names(&mut tx, &work).await,
+            ["game", "splatoon", "video_game"]
+        );
+        assert_eq!(Rule::run_all(&mut tx).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_apply_to_work() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        Rule::create(&mut tx, "pokemon", &tags(&["anime"]))
+            .await
+            .unwrap();
+
+        // Works are created with the rules applied already.
+        let work = work(&mut tx, "work", &["splatoon"]).await;
+
+        assert!(work
+            .matches(&mut tx, &Query::from_str("splatoon").unwrap())
+            .await
+            .unwrap());
+        assert!(!work
+            .matches(&mut tx, &Query::from_str("pokemon").unwrap())
+            .await
+            .unwrap());
+
+        Rule::create(&mut tx, "splatoon", &tags(&["splatoon", "game"]))
+            .await
+            .unwrap();
+
+        assert_eq!(Rule::apply_to_work(&mut tx, &work).await.unwrap(), 1);
+        assert_eq!(tag_names(&mut tx, &work).await, ["game", "splatoon"]);
+        assert_eq!(Rule::apply_to_work(&mut tx, &work).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_caption_rule() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let mut details = RecordDetails {
+            caption: Some(String::from("new art! #splatoon #marina")),
+            ..Default::default()
+        };
+        let captioned = test_work(&mut tx, "captioned", details.clone()).await;
+
+        details.caption = Some(String::from("splatoon without the hashtag"));
+        let other = test_work(&mut tx, "other", details).await;
+
+        let rule = Rule::create(&mut tx, r##"caption:"#splatoon""##, &tags(&["splatoon"]))
+            .await
+            .unwrap();
+
+        assert_eq!(rule.run(&mut tx).await.unwrap(), 1);
+        assert_eq!(tag_names(&mut tx, &captioned).await, ["splatoon"]);
+        assert!(tag_names(&mut tx, &other).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prune_keeps_rule_tags() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let rule = Rule::create(&mut tx, "splatoon", &tags(&["game"]))
+            .await
+            .unwrap();
+        let unused = Tag::create(&mut tx, "unused", None).await.unwrap();
+
+        let pruned = Tag::prune(&mut tx).await.unwrap();
+
+        assert_eq!(
+            pruned.iter().map(|tag| tag.id).collect::<Vec<_>>(),
+            vec![unused.id]
+        );
+        assert_eq!(
+            rule.tags(&mut tx)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|tag| tag.name)
+                .collect::<Vec<_>>(),
+            ["game"]
+        );
+    }
+}
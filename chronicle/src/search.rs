@@ -9,29 +9,88 @@ use std::{
 use builder::SearchQueryBuilder;
 use sqlx::{Execute, Sqlite, Transaction};
 
-use crate::{models::Work, parse::ParseError, tag::DiscriminatedTag, utils::hash_t, Chronicle};
+use crate::{
+    canonical::canonicalize,
+    models::{Work, WorkId},
+    parse::ParseError,
+    tag::DiscriminatedTag,
+    utils::hash_t,
+    Chronicle,
+};
 
 pub mod builder;
 pub(crate) mod parse;
 
+/// The forms accepted by the `author:` term.
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+pub enum AuthorTerm {
+    /// Works without any author, written `none`
+    None,
+    Id(i64),
+    Url(String),
+    /// Any of the author's names
+    Name(String),
+    /// Names starting with the text, written `text*`
+    Prefix(String),
+    /// Names containing the characters of the text in order, written `~text`
+    Fuzzy(String),
+}
+
+impl From<&str> for AuthorTerm {
+    /// Interprets a value the same way as [`AuthorQuery`](crate::author::AuthorQuery), along with
+    /// the `none`, `~fuzzy` and `prefix*` forms.
+    fn from(value: &str) -> Self {
+        if value == "none" {
+            Self::None
+        } else if let Some(fuzzy) = value.strip_prefix('~') {
+            Self::Fuzzy(fuzzy.to_owned())
+        } else if let Some(prefix) = value.strip_suffix('*') {
+            Self::Prefix(prefix.to_owned())
+        } else if let Ok(id) = i64::from_str(value) {
+            Self::Id(id)
+        } else if url::Url::parse(value).is_ok() {
+            Self::Url(value.to_owned())
+        } else {
+            Self::Name(value.to_owned())
+        }
+    }
+}
+
+impl Display for AuthorTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthorTerm::None => write!(f, "none"),
+            AuthorTerm::Id(id) => write!(f, "{id}"),
+            AuthorTerm::Url(url) => write!(f, r#""{url}""#),
+            AuthorTerm::Name(name) => write!(f, r#""{name}""#),
+            AuthorTerm::Prefix(prefix) => write!(f, r#""{prefix}"*"#),
+            AuthorTerm::Fuzzy(text) => write!(f, r#"~"{text}""#),
+        }
+    }
+}
+
 // TODO: Add Id query term
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum QueryTerm {
+    /// Written `category:name#discriminator`, categories named like a term kind such as `artist`
+    /// must be written `tag:artist:name`
     Tag(DiscriminatedTag),
     Title(String),
-    Author(String),
+    Author(AuthorTerm),
     Caption(String),
     Url(String),
+    Category(String),
 }
 
 impl Display for QueryTerm {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            QueryTerm::Tag(tag) => write!(f, r#"tag:"{tag}""#),
+            QueryTerm::Tag(tag) => write!(f, "tag:{tag}"),
             QueryTerm::Title(text) => write!(f, r#"title:"{text}""#),
-            QueryTerm::Author(text) => write!(f, r#"author:"{text}""#),
+            QueryTerm::Author(term) => write!(f, "author:{term}"),
             QueryTerm::Caption(text) => write!(f, r#"caption:"{text}""#),
             QueryTerm::Url(text) => write!(f, r#"url:"{text}""#),
+            QueryTerm::Category(text) => write!(f, r#"cat:"{text}""#),
         }
     }
 }
@@ -46,9 +105,10 @@ impl Hash for QueryTerm {
         match self {
             QueryTerm::Tag(text) => hash_and(state, 1, text),
             QueryTerm::Title(text) => hash_and(state, 2, text),
-            QueryTerm::Author(text) => hash_and(state, 3, text),
+            QueryTerm::Author(term) => hash_and(state, 3, term),
             QueryTerm::Caption(text) => hash_and(state, 4, text),
             QueryTerm::Url(text) => hash_and(state, 5, text),
+            QueryTerm::Category(text) => hash_and(state, 6, text),
         }
     }
 }
@@ -262,6 +322,37 @@ impl Work {
             .await?)
     }
 
+    pub async fn get_by_id(
+        tx: &mut Transaction<'_, Sqlite>,
+        id: WorkId,
+    ) -> Result<Option<Work>, crate::Error> {
+        Ok(sqlx::query_as("SELECT * FROM works WHERE work_id = ?;")
+            .bind(id)
+            .fetch_optional(&mut **tx)
+            .await?)
+    }
+
+    pub async fn get_by_hash(
+        tx: &mut Transaction<'_, Sqlite>,
+        hash: i32,
+    ) -> Result<Option<Work>, crate::Error> {
+        Ok(sqlx::query_as("SELECT * FROM works WHERE hash = ?;")
+            .bind(hash)
+            .fetch_optional(&mut **tx)
+            .await?)
+    }
+
+    /// Returns every work imported from `url`, which is canonicalized first.
+    pub async fn get_by_url(
+        tx: &mut Transaction<'_, Sqlite>,
+        url: &url::Url,
+    ) -> Result<Vec<Work>, crate::Error> {
+        Ok(sqlx::query_as("SELECT * FROM works WHERE url = ?;")
+            .bind(canonicalize(url).to_string())
+            .fetch_all(&mut **tx)
+            .await?)
+    }
+
     pub async fn search(
         tx: &mut Transaction<'_, Sqlite>,
         query: &Query,
@@ -281,6 +372,33 @@ impl Work {
         Ok(built.fetch_all(&mut **tx).await?)
     }
 
+    /// Returns whether this work is matched by `query`.
+    pub async fn matches(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        query: &Query,
+    ) -> Result<bool, crate::Error> {
+        let mut builder = SearchQueryBuilder::new();
+
+        let table = builder.push_query_table(query);
+
+        builder.query_builder.push(format_args!(
+            "SELECT EXISTS(SELECT 1 FROM {table} WHERE work_id = "
+        ));
+        builder.query_builder.push_bind(self.work_id);
+        builder.query_builder.push(");\n");
+
+        builder.drop_tables();
+
+        let built = builder.query_builder.build_query_as::<(bool,)>();
+
+        Ok(built
+            .fetch_all(&mut **tx)
+            .await?
+            .first()
+            .is_some_and(|(matches,)| *matches))
+    }
+
     pub async fn search_by_str(
         tx: &mut Transaction<'_, Sqlite>,
         search_query: &str,
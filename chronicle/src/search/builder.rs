@@ -1,31 +1,80 @@
 use sqlx::{QueryBuilder, Sqlite};
 
-use super::{Query, QueryTerm};
+use crate::canonical::canonicalize_str;
+
+use super::{AuthorTerm, Query, QueryTerm};
+
+/// Escapes the wildcards of a `LIKE` pattern with a backslash.
+pub(crate) fn escape_like(text: &str) -> String {
+    text.replace('\\', r"\\")
+        .replace('%', r"\%")
+        .replace('_', r"\_")
+}
 
 impl QueryTerm {
     fn push_select<'args>(&'args self, b: &mut QueryBuilder<'args, Sqlite>) {
         match self {
             QueryTerm::Tag(tag) => {
+                b.push("SELECT work_id FROM works WHERE work_id IN (SELECT work_tags.work_id FROM work_tags JOIN tag_closure ON work_tags.tag = tag_closure.descendant WHERE tag_closure.ancestor IN (SELECT id FROM tags WHERE name = ")
+                    .push_bind(&tag.name);
+
+                if let Some(discriminator) = &tag.discriminator {
+                    b.push(" AND discriminator = ").push_bind(discriminator);
+                }
+
+                if let Some(category) = &tag.category {
+                    b.push(" AND category = ").push_bind(category);
+                }
+
+                b.push(" UNION SELECT tag_id FROM tag_aliases JOIN tags ON tags.id = tag_aliases.tag_id WHERE tag_aliases.name = ")
+                    .push_bind(&tag.name);
+
                 if let Some(discriminator) = &tag.discriminator {
-                    b.push("WITH RECURSIVE implied(tag_id) AS (SELECT id FROM tags WHERE name = ")
-                    .push_bind(&tag.name)
-                    .push(" AND discriminator = ")
-                    .push_bind(discriminator)
-                    .push(" UNION SELECT target FROM meta_tags JOIN implied ON meta_tags.tag = implied.tag_id) SELECT work_id FROM works WHERE work_id IN (SELECT work_id FROM work_tags JOIN implied ON work_tags.tag = implied.tag_id)");
-                } else {
-                    b.push("WITH RECURSIVE implied(tag_id) AS (SELECT id FROM tags WHERE name = ")
-                    .push_bind(&tag.name)
-                    .push(" UNION SELECT target FROM meta_tags JOIN implied ON meta_tags.tag = implied.tag_id) SELECT work_id FROM works WHERE work_id IN (SELECT work_id FROM work_tags JOIN implied ON work_tags.tag = implied.tag_id)");
+                    b.push(" AND tag_aliases.discriminator = ")
+                        .push_bind(discriminator);
                 }
+
+                if let Some(category) = &tag.category {
+                    b.push(" AND tags.category = ").push_bind(category);
+                }
+
+                b.push("))");
             }
             QueryTerm::Title(title) => {
                 b.push("SELECT work_id FROM works WHERE title LIKE '%' || ")
                     .push_bind(title)
                     .push(" || '%'");
             }
-            QueryTerm::Author(author) => {
-                b.push("SELECT work_id FROM works JOIN authors ON works.author_id = authors.author_id JOIN author_names ON authors.author_id = author_names.author_id WHERE author_names.name = ")
-                    .push_bind(author);
+            QueryTerm::Author(AuthorTerm::None) => {
+                b.push("SELECT work_id FROM works WHERE work_id NOT IN (SELECT work_id FROM work_authors)");
+            }
+            QueryTerm::Author(AuthorTerm::Id(id)) => {
+                b.push("SELECT work_id FROM work_authors WHERE author_id = ")
+                    .push_bind(id);
+            }
+            QueryTerm::Author(AuthorTerm::Url(url)) => {
+                b.push("SELECT work_id FROM work_authors JOIN author_urls ON work_authors.author_id = author_urls.author_id WHERE author_urls.url = ")
+                    .push_bind(canonicalize_str(url));
+            }
+            QueryTerm::Author(AuthorTerm::Name(name)) => {
+                b.push("SELECT work_id FROM work_authors JOIN author_names ON work_authors.author_id = author_names.author_id WHERE author_names.name = ")
+                    .push_bind(name);
+            }
+            QueryTerm::Author(AuthorTerm::Prefix(prefix)) => {
+                b.push("SELECT work_id FROM work_authors JOIN author_names ON work_authors.author_id = author_names.author_id WHERE author_names.name LIKE ")
+                    .push_bind(format!("{}%", escape_like(prefix)))
+                    .push(r" ESCAPE '\'");
+            }
+            QueryTerm::Author(AuthorTerm::Fuzzy(text)) => {
+                let pattern = text.chars().fold(String::from("%"), |mut pattern, c| {
+                    pattern.push_str(&escape_like(&c.to_string()));
+                    pattern.push('%');
+                    pattern
+                });
+
+                b.push("SELECT work_id FROM work_authors JOIN author_names ON work_authors.author_id = author_names.author_id WHERE author_names.name LIKE ")
+                    .push_bind(pattern)
+                    .push(r" ESCAPE '\'");
             }
             QueryTerm::Caption(caption) => {
                 b.push("SELECT work_id FROM works WHERE caption LIKE '%' || ")
@@ -34,7 +83,12 @@ impl QueryTerm {
             }
             QueryTerm::Url(url) => {
                 b.push("SELECT work_id FROM works WHERE url = ")
-                    .push_bind(url);
+                    .push_bind(canonicalize_str(url));
+            }
+            QueryTerm::Category(category) => {
+                b.push("SELECT work_id FROM works WHERE work_id IN (SELECT work_tags.work_id FROM work_tags JOIN tag_closure ON work_tags.tag = tag_closure.descendant WHERE tag_closure.ancestor IN (SELECT id FROM tags WHERE category = ")
+                    .push_bind(category)
+                    .push("))");
             }
         }
     }
@@ -10,7 +10,7 @@ use nom::{
 
 use crate::{parse::string, tag::parse::discriminated_tag};
 
-use super::{Query, QueryTerm};
+use super::{AuthorTerm, Query, QueryTerm};
 
 fn term_kind(input: &str) -> IResult<&str, &str, nom::error::VerboseError<&str>> {
     alt((
@@ -19,6 +19,8 @@ fn term_kind(input: &str) -> IResult<&str, &str, nom::error::VerboseError<&str>>
         tag("artist"),
         tag("author"),
         tag("caption"),
+        tag("category"),
+        tag("cat"),
         tag("url"),
         tag("t"),
         tag("a"),
@@ -33,13 +35,26 @@ fn tagged_term(input: &str) -> IResult<&str, QueryTerm, nom::error::VerboseError
     match kind {
         "tag" => map(discriminated_tag, |t| QueryTerm::Tag(t.into()))(i),
         "t" | "title" => map(string, |s| QueryTerm::Title(s.to_owned()))(i),
-        "a" | "artist" | "author" => map(string, |s| QueryTerm::Author(s.to_owned()))(i),
+        "a" | "artist" | "author" => map(author_term, QueryTerm::Author)(i),
         "c" | "caption" => map(string, |s| QueryTerm::Caption(s.to_owned()))(i),
         "u" | "url" => map(string, |s| QueryTerm::Url(s.to_owned()))(i),
+        "cat" | "category" => map(string, |s| QueryTerm::Category(s.to_owned()))(i),
         _ => return fail("invalid term tag"),
     }
 }
 
+fn author_term(input: &str) -> IResult<&str, AuthorTerm, nom::error::VerboseError<&str>> {
+    alt((
+        map(preceded(tag("~"), string), |s| {
+            AuthorTerm::Fuzzy(s.to_owned())
+        }),
+        map(terminated(string, tag("*")), |s| {
+            AuthorTerm::Prefix(s.to_owned())
+        }),
+        map(string, AuthorTerm::from),
+    ))(input)
+}
+
 fn term(input: &str) -> IResult<&str, QueryTerm, nom::error::VerboseError<&str>> {
     preceded(
         nom::combinator::not(alt((and_separator, or_separator))),
@@ -105,7 +120,10 @@ pub fn query(input: &str) -> IResult<&str, Query, nom::error::VerboseError<&str>
 mod tests {
     use nom::Parser;
 
-    use crate::search::{parse::term, QueryTerm};
+    use crate::{
+        search::{parse::term, AuthorTerm, QueryTerm},
+        tag::DiscriminatedTag,
+    };
 
     use super::term_kind;
 
@@ -119,7 +137,8 @@ mod tests {
     #[test]
     fn test_term_kind() {
         for kind in &[
-            "t", "title", "tag", "a", "artist", "author", "c", "caption", "u", "url",
+            "t", "title", "tag", "a", "artist", "author", "c", "caption", "cat", "category", "u",
+            "url",
         ] {
             assert_matches(term_kind, kind);
         }
@@ -140,4 +159,67 @@ mod tests {
             Ok(("", QueryTerm::Title(String::from("Ace Attorney"))))
         );
     }
+
+    #[test]
+    fn test_author_term() {
+        for (input, expected) in [
+            ("a:marina", AuthorTerm::Name(String::from("marina"))),
+            ("author:12", AuthorTerm::Id(12)),
+            (
+                r#"author:"https://x.com/marina""#,
+                AuthorTerm::Url(String::from("https://x.com/marina")),
+            ),
+            ("author:mar*", AuthorTerm::Prefix(String::from("mar"))),
+            ("author:~mrna", AuthorTerm::Fuzzy(String::from("mrna"))),
+            ("author:none", AuthorTerm::None),
+        ] {
+            let term = QueryTerm::Author(expected);
+
+            assert_eq!(super::term(input), Ok(("", term.clone())), "{input}");
+            assert_eq!(
+                super::term(&term.to_string()),
+                Ok(("", term.clone())),
+                "{term}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_category_term() {
+        assert_eq!(
+            term("cat:character"),
+            Ok(("", QueryTerm::Category(String::from("character"))))
+        );
+        assert_eq!(
+            term("character:marina"),
+            Ok((
+                "",
+                QueryTerm::Tag(DiscriminatedTag {
+                    category: Some(String::from("character")),
+                    name: String::from("marina"),
+                    discriminator: None,
+                })
+            ))
+        );
+        assert_eq!(term("cats"), Ok(("", QueryTerm::Tag("cats".into()))));
+    }
+
+    #[test]
+    fn test_term_kind_category() {
+        let artist = QueryTerm::Tag(DiscriminatedTag {
+            category: Some(String::from("artist")),
+            name: String::from("hazel"),
+            discriminator: None,
+        });
+
+        assert_eq!(term("tag:artist:hazel"), Ok(("", artist.clone())));
+        assert_eq!(term(&artist.to_string()), Ok(("", artist)));
+        assert_eq!(
+            term("artist:hazel"),
+            Ok((
+                "",
+                QueryTerm::Author(AuthorTerm::Name(String::from("hazel")))
+            ))
+        );
+    }
 }
@@ -1,18 +1,28 @@
+pub mod graph;
+pub mod mapping;
 pub mod parse;
 
-use std::{fmt::Display, ops::Deref, str::FromStr};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    iter::once,
+    ops::Deref,
+    str::FromStr,
+};
 
 use parse::{discriminated_tag, tag_expression, tag_part, ParsedTag};
+use serde::Serialize;
 use sqlx::{Acquire, Sqlite, Transaction};
 
 use crate::{
-    models::{ModelKind, Tag, TagId, Work},
+    models::{MetaTag, ModelKind, Tag, TagAlias, TagId, Work, WorkId},
     parse::{string, ParseError},
-    search::Query,
+    search::{builder::escape_like, Query},
 };
 
 #[derive(PartialEq, Eq, Debug, Clone, Hash)]
 pub struct DiscriminatedTag {
+    pub category: Option<String>,
     pub name: String,
     pub discriminator: Option<String>,
 }
@@ -20,17 +30,30 @@ pub struct DiscriminatedTag {
 impl<'s> From<ParsedTag<'s>> for DiscriminatedTag {
     fn from(
         ParsedTag {
+            category,
             name,
             discriminator,
         }: ParsedTag,
     ) -> Self {
         Self {
+            category: category.map(String::from),
             name: name.to_owned(),
             discriminator: discriminator.map(String::from),
         }
     }
 }
 
+#[cfg(test)]
+impl From<&str> for DiscriminatedTag {
+    fn from(name: &str) -> Self {
+        Self {
+            category: None,
+            name: name.to_owned(),
+            discriminator: None,
+        }
+    }
+}
+
 impl FromStr for DiscriminatedTag {
     type Err = ParseError;
 
@@ -68,8 +91,22 @@ impl FromStr for TagPart {
     }
 }
 
+impl From<&Tag> for DiscriminatedTag {
+    fn from(tag: &Tag) -> Self {
+        Self {
+            category: tag.category.clone(),
+            name: tag.name.clone(),
+            discriminator: tag.discriminator.clone(),
+        }
+    }
+}
+
 impl Display for DiscriminatedTag {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(category) = &self.category {
+            write!(f, "{category}:")?;
+        }
+
         write!(f, "{}", &self.name)?;
 
         if let Some(discriminator) = &self.discriminator {
@@ -115,6 +152,44 @@ impl FromStr for TagExpression {
     }
 }
 
+/// A link which a [`TagExpression`] would add, along with whether it already exists.
+pub struct PlannedLink<T> {
+    pub from: T,
+    pub to: Tag,
+    pub exists: bool,
+}
+
+/// The changes a [`TagExpression`] would make if executed.
+///
+/// Tags which would be created are given the ids they would receive.
+#[derive(Default)]
+pub struct TagPlan {
+    pub created_tags: Vec<Tag>,
+    pub work_links: Vec<PlannedLink<WorkId>>,
+    pub tag_links: Vec<PlannedLink<Tag>>,
+    /// The implication paths which would form a cycle, each starting and ending with the same tag.
+    pub cycles: Vec<Vec<Tag>>,
+    /// Existing tags which the expression names with a different category
+    pub category_conflicts: Vec<CategoryConflict>,
+    /// Existing undiscriminated tags which have to be discriminated before the expression can be
+    /// executed, see [`TagExpression::discriminator_conflicts`]
+    pub discriminator_conflicts: Vec<Tag>,
+}
+
+/// A tag named with a different category than the one it already has.
+pub struct CategoryConflict {
+    pub tag: Tag,
+    pub requested: String,
+}
+
+impl TagPlan {
+    /// The number of connections which would be added.
+    pub fn new_connections(&self) -> usize {
+        self.work_links.iter().filter(|link| !link.exists).count()
+            + self.tag_links.iter().filter(|link| !link.exists).count()
+    }
+}
+
 impl TagExpression {
     pub fn approximate_connections(&self) -> usize {
         self.hierarchy
@@ -123,6 +198,29 @@ impl TagExpression {
             .sum::<usize>()
     }
 
+    /// Returns the existing undiscriminated tags which share a name with a discriminated tag in
+    /// this expression.
+    pub async fn discriminator_conflicts(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+    ) -> Result<Vec<Tag>, crate::Error> {
+        let mut conflicts: Vec<Tag> = Vec::new();
+
+        for tag in self.hierarchy.iter().flatten() {
+            if tag.discriminator.is_none() {
+                continue;
+            }
+
+            if let Some(other) = Tag::try_get_unaliased(tx, &tag.name, None).await? {
+                if !conflicts.iter().any(|conflict| conflict.id == other.id) {
+                    conflicts.push(other);
+                }
+            }
+        }
+
+        Ok(conflicts)
+    }
+
     pub async fn create_missing_tags(
         &self,
         tx: &mut Transaction<'_, Sqlite>,
@@ -132,16 +230,12 @@ impl TagExpression {
         let mut tx = tx.begin().await?;
 
         for level in &self.hierarchy {
-            for DiscriminatedTag {
-                name,
-                discriminator,
-            } in level.iter()
-            {
-                if Tag::try_get_discriminated(&mut tx, name, discriminator.as_deref())
+            for tag in level.iter() {
+                if Tag::try_get_discriminated(&mut tx, &tag.name, tag.discriminator.as_deref())
                     .await?
                     .is_none()
                 {
-                    Tag::create(&mut tx, name, discriminator.as_deref()).await?;
+                    Tag::get_or_create(&mut tx, tag).await?;
                     created += 1;
                 }
             }
@@ -162,6 +256,7 @@ impl TagExpression {
             for DiscriminatedTag {
                 name,
                 discriminator,
+                ..
             } in level.iter()
             {
                 tags.push(
@@ -188,14 +283,8 @@ impl TagExpression {
             let tags = &self.hierarchy[0];
 
             for work in &works {
-                for DiscriminatedTag {
-                    name,
-                    discriminator,
-                } in tags
-                {
-                    let tag =
-                        Tag::get_discriminated_or_create(&mut tx, name, discriminator.as_deref())
-                            .await?;
+                for tag in tags {
+                    let tag = Tag::get_or_create(&mut tx, tag).await?;
 
                     if work.tag(&mut tx, &tag).await? {
                         total_connections += 1;
@@ -209,26 +298,11 @@ impl TagExpression {
                 let previous_tags = &window[0];
                 let next_tags = &window[1];
 
-                for DiscriminatedTag {
-                    name,
-                    discriminator,
-                } in previous_tags
-                {
-                    let tag =
-                        Tag::get_discriminated_or_create(&mut tx, name, discriminator.as_deref())
-                            .await?;
-
-                    for DiscriminatedTag {
-                        name,
-                        discriminator,
-                    } in next_tags
-                    {
-                        let next = Tag::get_discriminated_or_create(
-                            &mut tx,
-                            name,
-                            discriminator.as_deref(),
-                        )
-                        .await?;
+                for tag in previous_tags {
+                    let tag = Tag::get_or_create(&mut tx, tag).await?;
+
+                    for next in next_tags {
+                        let next = Tag::get_or_create(&mut tx, next).await?;
                         if tag.tag(&mut tx, &next).await? {
                             total_connections += 1;
                         }
@@ -241,9 +315,110 @@ impl TagExpression {
 
         Ok(total_connections)
     }
+
+    /// Determines what [`TagExpression::execute`] would do without changing the database.
+    ///
+    /// Unlike `execute`, links which would form a cycle and tags named with a conflicting category
+    /// are recorded in the plan rather than aborting it. Discriminator conflicts are recorded as
+    /// well, the rest of the plan is made as though they were discriminated automatically.
+    pub async fn plan(&self, tx: &mut Transaction<'_, Sqlite>) -> Result<TagPlan, crate::Error> {
+        let mut plan = TagPlan {
+            discriminator_conflicts: self.discriminator_conflicts(tx).await?,
+            ..Default::default()
+        };
+
+        let mut tx = tx.begin().await?;
+
+        for conflict in &plan.discriminator_conflicts {
+            conflict.clone().discriminate_automatically(&mut tx).await?;
+        }
+
+        let mut resolved = Vec::with_capacity(self.hierarchy.len());
+
+        for level in &self.hierarchy {
+            let mut tags = Vec::with_capacity(level.len());
+
+            for tag in level {
+                let existing =
+                    Tag::try_get_discriminated(&mut tx, &tag.name, tag.discriminator.as_deref())
+                        .await?;
+                let exists = existing.is_some();
+
+                let tag = match Tag::get_or_create(&mut tx, tag).await {
+                    Ok(tag) => tag,
+                    Err(crate::Error::CategoryConflict { requested, .. }) => {
+                        let existing = existing.expect("only existing tags have a category");
+
+                        plan.category_conflicts.push(CategoryConflict {
+                            tag: existing.clone(),
+                            requested,
+                        });
+
+                        existing
+                    }
+                    Err(err) => return Err(err),
+                };
+
+                if !exists && !plan.created_tags.iter().any(|created| created.id == tag.id) {
+                    plan.created_tags.push(tag.clone());
+                }
+
+                tags.push(tag);
+            }
+
+            resolved.push(tags);
+        }
+
+        if let (Some(query), Some(tags)) = (&self.query, resolved.first()) {
+            for work in Work::search(&mut tx, query).await? {
+                for tag in tags {
+                    let exists = !work.tag(&mut tx, tag).await?;
+
+                    plan.work_links.push(PlannedLink {
+                        from: work.work_id,
+                        to: tag.clone(),
+                        exists,
+                    });
+                }
+            }
+        }
+
+        for window in resolved.windows(2) {
+            for tag in &window[0] {
+                for next in &window[1] {
+                    match tag.tag(&mut tx, next).await {
+                        Ok(inserted) => plan.tag_links.push(PlannedLink {
+                            from: tag.clone(),
+                            to: next.clone(),
+                            exists: !inserted,
+                        }),
+                        Err(crate::Error::TagCycle { .. }) => {
+                            if let Some(cycle) = tag.cycle_path(&mut tx, next).await? {
+                                plan.cycles.push(cycle);
+                            }
+                        }
+                        Err(err) => return Err(err),
+                    }
+                }
+            }
+        }
+
+        tx.rollback().await?;
+
+        Ok(plan)
+    }
 }
 
 impl Work {
+    pub async fn tags(&self, tx: &mut Transaction<'_, Sqlite>) -> Result<Vec<Tag>, crate::Error> {
+        Ok(sqlx::query_as(
+            "SELECT tags.* FROM tags JOIN work_tags ON tags.id = work_tags.tag WHERE work_tags.work_id = ?;",
+        )
+        .bind(self.work_id)
+        .fetch_all(&mut **tx)
+        .await?)
+    }
+
     pub async fn tag(
         &self,
         tx: &mut Transaction<'_, Sqlite>,
@@ -267,11 +442,90 @@ pub struct DepthTag {
     pub tag: Tag,
 }
 
+/// Finds the shortest path from `from` to `to` following edges from tags to their targets.
+fn find_path(edges: &[MetaTag], from: TagId, to: TagId) -> Option<Vec<TagId>> {
+    let mut previous: HashMap<TagId, TagId> = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+
+    while let Some(current) = queue.pop_front() {
+        if current == to {
+            let mut path = vec![current];
+
+            while let Some(&prev) = path.last().and_then(|last| previous.get(last)) {
+                path.push(prev);
+            }
+
+            path.reverse();
+
+            return Some(path);
+        }
+
+        for edge in edges.iter().filter(|edge| edge.tag == current) {
+            if edge.target != from && !previous.contains_key(&edge.target) {
+                previous.insert(edge.target, current);
+                queue.push_back(edge.target);
+            }
+        }
+    }
+
+    None
+}
+
+#[derive(sqlx::FromRow, Serialize)]
+pub struct TagCount {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub tag: Tag,
+    /// The number of works tagged with this tag directly
+    pub direct_works: i64,
+    /// The number of works tagged with this tag or any tag it implies
+    pub implied_works: i64,
+    pub parents: i64,
+    pub children: i64,
+}
+
+const TAG_COUNT_SELECT: &str = r#"
+    SELECT
+        tags.*,
+        (SELECT COUNT(*) FROM work_tags WHERE work_tags.tag = tags.id) AS direct_works,
+        (SELECT COUNT(DISTINCT work_tags.work_id) FROM tag_closure JOIN work_tags ON work_tags.tag = tag_closure.descendant WHERE tag_closure.ancestor = tags.id) AS implied_works,
+        (SELECT COUNT(*) FROM meta_tags WHERE meta_tags.target = tags.id) AS parents,
+        (SELECT COUNT(*) FROM meta_tags WHERE meta_tags.tag = tags.id) AS children
+    FROM tags"#;
+
+/// Score given to a candidate tag for every tag it shares with the work.
+const SHARED_TAG_WEIGHT: f64 = 1.0;
+/// Score given to a candidate tag for appearing on a work by the same author.
+const AUTHOR_WEIGHT: f64 = 2.0;
+/// Score given to a candidate tag for appearing on a work from the same url.
+const SOURCE_WEIGHT: f64 = 4.0;
+
+#[derive(sqlx::FromRow)]
+pub struct TagSuggestion {
+    #[sqlx(flatten)]
+    pub tag: Tag,
+    pub score: f64,
+}
+
 impl Tag {
+    /// Gets a tag by name and discriminator, resolving aliases if no tag matches directly.
     pub async fn try_get_discriminated(
         tx: &mut Transaction<'_, Sqlite>,
         name: &str,
         discriminator: Option<&str>,
+    ) -> Result<Option<Tag>, crate::Error> {
+        if let Some(tag) = Self::try_get_unaliased(tx, name, discriminator).await? {
+            return Ok(Some(tag));
+        }
+
+        Self::try_get_by_alias(tx, name, discriminator).await
+    }
+
+    /// Gets a tag by name and discriminator without resolving aliases.
+    pub async fn try_get_unaliased(
+        tx: &mut Transaction<'_, Sqlite>,
+        name: &str,
+        discriminator: Option<&str>,
     ) -> Result<Option<Tag>, crate::Error> {
         if let Some(discriminator) = discriminator {
             Ok(
@@ -291,6 +545,79 @@ impl Tag {
         }
     }
 
+    /// Gets the tag which the given alias resolves to.
+    pub async fn try_get_by_alias(
+        tx: &mut Transaction<'_, Sqlite>,
+        name: &str,
+        discriminator: Option<&str>,
+    ) -> Result<Option<Tag>, crate::Error> {
+        if let Some(discriminator) = discriminator {
+            Ok(sqlx::query_as(
+                "SELECT tags.* FROM tags JOIN tag_aliases ON tags.id = tag_aliases.tag_id WHERE tag_aliases.name = ? AND tag_aliases.discriminator = ?;",
+            )
+            .bind(name)
+            .bind(discriminator)
+            .fetch_optional(&mut **tx)
+            .await?)
+        } else {
+            Ok(sqlx::query_as(
+                "SELECT tags.* FROM tags JOIN tag_aliases ON tags.id = tag_aliases.tag_id WHERE tag_aliases.name = ? AND tag_aliases.discriminator IS NULL;",
+            )
+            .bind(name)
+            .fetch_optional(&mut **tx)
+            .await?)
+        }
+    }
+
+    /// Adds an alias which resolves to this tag wherever tags are looked up.
+    pub async fn add_alias(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        alias: &DiscriminatedTag,
+    ) -> Result<TagAlias, crate::Error> {
+        if let Some(existing) =
+            Self::try_get_discriminated(tx, &alias.name, alias.discriminator.as_deref()).await?
+        {
+            if existing.id != self.id
+                || Self::try_get_unaliased(tx, &alias.name, alias.discriminator.as_deref())
+                    .await?
+                    .is_some()
+            {
+                return Err(crate::Error::Exists {
+                    kind: ModelKind::Tag,
+                    identifier: alias.to_string(),
+                });
+            }
+        }
+
+        Ok(sqlx::query_as(
+            r#"
+                INSERT OR IGNORE INTO tag_aliases (tag_id, name, discriminator) VALUES (?, ?, ?);
+                SELECT * FROM tag_aliases WHERE tag_id = ? AND name = ? AND discriminator IS ?;
+            "#,
+        )
+        .bind(self.id)
+        .bind(&alias.name)
+        .bind(&alias.discriminator)
+        .bind(self.id)
+        .bind(&alias.name)
+        .bind(&alias.discriminator)
+        .fetch_one(&mut **tx)
+        .await?)
+    }
+
+    pub async fn aliases(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+    ) -> Result<Vec<TagAlias>, crate::Error> {
+        Ok(
+            sqlx::query_as("SELECT * FROM tag_aliases WHERE tag_id = ?;")
+                .bind(self.id)
+                .fetch_all(&mut **tx)
+                .await?,
+        )
+    }
+
     pub async fn discriminate(
         &mut self,
         tx: &mut Transaction<'_, Sqlite>,
@@ -307,6 +634,165 @@ impl Tag {
         Ok(())
     }
 
+    /// Changes the discriminator of this tag.
+    ///
+    /// This fails if a tag with the new discriminator already exists, unless `merge` is set in
+    /// which case this tag is merged into it and it is returned.
+    pub async fn rediscriminate(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        discriminator: &str,
+        merge: bool,
+    ) -> Result<Tag, crate::Error> {
+        if let Some(existing) = Self::try_get_unaliased(tx, &self.name, Some(discriminator)).await?
+        {
+            if existing.id != self.id {
+                if !merge {
+                    return Err(crate::Error::Exists {
+                        kind: ModelKind::Tag,
+                        identifier: existing.to_string(),
+                    });
+                }
+
+                self.merge_into(tx, &existing).await?;
+            }
+
+            return Ok(existing);
+        }
+
+        sqlx::query("UPDATE tags SET discriminator = ? WHERE id = ?;")
+            .bind(discriminator)
+            .bind(self.id)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(Tag {
+            discriminator: Some(discriminator.to_owned()),
+            ..self.clone()
+        })
+    }
+
+    /// Removes the discriminator from this tag.
+    ///
+    /// This fails while other tags share its name, unless `merge` is set in which case they are
+    /// merged into this tag first.
+    pub async fn undiscriminate(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        merge: bool,
+    ) -> Result<Tag, crate::Error> {
+        let siblings: Vec<Tag> = sqlx::query_as("SELECT * FROM tags WHERE name = ? AND id != ?;")
+            .bind(&self.name)
+            .bind(self.id)
+            .fetch_all(&mut **tx)
+            .await?;
+
+        if !siblings.is_empty() && !merge {
+            return Err(crate::Error::Ambiguous {
+                kind: ModelKind::Tag,
+                identifier: self.name.clone(),
+                candidates: siblings.iter().map(|sibling| sibling.id.0).collect(),
+            });
+        }
+
+        for sibling in &siblings {
+            sibling.merge_into(tx, self).await?;
+        }
+
+        sqlx::query("UPDATE tags SET discriminator = NULL WHERE id = ?;")
+            .bind(self.id)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(Tag {
+            discriminator: None,
+            ..self.clone()
+        })
+    }
+
+    /// Discriminates this tag with the lowest number not already used as a discriminator for its
+    /// name.
+    pub async fn discriminate_automatically(
+        &mut self,
+        tx: &mut Transaction<'_, Sqlite>,
+    ) -> Result<(), crate::Error> {
+        let mut number = 1;
+
+        while Self::try_get_unaliased(tx, &self.name, Some(&number.to_string()))
+            .await?
+            .is_some()
+        {
+            number += 1;
+        }
+
+        self.discriminate(tx, &number.to_string()).await
+    }
+
+    /// Moves everything referring to this tag onto `target` and deletes this tag.
+    ///
+    /// The category and description of `target` are kept, falling back to this tag's. Nothing is
+    /// changed if the merged tag would imply itself.
+    pub async fn merge_into(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        target: &Tag,
+    ) -> Result<(), crate::Error> {
+        if self.id == target.id {
+            return Ok(());
+        }
+
+        let mut tx = tx.begin().await?;
+
+        sqlx::query(
+            "INSERT OR IGNORE INTO work_tags(tag, work_id) SELECT ?, work_id FROM work_tags WHERE tag = ?;",
+        )
+        .bind(target.id)
+        .bind(self.id)
+        .execute(&mut *tx)
+        .await?;
+
+        let edges: Vec<MetaTag> =
+            sqlx::query_as("DELETE FROM meta_tags WHERE tag = ?1 OR target = ?1 RETURNING *;")
+                .bind(self.id)
+                .fetch_all(&mut *tx)
+                .await?;
+
+        Self::rebuild_closure(&mut tx).await?;
+
+        for edge in edges {
+            if edge.tag == self.id && edge.target != target.id {
+                if let Some(child) = Self::get_by_id(&mut tx, edge.target).await? {
+                    child.tag(&mut tx, target).await?;
+                }
+            } else if edge.target == self.id && edge.tag != target.id {
+                if let Some(parent) = Self::get_by_id(&mut tx, edge.tag).await? {
+                    target.tag(&mut tx, &parent).await?;
+                }
+            }
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE tag_aliases SET tag_id = ?1 WHERE tag_id = ?2;
+            INSERT OR IGNORE INTO rule_tags(rule_id, tag) SELECT rule_id, ?1 FROM rule_tags WHERE tag = ?2;
+            UPDATE tag_mappings SET tag = ?1 WHERE tag = ?2;
+            UPDATE tags SET
+                category = COALESCE(category, (SELECT category FROM tags WHERE id = ?2)),
+                description = COALESCE(description, (SELECT description FROM tags WHERE id = ?2))
+            WHERE id = ?1;
+            DELETE FROM tags WHERE id = ?2;
+        "#,
+        )
+        .bind(target.id)
+        .bind(self.id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
     pub async fn get_discriminated(
         tx: &mut Transaction<'_, Sqlite>,
         name: &str,
@@ -323,10 +809,13 @@ impl Tag {
         tx: &mut Transaction<'_, Sqlite>,
         name: &str,
     ) -> Result<Vec<Tag>, crate::Error> {
-        Ok(sqlx::query_as("SELECT * FROM tags WHERE name = ?;")
-            .bind(name)
-            .fetch_all(&mut **tx)
-            .await?)
+        Ok(sqlx::query_as(
+            "SELECT * FROM tags WHERE name = ? UNION SELECT tags.* FROM tags JOIN tag_aliases ON tags.id = tag_aliases.tag_id WHERE tag_aliases.name = ?;",
+        )
+        .bind(name)
+        .bind(name)
+        .fetch_all(&mut **tx)
+        .await?)
     }
 
     pub async fn get_discriminated_or_create(
@@ -341,33 +830,355 @@ impl Tag {
         Ok(tag)
     }
 
-    pub async fn create(
+    /// Gets or creates the tag, assigning its category if one is given and the tag has none.
+    ///
+    /// Naming a tag with a different category than it already has fails with
+    /// [`crate::Error::CategoryConflict`] rather than recategorizing it everywhere it is used.
+    pub async fn get_or_create(
         tx: &mut Transaction<'_, Sqlite>,
-        name: &str,
-        discriminator: Option<&str>,
+        tag: &DiscriminatedTag,
     ) -> Result<Tag, crate::Error> {
-        Ok(
-            sqlx::query_as("INSERT INTO tags(name, discriminator) VALUES (?, ?) RETURNING *;")
-                .bind(name)
-                .bind(discriminator)
-                .fetch_one(&mut **tx)
-                .await?,
-        )
-    }
+        let mut resolved =
+            Self::get_discriminated_or_create(tx, &tag.name, tag.discriminator.as_deref()).await?;
 
-    pub async fn tag(
-        &self,
-        tx: &mut Transaction<'_, Sqlite>,
-        tag: &Self,
-    ) -> Result<bool, crate::Error> {
-        Ok(sqlx::query_as::<_, (i32,)>(
-            "INSERT OR IGNORE INTO meta_tags(tag, target) VALUES (?, ?) RETURNING 1;",
-        )
-        .bind(&tag.id)
-        .bind(&self.id)
-        .fetch_optional(&mut **tx)
-        .await?
-        .is_some())
+        if let Some(requested) = &tag.category {
+            match &resolved.category {
+                None => resolved.categorize(tx, Some(requested)).await?,
+                Some(existing) if !existing.eq_ignore_ascii_case(requested) => {
+                    return Err(crate::Error::CategoryConflict {
+                        tag: resolved.to_string(),
+                        existing: existing.clone(),
+                        requested: requested.clone(),
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    pub async fn categorize(
+        &mut self,
+        tx: &mut Transaction<'_, Sqlite>,
+        category: Option<&str>,
+    ) -> Result<(), crate::Error> {
+        sqlx::query("UPDATE tags SET category = ? WHERE id = ?;")
+            .bind(category)
+            .bind(self.id)
+            .execute(&mut **tx)
+            .await?;
+
+        self.category = category.map(String::from);
+
+        Ok(())
+    }
+
+    pub async fn describe(
+        &mut self,
+        tx: &mut Transaction<'_, Sqlite>,
+        description: Option<&str>,
+    ) -> Result<(), crate::Error> {
+        sqlx::query("UPDATE tags SET description = ? WHERE id = ?;")
+            .bind(description)
+            .bind(self.id)
+            .execute(&mut **tx)
+            .await?;
+
+        self.description = description.map(String::from);
+
+        Ok(())
+    }
+
+    /// Searches for tags whose name, alias or description contains the given text.
+    pub async fn search(
+        tx: &mut Transaction<'_, Sqlite>,
+        text: &str,
+    ) -> Result<Vec<Tag>, crate::Error> {
+        Ok(sqlx::query_as(
+            r#"
+            SELECT * FROM tags WHERE name LIKE '%' || ?1 || '%' ESCAPE '\' OR description LIKE '%' || ?1 || '%' ESCAPE '\'
+            UNION
+            SELECT tags.* FROM tags JOIN tag_aliases ON tags.id = tag_aliases.tag_id WHERE tag_aliases.name LIKE '%' || ?1 || '%' ESCAPE '\';
+        "#,
+        )
+        .bind(escape_like(text))
+        .fetch_all(&mut **tx)
+        .await?)
+    }
+
+    /// Lists every tag along with its work and implication counts, optionally only tags whose name
+    /// starts with `prefix`.
+    pub async fn list_with_counts(
+        tx: &mut Transaction<'_, Sqlite>,
+        prefix: Option<&str>,
+    ) -> Result<Vec<TagCount>, crate::Error> {
+        Ok(sqlx::query_as(&format!(
+            r"{TAG_COUNT_SELECT} WHERE name LIKE ? || '%' ESCAPE '\';"
+        ))
+        .bind(escape_like(prefix.unwrap_or_default()))
+        .fetch_all(&mut **tx)
+        .await?)
+    }
+
+    /// Lists every tag named `name` regardless of discriminator, along with its counts.
+    pub async fn family(
+        tx: &mut Transaction<'_, Sqlite>,
+        name: &str,
+    ) -> Result<Vec<TagCount>, crate::Error> {
+        Ok(sqlx::query_as(&format!(
+            "{TAG_COUNT_SELECT} WHERE name = ? ORDER BY discriminator IS NOT NULL, discriminator;"
+        ))
+        .bind(name)
+        .fetch_all(&mut **tx)
+        .await?)
+    }
+
+    /// Deletes every tag which is not applied to any work, is not part of any implication and is
+    /// not used by any rule or tag mapping, returning the deleted tags.
+    pub async fn prune(tx: &mut Transaction<'_, Sqlite>) -> Result<Vec<Tag>, crate::Error> {
+        Ok(sqlx::query_as(
+            r#"
+            DELETE FROM tags WHERE
+                id NOT IN (SELECT tag FROM work_tags)
+                AND id NOT IN (SELECT tag FROM meta_tags)
+                AND id NOT IN (SELECT target FROM meta_tags)
+                AND id NOT IN (SELECT tag FROM rule_tags)
+                AND id NOT IN (SELECT tag FROM tag_mappings WHERE tag IS NOT NULL)
+            RETURNING *;
+        "#,
+        )
+        .fetch_all(&mut **tx)
+        .await?)
+    }
+
+    /// Ranks tags which are likely to apply to `work`, based on the tags of works sharing its tags
+    /// or their ancestors, works by the same author and works from the same url. Tags already
+    /// applied to or implied by the work are never suggested.
+    pub async fn suggest_for_work(
+        tx: &mut Transaction<'_, Sqlite>,
+        work: &Work,
+        limit: u32,
+    ) -> Result<Vec<TagSuggestion>, crate::Error> {
+        Ok(sqlx::query_as(
+            r#"
+            WITH own(tag_id) AS (
+                SELECT tag_closure.ancestor FROM work_tags JOIN tag_closure ON work_tags.tag = tag_closure.descendant WHERE work_tags.work_id = ?1
+            ), related(work_id, weight) AS (
+                SELECT work_tags.work_id, COUNT(DISTINCT tag_closure.ancestor) * ?2 FROM work_tags JOIN tag_closure ON work_tags.tag = tag_closure.descendant
+                    WHERE tag_closure.ancestor IN own AND work_tags.work_id != ?1 GROUP BY work_tags.work_id
+                UNION ALL
+                SELECT DISTINCT shared.work_id, ?3 FROM work_authors AS credited
+                    JOIN work_authors AS shared ON credited.author_id = shared.author_id
+                    WHERE credited.work_id = ?1 AND shared.work_id != ?1
+                UNION ALL
+                SELECT work_id, ?4 FROM works WHERE url = ?5 AND work_id != ?1
+            ) SELECT tags.*, SUM(related.weight) AS score FROM related
+                JOIN work_tags ON work_tags.work_id = related.work_id
+                JOIN tags ON tags.id = work_tags.tag
+            WHERE tags.id NOT IN own
+            GROUP BY tags.id ORDER BY score DESC, tags.name LIMIT ?6;
+        "#,
+        )
+        .bind(work.work_id)
+        .bind(SHARED_TAG_WEIGHT)
+        .bind(AUTHOR_WEIGHT)
+        .bind(SOURCE_WEIGHT)
+        .bind(&work.url)
+        .bind(limit)
+        .fetch_all(&mut **tx)
+        .await?)
+    }
+
+    pub async fn create(
+        tx: &mut Transaction<'_, Sqlite>,
+        name: &str,
+        discriminator: Option<&str>,
+    ) -> Result<Tag, crate::Error> {
+        let tag: Tag =
+            sqlx::query_as("INSERT INTO tags(name, discriminator) VALUES (?, ?) RETURNING *;")
+                .bind(name)
+                .bind(discriminator)
+                .fetch_one(&mut **tx)
+                .await?;
+
+        sqlx::query("INSERT INTO tag_closure(ancestor, descendant, depth) VALUES (?1, ?1, 0);")
+            .bind(tag.id)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(tag)
+    }
+
+    pub async fn get_by_id(
+        tx: &mut Transaction<'_, Sqlite>,
+        id: TagId,
+    ) -> Result<Option<Tag>, crate::Error> {
+        Ok(sqlx::query_as("SELECT * FROM tags WHERE id = ?;")
+            .bind(id)
+            .fetch_optional(&mut **tx)
+            .await?)
+    }
+
+    /// Returns the implication path which would form a cycle if this tag were tagged with `tag`,
+    /// starting and ending with this tag.
+    pub async fn cycle_path(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        tag: &Self,
+    ) -> Result<Option<Vec<Tag>>, crate::Error> {
+        let reachable = sqlx::query_as::<_, (i32,)>(
+            "SELECT 1 FROM tag_closure WHERE ancestor = ? AND descendant = ?;",
+        )
+        .bind(self.id)
+        .bind(tag.id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .is_some();
+
+        if !reachable {
+            return Ok(None);
+        }
+
+        let edges: Vec<MetaTag> = sqlx::query_as(
+            r#"
+            SELECT meta_tags.* FROM meta_tags JOIN tag_closure ON meta_tags.tag = tag_closure.descendant WHERE tag_closure.ancestor = ?;
+        "#,
+        )
+        .bind(self.id)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        let Some(path) = find_path(&edges, self.id, tag.id) else {
+            return Ok(None);
+        };
+
+        let mut cycle = Vec::with_capacity(path.len() + 1);
+
+        for id in once(self.id).chain(path.into_iter().rev()) {
+            cycle.push(
+                Self::get_by_id(tx, id)
+                    .await?
+                    .ok_or(crate::Error::NotFound {
+                        kind: ModelKind::Tag,
+                    })?,
+            );
+        }
+
+        Ok(Some(cycle))
+    }
+
+    /// Tags this tag with `tag`, so that anything tagged with this tag is implied to be tagged with
+    /// `tag`.
+    pub async fn tag(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        tag: &Self,
+    ) -> Result<bool, crate::Error> {
+        if let Some(cycle) = self.cycle_path(tx, tag).await? {
+            return Err(crate::Error::TagCycle {
+                path: cycle.iter().map(Tag::to_string).collect(),
+            });
+        }
+
+        let inserted = sqlx::query_as::<_, (i32,)>(
+            "INSERT OR IGNORE INTO meta_tags(tag, target) VALUES (?, ?) RETURNING 1;",
+        )
+        .bind(tag.id)
+        .bind(self.id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .is_some();
+
+        if inserted {
+            sqlx::query(
+                r#"
+                INSERT INTO tag_closure(ancestor, descendant, depth)
+                SELECT a.ancestor, d.descendant, a.depth + d.depth + 1
+                FROM tag_closure a, tag_closure d
+                WHERE a.descendant = ? AND d.ancestor = ?
+                ON CONFLICT(ancestor, descendant) DO UPDATE SET depth = MIN(depth, excluded.depth);
+            "#,
+            )
+            .bind(tag.id)
+            .bind(self.id)
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(inserted)
+    }
+
+    /// Removes the implication from `tag` to this tag, returning whether it existed.
+    pub async fn untag(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        tag: &Self,
+    ) -> Result<bool, crate::Error> {
+        let removed = sqlx::query("DELETE FROM meta_tags WHERE tag = ? AND target = ?;")
+            .bind(tag.id)
+            .bind(self.id)
+            .execute(&mut **tx)
+            .await?
+            .rows_affected()
+            > 0;
+
+        if removed {
+            // Only paths from an ancestor of `tag` to a descendant of this tag could have used the
+            // removed implication, the rest of the closure stays as is.
+            sqlx::query(
+                r#"
+                DELETE FROM tag_closure
+                WHERE ancestor IN (SELECT ancestor FROM tag_closure WHERE descendant = ?1)
+                AND descendant IN (SELECT descendant FROM tag_closure WHERE ancestor = ?2);
+            "#,
+            )
+            .bind(tag.id)
+            .bind(self.id)
+            .execute(&mut **tx)
+            .await?;
+
+            // Any remaining path to a descendant of this tag enters them through some other
+            // implication, and the parts of the path before and after it are still in the closure.
+            sqlx::query(
+                r#"
+                INSERT INTO tag_closure(ancestor, descendant, depth)
+                SELECT up.ancestor, down.descendant, MIN(up.depth + 1 + down.depth)
+                FROM tag_closure up
+                JOIN meta_tags ON meta_tags.tag = up.descendant
+                JOIN tag_closure down ON down.ancestor = meta_tags.target
+                WHERE up.ancestor IN (SELECT ancestor FROM tag_closure WHERE descendant = ?1)
+                AND up.descendant NOT IN (SELECT descendant FROM tag_closure WHERE ancestor = ?2)
+                AND meta_tags.target IN (SELECT descendant FROM tag_closure WHERE ancestor = ?2)
+                GROUP BY up.ancestor, down.descendant;
+            "#,
+            )
+            .bind(tag.id)
+            .bind(self.id)
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(removed)
+    }
+
+    /// Recomputes the `tag_closure` table from `meta_tags`.
+    pub async fn rebuild_closure(tx: &mut Transaction<'_, Sqlite>) -> Result<(), crate::Error> {
+        sqlx::query(
+            r#"
+            DELETE FROM tag_closure;
+            INSERT INTO tag_closure(ancestor, descendant, depth)
+            WITH RECURSIVE closure(ancestor, descendant, depth) AS (
+                SELECT id, id, 0 FROM tags
+                UNION
+                SELECT closure.ancestor, meta_tags.target, closure.depth + 1 FROM meta_tags JOIN closure ON meta_tags.tag = closure.descendant
+            ) SELECT ancestor, descendant, MIN(depth) FROM closure GROUP BY ancestor, descendant;
+        "#,
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
     }
 
     pub async fn descendants(
@@ -376,11 +1187,7 @@ impl Tag {
     ) -> Result<Vec<DepthTag>, crate::Error> {
         Ok(sqlx::query_as(
             r#"
-            WITH RECURSIVE descendants(tag_id, depth) AS (
-                SELECT ?, 0
-                UNION
-                SELECT target, descendants.depth + 1 FROM meta_tags JOIN descendants ON meta_tags.tag = descendants.tag_id
-            ) SELECT tags.*, descendants.depth FROM tags JOIN descendants ON tags.id = descendants.tag_id;
+            SELECT tags.*, tag_closure.depth FROM tags JOIN tag_closure ON tags.id = tag_closure.descendant WHERE tag_closure.ancestor = ?;
         "#,
         )
         .bind(&self.id)
@@ -394,11 +1201,7 @@ impl Tag {
     ) -> Result<Vec<DepthTag>, crate::Error> {
         Ok(sqlx::query_as(
             r#"
-            WITH RECURSIVE ancestors(tag_id, depth) AS (
-                SELECT ?, 0
-                UNION
-                SELECT tag, ancestors.depth - 1 FROM meta_tags JOIN ancestors ON meta_tags.target = ancestors.tag_id
-            ) SELECT tags.*, ancestors.depth FROM tags JOIN ancestors ON tags.id = ancestors.tag_id;
+            SELECT tags.*, -tag_closure.depth AS depth FROM tags JOIN tag_closure ON tags.id = tag_closure.ancestor WHERE tag_closure.descendant = ?;
         "#,
         )
         .bind(&self.id)
@@ -406,3 +1209,624 @@ impl Tag {
         .await?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::{
+        author::{AuthorCredit, AuthorQuery},
+        models::{MappingAction, MetaTag, Rule, Tag, TagId, TagMapping, Work},
+        record::RecordDetails,
+        search::Query,
+        utils::{test_pool, test_work},
+    };
+
+    use super::{find_path, DiscriminatedTag, TagExpression};
+
+    fn edge(tag: i64, target: i64) -> MetaTag {
+        MetaTag {
+            tag: TagId(tag),
+            target: TagId(target),
+        }
+    }
+
+    #[test]
+    fn test_find_path() {
+        let edges = vec![edge(1, 11), edge(11, 21), edge(1, 2), edge(2, 21)];
+
+        assert_eq!(
+            find_path(&edges, TagId(1), TagId(21)),
+            Some(vec![TagId(1), TagId(11), TagId(21)])
+        );
+        assert_eq!(find_path(&edges, TagId(21), TagId(1)), None);
+        assert_eq!(find_path(&edges, TagId(11), TagId(1)), None);
+        assert_eq!(find_path(&edges, TagId(1), TagId(1)), Some(vec![TagId(1)]));
+    }
+
+    #[tokio::test]
+    async fn test_tag_cycle() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let mut tags = Vec::new();
+        for i in 1..=12 {
+            tags.push(Tag::create(&mut tx, &format!("t{i}"), None).await.unwrap());
+        }
+        let (t1, t2, t11) = (&tags[0], &tags[1], &tags[10]);
+        assert_eq!((t1.id, t11.id), (TagId(1), TagId(11)));
+
+        // Ids which share a textual prefix are not related.
+        assert!(t1.tag(&mut tx, t11).await.unwrap());
+        assert!(t11.tag(&mut tx, t2).await.unwrap());
+
+        match t2.tag(&mut tx, t1).await {
+            Err(crate::Error::TagCycle { path }) => {
+                assert_eq!(path, ["t2", "t1", "t11", "t2"])
+            }
+            other => panic!("expected a tag cycle, got {other:?}"),
+        }
+        assert!(t1.cycle_path(&mut tx, t2).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_category_conflict() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let tag = |s: &str| DiscriminatedTag::from_str(s).unwrap();
+
+        let marina = Tag::get_or_create(&mut tx, &tag("marina")).await.unwrap();
+        assert_eq!(marina.category, None);
+
+        let marina = Tag::get_or_create(&mut tx, &tag("series:marina"))
+            .await
+            .unwrap();
+        assert_eq!(marina.category.as_deref(), Some("series"));
+
+        assert!(matches!(
+            Tag::get_or_create(&mut tx, &tag("character:marina")).await,
+            Err(crate::Error::CategoryConflict { .. })
+        ));
+        assert_eq!(
+            Tag::get_or_create(&mut tx, &tag("marina"))
+                .await
+                .unwrap()
+                .category
+                .as_deref(),
+            Some("series")
+        );
+
+        let plan = TagExpression::from_str("character:marina/art")
+            .unwrap()
+            .plan(&mut tx)
+            .await
+            .unwrap();
+
+        assert_eq!(plan.category_conflicts.len(), 1);
+        assert_eq!(plan.category_conflicts[0].tag.id, marina.id);
+        assert_eq!(plan.category_conflicts[0].requested, "character");
+    }
+
+    #[tokio::test]
+    async fn test_list_prefix_wildcards() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        Tag::create(&mut tx, "a_b", None).await.unwrap();
+        Tag::create(&mut tx, "axb", None).await.unwrap();
+
+        let names = |tags: Vec<super::TagCount>| -> Vec<String> {
+            tags.into_iter().map(|count| count.tag.name).collect()
+        };
+
+        assert_eq!(
+            names(Tag::list_with_counts(&mut tx, Some("a_")).await.unwrap()),
+            vec!["a_b"]
+        );
+        assert_eq!(Tag::list_with_counts(&mut tx, None).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_search_wildcards() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        Tag::create(&mut tx, "100%", None).await.unwrap();
+        Tag::create(&mut tx, "1000", None).await.unwrap();
+
+        let found = Tag::search(&mut tx, "0%").await.unwrap();
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "100%");
+    }
+
+    async fn closure(tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>) -> Vec<(i64, i64, i64)> {
+        sqlx::query_as(
+            "SELECT ancestor, descendant, depth FROM tag_closure ORDER BY ancestor, descendant;",
+        )
+        .fetch_all(&mut **tx)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_untag_closure() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let mut tags = Vec::new();
+
+        for name in ["a", "b", "c", "d", "e", "f"] {
+            tags.push(Tag::create(&mut tx, name, None).await.unwrap());
+        }
+
+        let [a, b, c, d, e, f] = &tags[..] else {
+            unreachable!()
+        };
+
+        // a implied by b and c, which are both implied by d, which is implied by e. f is implied
+        // by e through d and directly.
+        for (child, parent) in [
+            (b, a),
+            (c, a),
+            (d, b),
+            (d, c),
+            (e, d),
+            (f, e),
+            (f, a),
+            (d, a),
+        ] {
+            child.tag(&mut tx, parent).await.unwrap();
+        }
+
+        for (child, parent) in [(d, a), (d, b), (f, a), (b, a), (d, c)] {
+            assert!(child.untag(&mut tx, parent).await.unwrap());
+
+            let incremental = closure(&mut tx).await;
+            Tag::rebuild_closure(&mut tx).await.unwrap();
+
+            assert_eq!(incremental, closure(&mut tx).await);
+        }
+
+        assert!(!d.untag(&mut tx, c).await.unwrap());
+    }
+
+    fn names(tags: &[Tag]) -> Vec<String> {
+        tags.iter().map(Tag::to_string).collect()
+    }
+
+    #[tokio::test]
+    async fn test_plan() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let details = RecordDetails {
+            tags: vec![DiscriminatedTag::from_str("b").unwrap()],
+            ..Default::default()
+        };
+        let work = test_work(&mut tx, "work", details).await;
+
+        let a = Tag::create(&mut tx, "a", None).await.unwrap();
+        let b = Tag::get_discriminated(&mut tx, "b", None).await.unwrap();
+        b.tag(&mut tx, &a).await.unwrap();
+
+        let before = closure(&mut tx).await;
+
+        let plan = TagExpression::from_str("<b>/(b,c)/a")
+            .unwrap()
+            .plan(&mut tx)
+            .await
+            .unwrap();
+
+        assert_eq!(names(&plan.created_tags), ["c"]);
+
+        let work_links: Vec<_> = plan
+            .work_links
+            .iter()
+            .map(|link| (link.from, link.to.to_string(), link.exists))
+            .collect();
+        assert_eq!(
+            work_links,
+            [
+                (work.work_id, String::from("b"), true),
+                (work.work_id, String::from("c"), false)
+            ]
+        );
+
+        let tag_links: Vec<_> = plan
+            .tag_links
+            .iter()
+            .map(|link| (link.from.to_string(), link.to.to_string(), link.exists))
+            .collect();
+        assert_eq!(
+            tag_links,
+            [
+                (String::from("b"), String::from("a"), true),
+                (String::from("c"), String::from("a"), false)
+            ]
+        );
+
+        assert!(plan.cycles.is_empty());
+        assert_eq!(plan.new_connections(), 2);
+
+        // Nothing the plan made is kept.
+        assert!(Tag::try_get_discriminated(&mut tx, "c", None)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(closure(&mut tx).await, before);
+    }
+
+    #[tokio::test]
+    async fn test_plan_conflicts() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let a = Tag::create(&mut tx, "a", None).await.unwrap();
+        let b = Tag::create(&mut tx, "b", None).await.unwrap();
+        b.tag(&mut tx, &a).await.unwrap();
+
+        let marina = Tag::create(&mut tx, "marina", None).await.unwrap();
+
+        let plan = TagExpression::from_str("a/(b,marina#2)")
+            .unwrap()
+            .plan(&mut tx)
+            .await
+            .unwrap();
+
+        assert_eq!(plan.cycles.len(), 1);
+        assert_eq!(names(&plan.cycles[0]), ["a", "b", "a"]);
+
+        assert_eq!(names(&plan.discriminator_conflicts), ["marina"]);
+        assert_eq!(names(&plan.created_tags), ["marina#2"]);
+        assert_eq!(
+            Tag::get_by_id(&mut tx, marina.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .discriminator,
+            None
+        );
+    }
+
+    async fn work_ids(tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, tag: &Tag) -> Vec<i64> {
+        sqlx::query_as::<_, (i64,)>("SELECT work_id FROM work_tags WHERE tag = ? ORDER BY work_id;")
+            .bind(tag.id)
+            .fetch_all(&mut **tx)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(work_id,)| work_id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_rediscriminate() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let first = Tag::create(&mut tx, "marina", Some("1")).await.unwrap();
+        let second = Tag::create(&mut tx, "marina", Some("2")).await.unwrap();
+
+        assert!(matches!(
+            first.rediscriminate(&mut tx, "2", false).await,
+            Err(crate::Error::Exists { .. })
+        ));
+        assert!(Tag::get_by_id(&mut tx, first.id).await.unwrap().is_some());
+
+        let renamed = first.rediscriminate(&mut tx, "3", false).await.unwrap();
+        assert_eq!(renamed.id, first.id);
+        assert_eq!(renamed.to_string(), "marina#3");
+
+        let merged = renamed.rediscriminate(&mut tx, "2", true).await.unwrap();
+        assert_eq!(merged.id, second.id);
+        assert!(Tag::get_by_id(&mut tx, first.id).await.unwrap().is_none());
+
+        let family = Tag::family(&mut tx, "marina").await.unwrap();
+        assert_eq!(family.len(), 1);
+        assert_eq!(family[0].tag.id, second.id);
+    }
+
+    #[tokio::test]
+    async fn test_undiscriminate() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let first = Tag::create(&mut tx, "marina", Some("1")).await.unwrap();
+        let second = Tag::create(&mut tx, "marina", Some("2")).await.unwrap();
+
+        match first.undiscriminate(&mut tx, false).await {
+            Err(crate::Error::Ambiguous { candidates, .. }) => {
+                assert_eq!(candidates, vec![second.id.0])
+            }
+            _ => panic!("expected the other marina to be ambiguous"),
+        }
+
+        let merged = first.undiscriminate(&mut tx, true).await.unwrap();
+        assert_eq!(merged.id, first.id);
+        assert_eq!(merged.discriminator, None);
+
+        let family = Tag::family(&mut tx, "marina").await.unwrap();
+        assert_eq!(names(&[family[0].tag.clone()]), ["marina"]);
+        assert_eq!(family.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_discriminate_automatically() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let mut marina = Tag::create(&mut tx, "marina", None).await.unwrap();
+        marina.discriminate_automatically(&mut tx).await.unwrap();
+        assert_eq!(marina.discriminator.as_deref(), Some("1"));
+
+        Tag::create(&mut tx, "marina", Some("3")).await.unwrap();
+
+        let mut other = Tag::create(&mut tx, "marina", Some("other")).await.unwrap();
+        other.discriminate_automatically(&mut tx).await.unwrap();
+        assert_eq!(other.discriminator.as_deref(), Some("2"));
+    }
+
+    #[tokio::test]
+    async fn test_merge_into() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let details = RecordDetails {
+            tags: vec![DiscriminatedTag::from_str("source").unwrap()],
+            ..Default::default()
+        };
+        let only_source = test_work(&mut tx, "only source", details.clone()).await;
+
+        let mut details = details;
+        details
+            .tags
+            .push(DiscriminatedTag::from_str("target").unwrap());
+        let both = test_work(&mut tx, "both", details).await;
+
+        let source = Tag::get_discriminated(&mut tx, "source", None)
+            .await
+            .unwrap();
+        let target = Tag::get_discriminated(&mut tx, "target", None)
+            .await
+            .unwrap();
+        let parent = Tag::create(&mut tx, "parent", None).await.unwrap();
+        source.tag(&mut tx, &parent).await.unwrap();
+
+        source
+            .add_alias(&mut tx, &DiscriminatedTag::from_str("src").unwrap())
+            .await
+            .unwrap();
+        let rule = Rule::create(&mut tx, "a", &[DiscriminatedTag::from(&source)])
+            .await
+            .unwrap();
+        TagMapping::set(
+            &mut tx,
+            "tumblr",
+            "Source",
+            MappingAction::Map,
+            Some(&source),
+        )
+        .await
+        .unwrap();
+
+        source.merge_into(&mut tx, &target).await.unwrap();
+
+        assert!(Tag::get_by_id(&mut tx, source.id).await.unwrap().is_none());
+        assert_eq!(
+            work_ids(&mut tx, &target).await,
+            [only_source.work_id.0, both.work_id.0]
+        );
+        assert_eq!(
+            Tag::get_discriminated(&mut tx, "src", None)
+                .await
+                .unwrap()
+                .id,
+            target.id
+        );
+        assert_eq!(names(&rule.tags(&mut tx).await.unwrap()), ["target"]);
+        assert_eq!(
+            TagMapping::get(&mut tx, "tumblr", "source")
+                .await
+                .unwrap()
+                .unwrap()
+                .tag,
+            Some(target.id)
+        );
+        let parents: Vec<Tag> = target
+            .ancestors(&mut tx)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|ancestor| ancestor.depth == -1)
+            .map(|ancestor| ancestor.tag)
+            .collect();
+        assert_eq!(names(&parents), ["parent"]);
+    }
+
+    #[tokio::test]
+    async fn test_merge_into_cycle() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let details = RecordDetails {
+            tags: vec![DiscriminatedTag::from_str("source").unwrap()],
+            ..Default::default()
+        };
+        test_work(&mut tx, "work", details).await;
+
+        let source = Tag::get_discriminated(&mut tx, "source", None)
+            .await
+            .unwrap();
+        let middle = Tag::create(&mut tx, "middle", None).await.unwrap();
+        let target = Tag::create(&mut tx, "target", None).await.unwrap();
+
+        // source implies middle, which implies target.
+        source.tag(&mut tx, &middle).await.unwrap();
+        middle.tag(&mut tx, &target).await.unwrap();
+
+        let before = closure(&mut tx).await;
+
+        // The merged tag would imply middle, which implies it.
+        assert!(matches!(
+            source.merge_into(&mut tx, &target).await,
+            Err(crate::Error::TagCycle { .. })
+        ));
+
+        assert!(Tag::get_by_id(&mut tx, source.id).await.unwrap().is_some());
+        assert_eq!(closure(&mut tx).await, before);
+        assert_eq!(work_ids(&mut tx, &source).await.len(), 1);
+        assert!(work_ids(&mut tx, &target).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_alias_never_creates() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let details = RecordDetails {
+            tags: vec![DiscriminatedTag::from_str("marina").unwrap()],
+            ..Default::default()
+        };
+        let work = test_work(&mut tx, "work", details).await;
+
+        let splatoon = Tag::create(&mut tx, "splatoon", Some("3")).await.unwrap();
+        splatoon
+            .add_alias(&mut tx, &DiscriminatedTag::from_str("spl").unwrap())
+            .await
+            .unwrap();
+
+        let resolved = Tag::get_or_create(&mut tx, &DiscriminatedTag::from_str("spl").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resolved.id, splatoon.id);
+
+        TagExpression::from_str("<marina>/spl/game")
+            .unwrap()
+            .execute(&mut tx)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            names(&work.tags(&mut tx).await.unwrap()),
+            ["marina", "splatoon#3"]
+        );
+        assert!(Tag::try_get_unaliased(&mut tx, "spl", None)
+            .await
+            .unwrap()
+            .is_none());
+
+        let found = Work::search(&mut tx, &Query::from_str("tag:spl").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            found.iter().map(|work| work.work_id).collect::<Vec<_>>(),
+            [work.work_id]
+        );
+
+        // The alias was resolved when linking the next level too.
+        let game = Tag::get_discriminated(&mut tx, "game", None).await.unwrap();
+        assert!(closure(&mut tx)
+            .await
+            .contains(&(game.id.0, splatoon.id.0, 1)));
+    }
+
+    #[tokio::test]
+    async fn test_suggest_for_work() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let details = |tags: &[&str], author: Option<&str>, url: Option<&str>| RecordDetails {
+            tags: tags
+                .iter()
+                .map(|tag| DiscriminatedTag::from_str(tag).unwrap())
+                .collect(),
+            authors: author
+                .map(|name| AuthorCredit::new(AuthorQuery::Name(name.to_string())))
+                .into_iter()
+                .collect(),
+            url: url.map(|url| url::Url::parse(url).unwrap()),
+            ..Default::default()
+        };
+
+        let work = test_work(
+            &mut tx,
+            "work",
+            details(
+                &["marina", "splatoon"],
+                Some("hazel"),
+                Some("https://example.com/1"),
+            ),
+        )
+        .await;
+        let splatoon = Tag::get_discriminated(&mut tx, "splatoon", None)
+            .await
+            .unwrap();
+        let game = Tag::create(&mut tx, "game", None).await.unwrap();
+        splatoon.tag(&mut tx, &game).await.unwrap();
+
+        test_work(
+            &mut tx,
+            "co-occurring",
+            details(&["marina", "splatoon", "octo"], None, None),
+        )
+        .await;
+        test_work(&mut tx, "implied", details(&["game", "misc"], None, None)).await;
+        test_work(&mut tx, "unrelated", details(&["unrelated"], None, None)).await;
+        test_work(
+            &mut tx,
+            "author",
+            details(&["inkling"], Some("hazel"), None),
+        )
+        .await;
+        test_work(
+            &mut tx,
+            "source",
+            details(&["pearl"], None, Some("https://example.com/1")),
+        )
+        .await;
+
+        let suggestions = Tag::suggest_for_work(&mut tx, &work, 10).await.unwrap();
+        assert_eq!(
+            suggestions
+                .iter()
+                .map(|suggestion| (suggestion.tag.name.as_str(), suggestion.score))
+                .collect::<Vec<_>>(),
+            [
+                ("pearl", 4.0),
+                ("octo", 3.0),
+                ("inkling", 2.0),
+                ("misc", 1.0)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_term_kind_category() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let details = RecordDetails {
+            tags: vec![DiscriminatedTag::from_str("artist:hazel").unwrap()],
+            ..Default::default()
+        };
+        let work = test_work(&mut tx, "work", details).await;
+
+        let tagged = Work::search(&mut tx, &Query::from_str("tag:artist:hazel").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            tagged.iter().map(|work| work.work_id).collect::<Vec<_>>(),
+            [work.work_id]
+        );
+
+        // Without the `tag:` prefix the category is read as the `artist:` term.
+        assert!(
+            Work::search(&mut tx, &Query::from_str("artist:hazel").unwrap())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
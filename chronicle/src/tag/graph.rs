@@ -0,0 +1,155 @@
+use std::{collections::HashSet, fmt::Write};
+
+use serde::Serialize;
+use sqlx::{Sqlite, Transaction};
+
+use crate::models::{MetaTag, Tag, TagId};
+
+#[derive(sqlx::FromRow, Serialize)]
+pub struct TagNode {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub tag: Tag,
+    /// The number of works tagged with this tag directly
+    pub works: i64,
+}
+
+#[derive(Serialize)]
+pub struct TagEdge {
+    pub parent: TagId,
+    pub child: TagId,
+}
+
+/// A snapshot of the tag implication graph, edges point from a parent tag down to each child tag
+/// it was applied to, so a work tagged with the child is implied to have the parent.
+#[derive(Serialize)]
+pub struct TagGraph {
+    pub nodes: Vec<TagNode>,
+    pub edges: Vec<TagEdge>,
+}
+
+impl TagGraph {
+    /// Loads the graph below `root`, or the whole graph if no root is given, stopping after
+    /// `depth` levels if provided.
+    pub async fn load(
+        tx: &mut Transaction<'_, Sqlite>,
+        root: Option<&Tag>,
+        depth: Option<u32>,
+    ) -> Result<Self, crate::Error> {
+        let root = root.map(|tag| tag.id);
+
+        let nodes: Vec<TagNode> = sqlx::query_as(
+            r#"
+            SELECT tags.*, (SELECT COUNT(*) FROM work_tags WHERE work_tags.tag = tags.id) AS works FROM tags WHERE id IN (
+                SELECT descendant FROM tag_closure
+                WHERE ancestor IN (SELECT id FROM tags WHERE (?1 IS NULL AND id NOT IN (SELECT target FROM meta_tags)) OR id = ?1)
+                GROUP BY descendant HAVING ?2 IS NULL OR MIN(depth) <= ?2
+            );
+        "#,
+        )
+        .bind(root)
+        .bind(depth)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        let ids: HashSet<TagId> = nodes.iter().map(|node| node.tag.id).collect();
+
+        let edges = sqlx::query_as::<_, MetaTag>("SELECT * FROM meta_tags;")
+            .fetch_all(&mut **tx)
+            .await?
+            .into_iter()
+            .filter(|edge| ids.contains(&edge.tag) && ids.contains(&edge.target))
+            .map(|edge| TagEdge {
+                parent: edge.tag,
+                child: edge.target,
+            })
+            .collect();
+
+        Ok(Self { nodes, edges })
+    }
+
+    /// Renders the graph in the Graphviz DOT language.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph tags {\n");
+
+        for node in &self.nodes {
+            let label = format!("{}\\n({})", node.tag, node.works).replace('"', "\\\"");
+
+            writeln!(dot, "    t{} [label=\"{label}\"];", node.tag.id).unwrap();
+        }
+
+        for edge in &self.edges {
+            writeln!(dot, "    t{} -> t{};", edge.parent, edge.child).unwrap();
+        }
+
+        dot.push_str("}\n");
+
+        dot
+    }
+
+    /// Renders the graph as a Mermaid flowchart.
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("graph TD\n");
+
+        for node in &self.nodes {
+            let label = format!("{} ({})", node.tag, node.works)
+                .replace('#', "#35;")
+                .replace('"', "#quot;");
+
+            writeln!(mermaid, "    t{}[\"{label}\"]", node.tag.id).unwrap();
+        }
+
+        for edge in &self.edges {
+            writeln!(mermaid, "    t{} --> t{}", edge.parent, edge.child).unwrap();
+        }
+
+        mermaid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{Tag, TagId};
+
+    use super::{TagEdge, TagGraph, TagNode};
+
+    fn graph() -> TagGraph {
+        let node = |id, name: &str, discriminator: Option<&str>, works| TagNode {
+            tag: Tag {
+                name: name.to_owned(),
+                discriminator: discriminator.map(String::from),
+                id: TagId(id),
+                category: None,
+                description: None,
+            },
+            works,
+        };
+
+        TagGraph {
+            nodes: vec![
+                node(1, "oc", Some("hazel"), 3),
+                node(2, "character", None, 0),
+            ],
+            edges: vec![TagEdge {
+                parent: TagId(2),
+                child: TagId(1),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_dot() {
+        assert_eq!(
+            graph().to_dot(),
+            "digraph tags {\n    t1 [label=\"oc#hazel\\n(3)\"];\n    t2 [label=\"character\\n(0)\"];\n    t2 -> t1;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_mermaid() {
+        assert_eq!(
+            graph().to_mermaid(),
+            "graph TD\n    t1[\"oc#35;hazel (3)\"]\n    t2[\"character (0)\"]\n    t2 --> t1\n"
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use sqlx::{Sqlite, Transaction};
+
+use crate::models::{MappingAction, Tag, TagMapping};
+
+use super::DiscriminatedTag;
+
+/// Converts a tag as written on a service into a valid tag name, returning `None` if it contains
+/// any character which can not appear in a tag name.
+///
+/// Whitespace becomes `_`. Tags are rejected rather than having characters dropped, as what would
+/// remain of a tag such as `café` is an unrelated tag.
+pub fn normalize_native_tag(source: &str) -> Option<String> {
+    let mut name = String::with_capacity(source.len());
+
+    for c in source.trim().trim_start_matches('#').chars() {
+        if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+            name.push(c.to_ascii_lowercase());
+        } else if c.is_whitespace() || c == '_' {
+            if !name.is_empty() && !name.ends_with('_') {
+                name.push('_');
+            }
+        } else {
+            return None;
+        }
+    }
+
+    let name = name
+        .trim_start_matches(|c: char| !c.is_ascii_alphanumeric())
+        .trim_end_matches('_');
+
+    (!name.is_empty()).then(|| name.to_owned())
+}
+
+fn source_key(source: &str) -> &str {
+    source.trim().trim_start_matches('#')
+}
+
+impl TagMapping {
+    /// Sets how `source` tags from `service` are imported, `tag` must be provided when mapping.
+    pub async fn set(
+        tx: &mut Transaction<'_, Sqlite>,
+        service: &str,
+        source: &str,
+        action: MappingAction,
+        tag: Option<&Tag>,
+    ) -> Result<TagMapping, crate::Error> {
+        Ok(sqlx::query_as(
+            r#"
+            INSERT INTO tag_mappings(service, source, action, tag) VALUES (?, ?, ?, ?)
+            ON CONFLICT(service, source) DO UPDATE SET action = excluded.action, tag = excluded.tag
+            RETURNING *;
+        "#,
+        )
+        .bind(service)
+        .bind(source_key(source))
+        .bind(action)
+        .bind(tag.map(|tag| tag.id))
+        .fetch_one(&mut **tx)
+        .await?)
+    }
+
+    /// Removes the mapping for `source` tags from `service`, returning whether it existed.
+    pub async fn remove(
+        tx: &mut Transaction<'_, Sqlite>,
+        service: &str,
+        source: &str,
+    ) -> Result<bool, crate::Error> {
+        Ok(
+            sqlx::query("DELETE FROM tag_mappings WHERE service = ? AND source = ?;")
+                .bind(service)
+                .bind(source_key(source))
+                .execute(&mut **tx)
+                .await?
+                .rows_affected()
+                > 0,
+        )
+    }
+
+    pub async fn list(
+        tx: &mut Transaction<'_, Sqlite>,
+        service: Option<&str>,
+    ) -> Result<Vec<TagMapping>, crate::Error> {
+        Ok(sqlx::query_as(
+            "SELECT * FROM tag_mappings WHERE ?1 IS NULL OR service = ?1 ORDER BY service, source;",
+        )
+        .bind(service)
+        .fetch_all(&mut **tx)
+        .await?)
+    }
+
+    pub async fn get(
+        tx: &mut Transaction<'_, Sqlite>,
+        service: &str,
+        source: &str,
+    ) -> Result<Option<TagMapping>, crate::Error> {
+        Ok(
+            sqlx::query_as("SELECT * FROM tag_mappings WHERE service = ? AND source = ?;")
+                .bind(service)
+                .bind(source_key(source))
+                .fetch_optional(&mut **tx)
+                .await?,
+        )
+    }
+
+    /// Translates tags from `service` into the tags they should be imported as.
+    ///
+    /// Unmapped tags are passed through after being normalized.
+    pub async fn resolve(
+        tx: &mut Transaction<'_, Sqlite>,
+        service: &str,
+        sources: &[String],
+    ) -> Result<Vec<DiscriminatedTag>, crate::Error> {
+        let mut tags: Vec<DiscriminatedTag> = Vec::with_capacity(sources.len());
+
+        for source in sources {
+            let mapping = Self::get(tx, service, source).await?;
+
+            let tag = match mapping {
+                Some(TagMapping {
+                    action: MappingAction::Ignore,
+                    ..
+                }) => None,
+                Some(TagMapping {
+                    action: MappingAction::Map,
+                    tag: Some(tag),
+                    ..
+                }) => Tag::get_by_id(tx, tag)
+                    .await?
+                    .map(|tag| DiscriminatedTag::from(&tag)),
+                _ => normalize_native_tag(source).map(|name| DiscriminatedTag {
+                    category: None,
+                    name,
+                    discriminator: None,
+                }),
+            };
+
+            if let Some(tag) = tag {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+        }
+
+        Ok(tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::{
+        models::{MappingAction, Tag, TagMapping},
+        tag::DiscriminatedTag,
+        utils::test_pool,
+    };
+
+    use super::normalize_native_tag;
+
+    #[test]
+    fn test_normalize_native_tag() {
+        assert_eq!(
+            normalize_native_tag("Splatoon 3"),
+            Some("splatoon_3".into())
+        );
+        assert_eq!(normalize_native_tag("#art"), Some("art".into()));
+        assert_eq!(
+            normalize_native_tag("  my  oc   art  "),
+            Some("my_oc_art".into())
+        );
+        assert_eq!(normalize_native_tag("my oc's art!"), None);
+        assert_eq!(
+            normalize_native_tag("__v1.2-beta"),
+            Some("v1.2-beta".into())
+        );
+        assert_eq!(normalize_native_tag("日本"), None);
+        assert_eq!(normalize_native_tag("#"), None);
+    }
+
+    #[test]
+    fn test_normalize_mixed_script() {
+        assert_eq!(normalize_native_tag("café"), None);
+        assert_eq!(normalize_native_tag("スプラトゥーン3"), None);
+        assert_eq!(normalize_native_tag("オリジナル1000users入り"), None);
+        assert_eq!(normalize_native_tag("#splatoon3 ファンアート"), None);
+        assert_eq!(normalize_native_tag("Ｓplatoon"), None);
+    }
+
+    fn sources(sources: &[&str]) -> Vec<String> {
+        sources.iter().map(|source| source.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_resolve() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let splatoon = Tag::create(&mut tx, "splatoon", Some("3")).await.unwrap();
+
+        TagMapping::set(
+            &mut tx,
+            "tumblr",
+            "Splatoon 3",
+            MappingAction::Map,
+            Some(&splatoon),
+        )
+        .await
+        .unwrap();
+        TagMapping::set(&mut tx, "tumblr", "#wip", MappingAction::Ignore, None)
+            .await
+            .unwrap();
+        TagMapping::set(&mut tx, "tumblr", "My OC", MappingAction::Pass, None)
+            .await
+            .unwrap();
+
+        let resolved = TagMapping::resolve(
+            &mut tx,
+            "tumblr",
+            &sources(&[
+                "#SPLATOON 3",
+                "splatoon 3",
+                "WIP",
+                "#my oc",
+                "marina",
+                "café",
+            ]),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            resolved,
+            vec![
+                DiscriminatedTag::from_str("splatoon#3").unwrap(),
+                DiscriminatedTag::from_str("my_oc").unwrap(),
+                DiscriminatedTag::from_str("marina").unwrap(),
+            ]
+        );
+
+        // Mappings only apply to the service they were made for.
+        assert_eq!(
+            TagMapping::resolve(&mut tx, "pixiv", &sources(&["wip"]))
+                .await
+                .unwrap(),
+            vec![DiscriminatedTag::from_str("wip").unwrap()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prune_keeps_mapped_tags() {
+        let pool = test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+
+        let splatoon = Tag::create(&mut tx, "splatoon", Some("3")).await.unwrap();
+
+        TagMapping::set(
+            &mut tx,
+            "tumblr",
+            "splatoon 3",
+            MappingAction::Map,
+            Some(&splatoon),
+        )
+        .await
+        .unwrap();
+
+        assert!(Tag::prune(&mut tx).await.unwrap().is_empty());
+
+        let mapping = TagMapping::get(&mut tx, "tumblr", "splatoon 3")
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(mapping.tag, Some(splatoon.id));
+    }
+}
@@ -1,9 +1,9 @@
 use nom::{
     branch::alt,
     character::complete::char,
-    combinator::map,
+    combinator::{map, opt},
     multi::separated_list1,
-    sequence::{delimited, preceded, separated_pair},
+    sequence::{delimited, pair, preceded, separated_pair, terminated},
 };
 
 use crate::{
@@ -14,6 +14,7 @@ use crate::{
 use super::TagExpression;
 
 pub struct ParsedTag<'s> {
+    pub category: Option<&'s str>,
     pub name: &'s str,
     pub discriminator: Option<&'s str>,
 }
@@ -22,20 +23,28 @@ pub fn tag_part(i: &str) -> ParseResult<&str> {
     identifier(i)
 }
 
+pub fn tag_category(i: &str) -> ParseResult<'_, &str> {
+    terminated(tag_part, char(':'))(i)
+}
+
 pub fn discriminated_tag(i: &str) -> ParseResult<ParsedTag> {
-    alt((
-        map(
-            separated_pair(tag_part, char('#'), tag_part),
-            |(name, discriminator)| ParsedTag {
-                name,
-                discriminator: Some(discriminator),
-            },
+    map(
+        pair(
+            opt(tag_category),
+            alt((
+                map(
+                    separated_pair(tag_part, char('#'), tag_part),
+                    |(name, discriminator)| (name, Some(discriminator)),
+                ),
+                map(tag_part, |name| (name, None)),
+            )),
         ),
-        map(tag_part, |name| ParsedTag {
+        |(category, (name, discriminator))| ParsedTag {
+            category,
             name,
-            discriminator: None,
-        }),
-    ))(i)
+            discriminator,
+        },
+    )(i)
 }
 
 fn tag_sequence(i: &str) -> ParseResult<Vec<ParsedTag>> {
@@ -69,7 +78,7 @@ pub fn tag_expression(i: &str) -> ParseResult<TagExpression> {
 mod test {
     use crate::{
         search::{Query, QueryTerm},
-        tag::{parse::tag_expression, TagExpression},
+        tag::{parse::tag_expression, DiscriminatedTag, TagExpression},
     };
 
     #[test]
@@ -103,4 +112,29 @@ mod test {
             tag_expression("<hello>/world")
         )
     }
+
+    #[test]
+    fn test_category() {
+        assert_eq!(
+            Ok((
+                "",
+                TagExpression {
+                    query: None,
+                    hierarchy: vec![
+                        vec![DiscriminatedTag {
+                            category: Some(String::from("character")),
+                            name: String::from("marina"),
+                            discriminator: None,
+                        }],
+                        vec![DiscriminatedTag {
+                            category: Some(String::from("series")),
+                            name: String::from("splatoon"),
+                            discriminator: Some(String::from("2")),
+                        }]
+                    ]
+                }
+            )),
+            tag_expression("character:marina/series:splatoon#2")
+        )
+    }
 }
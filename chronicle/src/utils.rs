@@ -11,3 +11,49 @@ pub fn hash_t(t: &impl Hash) -> u32 {
 pub fn hash_t_hex(t: &impl Hash) -> String {
     format!("{hash:X}", hash = hash_t(t))
 }
+
+/// Opens an in-memory database with every migration applied, for tests which need one.
+#[cfg(test)]
+pub(crate) async fn test_pool() -> sqlx::SqlitePool {
+    // Every connection to `sqlite::memory:` opens a separate database.
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::migrate!().run(&pool).await.unwrap();
+
+    pool
+}
+
+/// A record of an empty file at `path` with the given details. The hash is derived from the path
+/// so that every test record is distinct.
+#[cfg(test)]
+pub(crate) fn test_record(
+    path: &str,
+    details: crate::record::RecordDetails,
+) -> crate::record::Record {
+    crate::record::Record {
+        path: std::path::PathBuf::from(path),
+        size: 0,
+        hash: bytemuck::cast(crc32fast::hash(path.as_bytes())),
+        details,
+    }
+}
+
+/// Creates a work from [`test_record`], creating any authors it credits.
+#[cfg(test)]
+pub(crate) async fn test_work(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    path: &str,
+    details: crate::record::RecordDetails,
+) -> crate::models::Work {
+    crate::models::Work::create_from_record(
+        tx,
+        &test_record(path, details),
+        &crate::author::AuthorPolicy::Create,
+    )
+    .await
+    .unwrap()
+}